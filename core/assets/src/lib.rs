@@ -50,7 +50,7 @@ impl Listener<AssetsEvent> for AssetsModule {
         match event {
             AssetsEvent::Load { value } => {
                 let v = value.take();
-                self.loaded.insert((&*v).type_id(), v);
+                self.loaded.insert((*v).type_id(), v);
             }
             AssetsEvent::Reset => self.loaders.clear(),
         }
@@ -6,8 +6,8 @@ use rgine_modules::{
 use rgine_platform::window::{WindowPlatformConfig, WindowPlatformEngineExt};
 use wgpu::*;
 
-fn main() {
-    Engine::new::<Example>().run_windowed(WindowPlatformConfig::default());
+fn main() -> AnyResult<()> {
+    Engine::new::<Example>().run_windowed(WindowPlatformConfig::default())
 }
 
 pub struct Example {
@@ -6,20 +6,20 @@ pub struct Color3 {
     pub b: f32,
 }
 
-impl Into<wgpu::Color> for Color3 {
-    fn into(self) -> wgpu::Color {
+impl From<Color3> for wgpu::Color {
+    fn from(c: Color3) -> Self {
         wgpu::Color {
-            r: self.r as f64,
-            g: self.g as f64,
-            b: self.b as f64,
+            r: c.r as f64,
+            g: c.g as f64,
+            b: c.b as f64,
             a: 1.0,
         }
     }
 }
 
-impl Into<[f32; 3]> for Color3 {
-    fn into(self) -> [f32; 3] {
-        [self.r, self.g, self.b]
+impl From<Color3> for [f32; 3] {
+    fn from(c: Color3) -> Self {
+        [c.r, c.g, c.b]
     }
 }
 
@@ -34,4 +34,13 @@ impl Color3 {
     pub const fn gray(l: f32) -> Self {
         Self::rgb(l, l, l)
     }
+
+    /// Linear interpolation from `self` (`t = 0`) to `other` (`t = 1`)
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        Self::rgb(
+            self.r + (other.r - self.r) * t,
+            self.g + (other.g - self.g) * t,
+            self.b + (other.b - self.b) * t,
+        )
+    }
 }
@@ -1,112 +1,364 @@
+use rgine_logger::{info, warn};
 use rgine_platform::window::Window;
 use wgpu::*;
 
-//TODO: add real logging
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+pub use rgine_platform::window::PresentMode as WindowPresentMode;
+pub use wgpu::{Backends, PowerPreference};
 
 pub struct GraphicsCtx {
+    pub adapter: Adapter,
     pub device: Device,
     pub queue: Queue,
-    pub surface: Surface<'static>,
+    /// `None` in headless mode, frames are then rendered to an offscreen texture
+    pub surface: Option<Surface<'static>>,
     pub surface_texture_format: TextureFormat,
     pub surface_capabilities: SurfaceCapabilities,
+    present_mode: PresentMode,
+    surface_size: (u32, u32),
+    device_lost: Arc<AtomicBool>,
+    offscreen: Option<Arc<Texture>>,
+    instance: Instance,
+    /// Whether the surface was dropped because the application was suspended
+    suspended: bool,
+}
+
+/// Settings of the graphics context creation
+#[derive(Clone, Debug)]
+pub struct GraphicsConfig {
+    /// Backends allowed to be used, the `WGPU_BACKEND` environment variable overrides it
+    pub backends: Backends,
+    pub power_preference: PowerPreference,
+    /// Only uses adapters whose name contains this, ignoring case.
+    /// Falls back to the default adapter if none matches.
+    pub adapter_name: Option<String>,
+}
+
+impl Default for GraphicsConfig {
+    fn default() -> Self {
+        Self {
+            backends: Backends::all(),
+            power_preference: PowerPreference::HighPerformance,
+            adapter_name: None,
+        }
+    }
 }
 
 pub struct Frame {
     pub view: TextureView,
-    pub surface_texture: SurfaceTexture,
+    source: FrameSource,
 }
 
+enum FrameSource {
+    Surface(SurfaceTexture),
+    Offscreen(Arc<Texture>),
+}
+
+/// Format of the offscreen texture frames are rendered to in headless mode
+const OFFSCREEN_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
 impl GraphicsCtx {
-    pub(crate) fn new(window: Arc<Window>) -> Self {
-        let window_size = window.inner_size().into();
+    /// Creates a context rendering to `window`, or headless rendering to an offscreen texture
+    /// of `size` pixels if `None`.
+    pub(crate) fn new(
+        window: Option<Arc<Window>>,
+        size: (u32, u32),
+        present_mode: WindowPresentMode,
+        config: &GraphicsConfig,
+    ) -> Self {
+        let backends = util::backend_bits_from_env().unwrap_or(config.backends);
         let instance = Instance::new(InstanceDescriptor {
-            backends: util::backend_bits_from_env().unwrap_or(Backends::all()),
+            backends,
             ..Default::default()
         });
-        let surface = instance
-            .create_surface(window)
-            .unwrap_or_else(|e| panic!("Could not create graphics surface: {e}"));
-        let adapter = pollster::block_on(instance.request_adapter(&RequestAdapterOptions {
-            power_preference: PowerPreference::HighPerformance,
-            compatible_surface: Some(&surface),
-            force_fallback_adapter: false,
-        }))
-        .unwrap();
+        let surface = window.map(|window| {
+            instance
+                .create_surface(window)
+                .unwrap_or_else(|e| panic!("Could not create graphics surface: {e}"))
+        });
+        let adapter = select_adapter(&instance, backends, surface.as_ref(), config);
+        let adapter_info = adapter.get_info();
+        info!(
+            "Using graphics adapter {} ({:?})",
+            adapter_info.name, adapter_info.backend
+        );
         let (device, queue) = pollster::block_on(adapter.request_device(
             &DeviceDescriptor {
                 label: None,
-                required_features: Features::empty(),
-                required_limits: Limits::default(),
+                // Optional features, only enabled when supported
+                required_features: adapter.features()
+                    & (Features::TIMESTAMP_QUERY | Features::POLYGON_MODE_LINE),
+                // Allows textures as large as the adapter supports
+                required_limits: Limits::default().using_resolution(adapter.limits()),
             },
             None,
         ))
         .unwrap_or_else(|e| panic!("Could not acquire graphics device: {e}"));
+        info!(
+            "Graphics device features: {:?}, max texture size: {}",
+            device.features(),
+            device.limits().max_texture_dimension_2d
+        );
 
-        let surface_capabilities = surface.get_capabilities(&adapter);
-        let surface_texture_format = surface_capabilities
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_capabilities.formats[0]);
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let lost = device_lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // The callback is also called when the device is dropped or the callback replaced
+            if !matches!(
+                reason,
+                DeviceLostReason::Dropped | DeviceLostReason::ReplacedCallback
+            ) {
+                warn!("Graphics device lost ({reason:?}): {message}");
+                lost.store(true, Ordering::Relaxed);
+            }
+        });
+
+        let (surface_capabilities, surface_texture_format) = match &surface {
+            Some(surface) => {
+                let capabilities = surface.get_capabilities(&adapter);
+                let format = capabilities
+                    .formats
+                    .iter()
+                    .copied()
+                    .find(|f| f.is_srgb())
+                    .unwrap_or(capabilities.formats[0]);
+                (capabilities, format)
+            }
+            None => (SurfaceCapabilities::default(), OFFSCREEN_FORMAT),
+        };
 
         let mut _self = Self {
+            adapter,
             device,
             queue,
             surface,
             surface_capabilities,
             surface_texture_format,
+            present_mode: PresentMode::Fifo,
+            surface_size: size,
+            device_lost,
+            offscreen: None,
+            instance,
+            suspended: false,
         };
 
-        _self.resize(window_size);
+        _self.set_present_mode(present_mode);
 
         _self
     }
 
+    /// Changes how frames are synchronized with the display, falling back to
+    /// [`WindowPresentMode::Fifo`] if `mode` is unsupported by the surface.
+    pub fn set_present_mode(&mut self, mode: WindowPresentMode) {
+        let requested = match mode {
+            WindowPresentMode::Fifo => PresentMode::Fifo,
+            WindowPresentMode::Mailbox => PresentMode::Mailbox,
+            WindowPresentMode::Immediate => PresentMode::Immediate,
+        };
+        self.present_mode = if self.surface.is_none()
+            || self.surface_capabilities.present_modes.contains(&requested)
+        {
+            requested
+        } else {
+            warn!("Present mode {requested:?} is not supported, falling back to Fifo!");
+            PresentMode::Fifo
+        };
+        self.resize(self.surface_size);
+    }
+
+    /// Optional features enabled on the device
+    pub fn features(&self) -> Features {
+        self.device.features()
+    }
+
+    /// Limits of the device, which may be lower than the adapter ones
+    pub fn limits(&self) -> Limits {
+        self.device.limits()
+    }
+
+    /// Largest width and height of the textures the device can create
+    pub fn max_texture_size(&self) -> u32 {
+        self.device.limits().max_texture_dimension_2d
+    }
+
+    /// Runs `f`, returning the first validation or out of memory error raised by the device
+    /// meanwhile instead of panicking on it.
+    pub fn catch_errors<R>(&self, f: impl FnOnce() -> R) -> Result<R, Error> {
+        self.device.push_error_scope(ErrorFilter::OutOfMemory);
+        self.device.push_error_scope(ErrorFilter::Validation);
+        let result = f();
+        let validation = pollster::block_on(self.device.pop_error_scope());
+        let out_of_memory = pollster::block_on(self.device.pop_error_scope());
+        match validation.or(out_of_memory) {
+            Some(error) => Err(error),
+            None => Ok(result),
+        }
+    }
+
+    /// Whether the device was lost, the context must then be recreated
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed)
+    }
+
     pub(crate) fn resize(&mut self, window_size: (u32, u32)) {
-        if window_size.0 > 0 && window_size.1 > 0 {
-            self.surface.configure(
-                &self.device,
-                &SurfaceConfiguration {
-                    usage: TextureUsages::RENDER_ATTACHMENT,
-                    format: self.surface_texture_format,
-                    width: window_size.0,
-                    height: window_size.1,
-                    present_mode: self.surface_capabilities.present_modes[0],
-                    alpha_mode: self.surface_capabilities.alpha_modes[0],
-                    view_formats: vec![],
-                    desired_maximum_frame_latency: 2,
-                },
-            );
+        self.surface_size = window_size;
+        self.configure_surface();
+    }
+
+    /// Whether frames are rendered to an offscreen texture instead of a window
+    pub fn is_headless(&self) -> bool {
+        self.surface.is_none() && !self.suspended
+    }
+
+    /// Drops the surface, which some platforms invalidate while the application is suspended
+    pub(crate) fn suspend(&mut self) {
+        if self.surface.take().is_some() {
+            self.suspended = true;
         }
     }
 
-    pub(crate) fn next_frame(&self) -> Option<Frame> {
-        let surface_texture = self
-            .surface
-            .get_current_texture()
-            .map_err(|e| match e {
-                SurfaceError::OutOfMemory => {
-                    panic!("The system is out of memory for rendering!")
-                }
-                _ => format!("An error occured during surface texture acquisition: {e}"),
-            })
-            .ok()?;
+    /// Creates the surface dropped by [`GraphicsCtx::suspend`] again for `window`
+    pub(crate) fn resume(&mut self, window: Arc<Window>) {
+        if !self.suspended {
+            return;
+        }
+        self.surface = Some(
+            self.instance
+                .create_surface(window)
+                .unwrap_or_else(|e| panic!("Could not create graphics surface: {e}")),
+        );
+        self.suspended = false;
+        self.configure_surface();
+    }
+
+    fn configure_surface(&mut self) {
+        let (width, height) = self.surface_size;
+        if width == 0 || height == 0 || self.suspended {
+            return;
+        }
+        let Some(surface) = &self.surface else {
+            self.offscreen = Some(Arc::new(self.device.create_texture(&TextureDescriptor {
+                label: Some("Offscreen frame texture"),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: OFFSCREEN_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT
+                    | TextureUsages::COPY_SRC
+                    | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })));
+            return;
+        };
+        surface.configure(
+            &self.device,
+            &SurfaceConfiguration {
+                // Copying from the surface allows reading back frames
+                usage: TextureUsages::RENDER_ATTACHMENT
+                    | (self.surface_capabilities.usages & TextureUsages::COPY_SRC),
+                format: self.surface_texture_format,
+                width,
+                height,
+                present_mode: self.present_mode,
+                alpha_mode: self.surface_capabilities.alpha_modes[0],
+                view_formats: vec![],
+                desired_maximum_frame_latency: 2,
+            },
+        );
+    }
+
+    pub(crate) fn next_frame(&mut self) -> Option<Frame> {
+        if self.suspended {
+            return None;
+        }
+        let Some(surface) = &self.surface else {
+            let texture = self.offscreen.clone()?;
+            return Some(Frame {
+                view: texture.create_view(&TextureViewDescriptor::default()),
+                source: FrameSource::Offscreen(texture),
+            });
+        };
+        let surface_texture = match surface.get_current_texture() {
+            Ok(surface_texture) => surface_texture,
+            // The surface needs to be configured again, after the system resumed from sleep for example
+            Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+                self.configure_surface();
+                self.surface
+                    .as_ref()
+                    .unwrap()
+                    .get_current_texture()
+                    .map_err(|e| warn!("Unable to acquire the surface texture again: {e}"))
+                    .ok()?
+            }
+            Err(SurfaceError::OutOfMemory) => {
+                panic!("The system is out of memory for rendering!")
+            }
+            Err(e) => {
+                warn!("An error occured during surface texture acquisition: {e}");
+                return None;
+            }
+        };
 
         let view = surface_texture
             .texture
             .create_view(&TextureViewDescriptor::default());
 
         Some(Frame {
-            surface_texture,
             view,
+            source: FrameSource::Surface(surface_texture),
         })
     }
 }
 
 impl Frame {
+    /// Texture rendered to, the surface texture or the offscreen texture in headless mode
+    pub fn texture(&self) -> &Texture {
+        match &self.source {
+            FrameSource::Surface(surface_texture) => &surface_texture.texture,
+            FrameSource::Offscreen(texture) => texture,
+        }
+    }
+
     pub(crate) fn present(self) {
-        self.surface_texture.present();
+        if let FrameSource::Surface(surface_texture) = self.source {
+            surface_texture.present();
+        }
     }
 }
+
+// `Option::is_none_or` is too recent for the supported Rust versions
+#[allow(clippy::unnecessary_map_or)]
+fn select_adapter(
+    instance: &Instance,
+    backends: Backends,
+    surface: Option<&Surface>,
+    config: &GraphicsConfig,
+) -> Adapter {
+    if let Some(name) = &config.adapter_name {
+        let name = name.to_lowercase();
+        let adapter = instance.enumerate_adapters(backends).into_iter().find(|a| {
+            a.get_info().name.to_lowercase().contains(&name)
+                && surface.map_or(true, |s| a.is_surface_supported(s))
+        });
+        match adapter {
+            Some(adapter) => return adapter,
+            None => warn!("No graphics adapter matches {name:?}, using the default one!"),
+        }
+    }
+
+    pollster::block_on(instance.request_adapter(&RequestAdapterOptions {
+        power_preference: config.power_preference,
+        compatible_surface: surface,
+        force_fallback_adapter: false,
+    }))
+    .expect("No compatible graphics adapter found!")
+}
@@ -1,19 +1,57 @@
-use ctx::{Frame, GraphicsCtx};
+use std::collections::HashMap;
+
+use ctx::{Frame, GraphicsConfig, GraphicsCtx, WindowPresentMode};
+use rgine_logger::warn;
 use rgine_modules::{
     events::{EventQueue, Listener},
     standards::ShutdownEvent,
     AnyResult, Dependency, Engine, Module,
 };
-use rgine_platform::window::module::{
-    RequestWindowRedrawEvent, WindowPlatformModule, WindowRenderReadyEvent,
+use rgine_platform::window::{
+    module::{
+        RequestWindowRedrawEvent, SecondaryWindowRenderReadyEvent, WindowClosedEvent,
+        WindowOpenedEvent, WindowPlatformModule, WindowRenderReadyEvent,
+    },
+    ResumedEvent, SuspendedEvent, WindowId,
 };
 
 pub mod color;
 pub mod ctx;
+pub mod readback;
 
 pub use rgine_platform::window::{
-    module::WindowResizeEvent as SurfaceResizeEvent, WindowReadyEvent,
+    module::WindowResizedEvent as SurfaceResizeEvent, WindowReadyEvent,
 };
+/// Changes the surface present mode, see [`GraphicsCtx::set_present_mode`]
+pub struct SetPresentModeEvent {
+    pub mode: WindowPresentMode,
+}
+/// Emitted once the graphics context was recreated after its device was lost,
+/// every GPU resource created from the previous context must be recreated.
+pub struct GraphicsDeviceLostEvent;
+/// Sets the config used to create the graphics context, see [`GraphicsConfig`].
+///
+/// It must be pushed before the window is ready, during the [`StartEvent`](rgine_modules::standards::StartEvent) for example.
+pub struct SetGraphicsConfigEvent {
+    pub config: GraphicsConfig,
+}
+/// Emitted when the device of the secondary window `window` was lost and its context was
+/// recreated, see [`GraphicsDeviceLostEvent`]
+pub struct WindowGraphicsDeviceLostEvent {
+    pub window: WindowId,
+}
+/// Emitted when the frame of a secondary window is ready to be rendered to, it is presented
+/// right after the listeners of this event ran.
+///
+/// The context and frame of the window are given by [`GraphicsModule::window_ctx`] and
+/// [`GraphicsModule::window_frame`], a `SpriteRenderer` created with this context can
+/// submit to it.
+pub struct RenderWindowEvent {
+    pub window: WindowId,
+}
+pub struct PresentWindowEvent {
+    pub window: WindowId,
+}
 pub struct PreSubmitRenderEvent;
 pub struct SubmitRenderEvent;
 pub struct RenderPresentEvent;
@@ -23,12 +61,32 @@ pub struct GraphicsModule {
 
     pub ctx: Option<GraphicsCtx>,
     pub current_frame: Option<Frame>,
+    pub config: GraphicsConfig,
+    /// Contexts of the secondary windows, each one with its own device
+    window_ctxs: HashMap<WindowId, GraphicsCtx>,
+    window_frames: HashMap<WindowId, Frame>,
 }
 
 impl GraphicsModule {
     pub fn window_size(&self) -> Option<(u32, u32)> {
         self.platform.read_state().window_size()
     }
+
+    /// Whether `window` is the window of [`GraphicsModule::ctx`]
+    pub fn is_main_window(&self, window: WindowId) -> bool {
+        self.platform.read_state().is_main_window(window)
+    }
+
+    /// Context of the secondary window `window`, GPU resources can't be shared with the
+    /// main context
+    pub fn window_ctx(&self, window: WindowId) -> Option<&GraphicsCtx> {
+        self.window_ctxs.get(&window)
+    }
+
+    /// Frame of the secondary window `window`, only available during a [`RenderWindowEvent`]
+    pub fn window_frame(&self, window: WindowId) -> Option<&Frame> {
+        self.window_frames.get(&window)
+    }
 }
 
 impl Module for GraphicsModule {
@@ -37,6 +95,14 @@ impl Module for GraphicsModule {
         SurfaceResizeEvent,
         WindowRenderReadyEvent,
         RenderPresentEvent,
+        SetPresentModeEvent,
+        SetGraphicsConfigEvent,
+        WindowOpenedEvent,
+        WindowClosedEvent,
+        SecondaryWindowRenderReadyEvent,
+        PresentWindowEvent,
+        SuspendedEvent,
+        ResumedEvent,
         ShutdownEvent,
     );
 
@@ -47,26 +113,67 @@ impl Module for GraphicsModule {
             ctx: None,
             platform,
             current_frame: None,
+            config: GraphicsConfig::default(),
+            window_ctxs: HashMap::new(),
+            window_frames: HashMap::new(),
         })
     }
 }
-impl Listener<WindowReadyEvent> for GraphicsModule {
-    fn on_event(&mut self, _: &mut WindowReadyEvent, _: &mut EventQueue) {
+impl GraphicsModule {
+    fn create_ctx(&mut self) {
+        let platform = self.platform.read_state();
+        // Headless when there is no window
         self.ctx = Some(GraphicsCtx::new(
-            self.platform.read_state().window.get().unwrap().clone(),
+            platform.window.get().cloned(),
+            platform.window_size().unwrap(),
+            platform.config.get().unwrap().present_mode,
+            &self.config,
         ))
     }
+
+    fn create_window_ctx(&mut self, window: WindowId) {
+        let platform = self.platform.read_state();
+        let Some(window) = platform.window_by_id(window) else {
+            return;
+        };
+        let ctx = GraphicsCtx::new(
+            Some(window.clone()),
+            window.inner_size().into(),
+            platform.config.get().unwrap().present_mode,
+            &self.config,
+        );
+        self.window_ctxs.insert(window.id(), ctx);
+        window.request_redraw();
+    }
+}
+
+impl Listener<WindowReadyEvent> for GraphicsModule {
+    fn on_event(&mut self, _: &mut WindowReadyEvent, _: &mut EventQueue) {
+        self.create_ctx();
+    }
 }
 impl Listener<SurfaceResizeEvent> for GraphicsModule {
-    fn on_event(&mut self, _: &mut SurfaceResizeEvent, _: &mut EventQueue) {
-        if let Some(ctx) = &mut self.ctx {
-            ctx.resize(self.platform.read_state().window_size().unwrap())
+    fn on_event(&mut self, event: &mut SurfaceResizeEvent, _: &mut EventQueue) {
+        let ctx = match self.platform.read_state().is_main_window(event.window) {
+            true => self.ctx.as_mut(),
+            false => self.window_ctxs.get_mut(&event.window),
+        };
+        if let Some(ctx) = ctx {
+            ctx.resize((event.width, event.height))
         }
     }
 }
 impl Listener<WindowRenderReadyEvent> for GraphicsModule {
     fn on_event(&mut self, _: &mut WindowRenderReadyEvent, queue: &mut EventQueue) {
-        if let Some(frame) = self.ctx.as_ref().unwrap().next_frame() {
+        if self.ctx.as_ref().unwrap().is_device_lost() {
+            // The previous surface must be dropped before creating a new one for the window
+            self.ctx.take();
+            self.create_ctx();
+            queue.push(GraphicsDeviceLostEvent);
+            queue.push(RequestWindowRedrawEvent);
+            return;
+        }
+        if let Some(frame) = self.ctx.as_mut().unwrap().next_frame() {
             self.current_frame = Some(frame);
             queue.push(PreSubmitRenderEvent);
             queue.push(SubmitRenderEvent);
@@ -76,15 +183,97 @@ impl Listener<WindowRenderReadyEvent> for GraphicsModule {
 }
 impl Listener<RenderPresentEvent> for GraphicsModule {
     fn on_event(&mut self, _: &mut RenderPresentEvent, queue: &mut EventQueue) {
-        self.current_frame.take().map(|frame| {
+        if let Some(frame) = self.current_frame.take() {
             frame.present();
             queue.push(RequestWindowRedrawEvent);
-        });
+        }
+    }
+}
+impl Listener<SetPresentModeEvent> for GraphicsModule {
+    fn on_event(&mut self, event: &mut SetPresentModeEvent, _: &mut EventQueue) {
+        if let Some(ctx) = &mut self.ctx {
+            ctx.set_present_mode(event.mode);
+        }
+    }
+}
+impl Listener<SetGraphicsConfigEvent> for GraphicsModule {
+    fn on_event(&mut self, event: &mut SetGraphicsConfigEvent, _: &mut EventQueue) {
+        if self.ctx.is_some() {
+            warn!("The graphics config was set after the graphics context creation, it will only be used if the context is recreated!");
+        }
+        self.config = event.config.clone();
+    }
+}
+impl Listener<WindowOpenedEvent> for GraphicsModule {
+    fn on_event(&mut self, event: &mut WindowOpenedEvent, _: &mut EventQueue) {
+        self.create_window_ctx(event.window);
+    }
+}
+impl Listener<WindowClosedEvent> for GraphicsModule {
+    fn on_event(&mut self, event: &mut WindowClosedEvent, _: &mut EventQueue) {
+        self.window_frames.remove(&event.window);
+        self.window_ctxs.remove(&event.window);
+    }
+}
+impl Listener<SecondaryWindowRenderReadyEvent> for GraphicsModule {
+    fn on_event(&mut self, event: &mut SecondaryWindowRenderReadyEvent, queue: &mut EventQueue) {
+        let window = event.window;
+        let Some(ctx) = self.window_ctxs.get_mut(&window) else {
+            return;
+        };
+        if ctx.is_device_lost() {
+            self.window_ctxs.remove(&window);
+            self.create_window_ctx(window);
+            queue.push(WindowGraphicsDeviceLostEvent { window });
+            return;
+        }
+        if let Some(frame) = ctx.next_frame() {
+            self.window_frames.insert(window, frame);
+            queue.push(RenderWindowEvent { window });
+            queue.push(PresentWindowEvent { window });
+        }
+    }
+}
+impl Listener<PresentWindowEvent> for GraphicsModule {
+    fn on_event(&mut self, event: &mut PresentWindowEvent, _: &mut EventQueue) {
+        if let Some(frame) = self.window_frames.remove(&event.window) {
+            frame.present();
+            if let Some(window) = self.platform.read_state().window_by_id(event.window) {
+                window.request_redraw();
+            }
+        }
+    }
+}
+impl Listener<SuspendedEvent> for GraphicsModule {
+    fn on_event(&mut self, _: &mut SuspendedEvent, _: &mut EventQueue) {
+        // Frames being rendered are dropped with their surface
+        self.current_frame = None;
+        self.window_frames.clear();
+        for ctx in self.ctx.iter_mut().chain(self.window_ctxs.values_mut()) {
+            ctx.suspend();
+        }
+    }
+}
+impl Listener<ResumedEvent> for GraphicsModule {
+    fn on_event(&mut self, _: &mut ResumedEvent, queue: &mut EventQueue) {
+        let platform = self.platform.read_state();
+        if let (Some(ctx), Some(window)) = (&mut self.ctx, platform.window()) {
+            ctx.resume(window);
+        }
+        for (id, ctx) in &mut self.window_ctxs {
+            if let Some(window) = platform.window_by_id(*id) {
+                ctx.resume(window.clone());
+                window.request_redraw();
+            }
+        }
+        queue.push(RequestWindowRedrawEvent);
     }
 }
 impl Listener<ShutdownEvent> for GraphicsModule {
     fn on_event(&mut self, _: &mut ShutdownEvent, _: &mut EventQueue) {
         // Prevent STATUS_ACCESS_VIOLATION because of platform dependency dropped before this module and so surface is pointing at null window, i guess?
         self.ctx.take();
+        self.window_frames.clear();
+        self.window_ctxs.clear();
     }
 }
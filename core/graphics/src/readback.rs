@@ -0,0 +1,294 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use wgpu::*;
+
+use crate::ctx::{Frame, GraphicsCtx};
+
+/// Error of a texture readback, see [`GraphicsCtx::read_texture`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadbackError {
+    /// The texture is not an 8 bits RGBA or BGRA texture
+    UnsupportedFormat(TextureFormat),
+    /// The texture was not created with the `COPY_SRC` usage
+    NotCopySource,
+    /// The copy could not be mapped, such as when the device is lost
+    Map(BufferAsyncError),
+}
+
+impl std::fmt::Display for ReadbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedFormat(format) => {
+                write!(f, "Unable to read back a texture of format {format:?}")
+            }
+            Self::NotCopySource => write!(
+                f,
+                "Unable to read back a texture without the COPY_SRC usage"
+            ),
+            Self::Map(e) => write!(f, "Unable to read back texture: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ReadbackError {}
+
+impl From<BufferAsyncError> for ReadbackError {
+    fn from(e: BufferAsyncError) -> Self {
+        Self::Map(e)
+    }
+}
+
+impl GraphicsCtx {
+    /// Reads the pixels rendered to `frame` so far, blocking until the GPU is done.
+    ///
+    /// It must be called before the frame is presented, see [`GraphicsCtx::read_texture`].
+    pub fn read_frame(&self, frame: &Frame) -> Result<Vec<u8>, ReadbackError> {
+        Ok(self.read_texture(frame.texture())?.wait(self)?)
+    }
+
+    /// Starts copying `texture` back to the CPU, the returned [`TextureReadback`] resolves
+    /// to its pixels as tightly packed RGBA8 rows, from the top left corner.
+    ///
+    /// The texture must be an 8 bits RGBA or BGRA texture with the `COPY_SRC` usage,
+    /// otherwise an error is returned. The readback progresses whenever the device is polled,
+    /// which happens every frame.
+    pub fn read_texture(&self, texture: &Texture) -> Result<TextureReadback, ReadbackError> {
+        let bgra = match texture.format() {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            format => return Err(ReadbackError::UnsupportedFormat(format)),
+        };
+        if !texture.usage().contains(TextureUsages::COPY_SRC) {
+            return Err(ReadbackError::NotCopySource);
+        }
+
+        let (width, height) = (texture.width(), texture.height());
+        // Buffer rows must be aligned to 256 bytes
+        let padded_bytes_per_row =
+            (width * 4).div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Readback buffer"),
+            size: padded_bytes_per_row as u64 * height as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Readback encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let state = Arc::new(Mutex::new(ReadbackState::default()));
+        let callback_state = state.clone();
+        buffer.slice(..).map_async(MapMode::Read, move |result| {
+            let mut state = callback_state.lock().unwrap();
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+
+        Ok(TextureReadback {
+            buffer,
+            width,
+            padded_bytes_per_row,
+            bgra,
+            state,
+        })
+    }
+}
+
+#[derive(Default)]
+struct ReadbackState {
+    result: Option<Result<(), BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+/// Pending copy of a texture to the CPU, see [`GraphicsCtx::read_texture`].
+///
+/// It resolves to an error if the copy can't be mapped, such as when the device is lost.
+pub struct TextureReadback {
+    buffer: Buffer,
+    width: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+    state: Arc<Mutex<ReadbackState>>,
+}
+
+impl TextureReadback {
+    /// Blocks until the pixels are available
+    pub fn wait(self, ctx: &GraphicsCtx) -> Result<Vec<u8>, BufferAsyncError> {
+        ctx.device.poll(Maintain::Wait);
+        pollster::block_on(self)
+    }
+
+    fn pixels(&self) -> Vec<u8> {
+        let row_size = self.width as usize * 4;
+        let data = self.buffer.slice(..).get_mapped_range();
+        let mut pixels =
+            Vec::with_capacity(row_size * data.len() / self.padded_bytes_per_row as usize);
+        for row in data.chunks(self.padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..row_size]);
+        }
+        drop(data);
+        self.buffer.unmap();
+
+        if self.bgra {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        pixels
+    }
+}
+
+impl Future for TextureReadback {
+    type Output = Result<Vec<u8>, BufferAsyncError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            Some(Err(e)) => Poll::Ready(Err(e)),
+            Some(Ok(())) => {
+                drop(state);
+                Poll::Ready(Ok(self.pixels()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rgine_modules::{
+        events::{EventQueue, Listener},
+        AnyResult, Dependency, Engine, Module,
+    };
+    use rgine_platform::window::{headless::HeadlessEngineExt, WindowPlatformConfig};
+
+    use super::*;
+    use crate::{GraphicsModule, SubmitRenderEvent};
+
+    struct ClearRed {
+        graphics: Dependency<GraphicsModule>,
+        pixels: Vec<u8>,
+    }
+    impl Module for ClearRed {
+        type ListeningTo = (SubmitRenderEvent,);
+        fn new(ctx: &mut Engine) -> AnyResult<Self> {
+            Ok(Self {
+                graphics: ctx.dependency()?,
+                pixels: Vec::new(),
+            })
+        }
+    }
+    impl Listener<SubmitRenderEvent> for ClearRed {
+        fn on_event(&mut self, _: &mut SubmitRenderEvent, _: &mut EventQueue) {
+            let graphics = self.graphics.read_state();
+            let (ctx, frame) = (
+                graphics.ctx.as_ref().unwrap(),
+                graphics.current_frame.as_ref().unwrap(),
+            );
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor::default());
+            encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &frame.view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::RED),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            ctx.queue.submit(std::iter::once(encoder.finish()));
+            self.pixels = ctx.read_frame(frame).unwrap();
+        }
+    }
+
+    #[test]
+    fn headless_frame_is_read_back() {
+        // Machines without any graphics adapter can't render
+        if pollster::block_on(Instance::default().request_adapter(&Default::default())).is_none() {
+            return;
+        }
+
+        let mut engine = Engine::new_without_logger::<ClearRed>();
+        engine.start_headless(WindowPlatformConfig {
+            size: Some((3, 2)),
+            ..Default::default()
+        });
+        engine.step_headless(std::time::Duration::ZERO);
+
+        let pixels = &engine.module::<ClearRed>().unwrap().pixels;
+        assert_eq!(pixels.len(), 3 * 2 * 4);
+        assert!(pixels.chunks(4).all(|p| p == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn unreadable_textures_are_errors() {
+        if pollster::block_on(Instance::default().request_adapter(&Default::default())).is_none() {
+            return;
+        }
+
+        let mut engine = Engine::new_without_logger::<GraphicsModule>();
+        engine.start_headless(WindowPlatformConfig::default());
+        let graphics = engine.module::<GraphicsModule>().unwrap();
+        let ctx = graphics.ctx.as_ref().unwrap();
+        let texture = |format, usage| {
+            ctx.device.create_texture(&TextureDescriptor {
+                label: None,
+                size: Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+
+        let float = texture(TextureFormat::Rgba16Float, TextureUsages::COPY_SRC);
+        assert_eq!(
+            ctx.read_texture(&float).err(),
+            Some(ReadbackError::UnsupportedFormat(TextureFormat::Rgba16Float))
+        );
+        let no_copy = texture(TextureFormat::Rgba8Unorm, TextureUsages::TEXTURE_BINDING);
+        assert_eq!(
+            ctx.read_texture(&no_copy).err(),
+            Some(ReadbackError::NotCopySource)
+        );
+    }
+}
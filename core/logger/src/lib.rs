@@ -1,46 +1,333 @@
-use std::{iter::Flatten, slice, vec};
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    iter::Flatten,
+    path::{Path, PathBuf},
+    slice,
+    sync::{Mutex, PoisonError, RwLock},
+    time::{Duration, Instant},
+    vec,
+};
 
 use colored::Colorize;
-use log::{set_logger, set_max_level, Level, LevelFilter, Log, Metadata, Record};
+use log::{set_logger, set_max_level, Level, Log, Metadata, Record};
 
-pub use log::{debug, error, info, trace, warn};
+pub use log::{debug, error, info, trace, warn, LevelFilter};
 
 #[cfg(feature = "ignore_wgpu")]
-const WGPU_IGNORE_LIST: &'static [&'static str] = &["wgpu", "naga"];
+const WGPU_IGNORE_LIST: &[&str] = &["wgpu", "naga"];
+
+/// Environment variable read by [`init_logger`] to configure the log levels.
+///
+/// It holds comma separated directives, either a level for every target (`info`)
+/// or a level for a target and its submodules (`rgine_renderer_2d=warn`), e.g.
+/// `RENGINE_LOG=info,rgine_renderer_2d=warn,physics=off`.
+pub const LOG_ENV_VAR: &str = "RENGINE_LOG";
 
 static LOGGER: Logger = Logger;
+static SINKS: RwLock<Sinks> = RwLock::new(Sinks {
+    console_level: LevelFilter::Trace,
+    file_level: LevelFilter::Off,
+});
+static FILE_SINK: Mutex<Option<FileSink>> = Mutex::new(None);
+static FILTERS: RwLock<Filters> = RwLock::new(Filters {
+    default: LevelFilter::Trace,
+    targets: Vec::new(),
+});
+
+#[derive(Clone, Debug)]
+pub struct LoggerConfig {
+    /// Most verbose level printed to the console, after the target levels are applied
+    pub console_level: LevelFilter,
+    /// Also writes the logs to a file if set
+    pub file: Option<FileSinkConfig>,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        Self {
+            console_level: LevelFilter::Trace,
+            file: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FileSinkConfig {
+    pub path: PathBuf,
+    /// Most verbose level written to the file, after the target levels are applied
+    pub level: LevelFilter,
+    /// Starts a new file once the current one is too large or too old, `None` appends forever
+    pub rotation: Option<LogRotation>,
+    /// Rotated files kept besides the current one, as `path.1` (newest) to `path.N`
+    pub max_rotated_files: usize,
+}
+
+impl FileSinkConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            level: LevelFilter::Info,
+            rotation: None,
+            max_rotated_files: 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogRotation {
+    /// Rotates once the file reaches this many bytes
+    Size(u64),
+    /// Rotates once the file was written to for this long
+    Interval(Duration),
+}
 
 pub fn init_logger() {
+    init_logger_with(LoggerConfig::default())
+}
+
+/// Installs the logger with the sinks of `config`, [`LOG_ENV_VAR`] is read on top of it.
+///
+/// Logs are written to the file sink as they come but only flushed on warnings, errors and
+/// [`flush_logger`], which the engine calls when dropped.
+pub fn init_logger_with(config: LoggerConfig) {
+    let invalid_directives = match env::var(LOG_ENV_VAR) {
+        Ok(directives) => parse_directives(&directives),
+        Err(_) => Vec::new(),
+    };
+
+    let mut file_error = None;
+    let file_level = match config.file {
+        Some(file_config) => match FileSink::open(file_config.clone()) {
+            Ok(sink) => {
+                let level = sink.config.level;
+                *FILE_SINK.lock().unwrap_or_else(PoisonError::into_inner) = Some(sink);
+                level
+            }
+            Err(e) => {
+                file_error = Some((file_config.path, e));
+                LevelFilter::Off
+            }
+        },
+        None => LevelFilter::Off,
+    };
+    *SINKS.write().unwrap_or_else(PoisonError::into_inner) = Sinks {
+        console_level: config.console_level,
+        file_level,
+    };
+
     set_logger(&LOGGER)
         .map(|()| set_max_level(LevelFilter::Trace))
-        .expect("Could not set logger!")
+        .expect("Could not set logger!");
+
+    // Reported once the logger is installed so that they reach the sinks
+    for directive in invalid_directives {
+        warn!("Invalid log level in {LOG_ENV_VAR}: {directive:?}, the directive is ignored");
+    }
+    if let Some((path, e)) = file_error {
+        error!("Unable to open the log file {}! error: {e}", path.display());
+    }
+}
+
+/// Writes the buffered logs to the log file
+pub fn flush_logger() {
+    log::logger().flush()
+}
+
+/// Sets the level of the targets without a level of their own, [`LevelFilter::Trace`] by default
+pub fn set_log_level(level: LevelFilter) {
+    FILTERS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .default = level;
+}
+
+/// Sets the level of `target` and of its submodules, such as `target::module`.
+///
+/// The target of a log is its module path unless given with `info!(target: "name", ...)`.
+pub fn set_target_log_level(target: &str, level: LevelFilter) {
+    let mut filters = FILTERS.write().unwrap_or_else(PoisonError::into_inner);
+    match filters.targets.iter_mut().find(|(t, _)| t == target) {
+        Some((_, l)) => *l = level,
+        None => filters.targets.push((target.to_string(), level)),
+    }
+}
+
+/// Level the logs of `target` are filtered with
+pub fn target_log_level(target: &str) -> LevelFilter {
+    let filters = FILTERS.read().unwrap_or_else(PoisonError::into_inner);
+    filters.target_level(target).unwrap_or(filters.default)
+}
+
+/// Applies the comma separated `directives`, returning the ones with an invalid level
+fn parse_directives(directives: &str) -> Vec<String> {
+    let mut invalid = Vec::new();
+    for directive in directives
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+    {
+        let (target, level) = match directive.split_once('=') {
+            Some((target, level)) => (Some(target.trim()), level.trim()),
+            None => (None, directive),
+        };
+        let Ok(level) = level.parse::<LevelFilter>() else {
+            invalid.push(directive.to_string());
+            continue;
+        };
+        match target {
+            Some(target) => set_target_log_level(target, level),
+            None => set_log_level(level),
+        }
+    }
+    invalid
+}
+
+struct Filters {
+    default: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl Filters {
+    /// Level of the most specific target matching `target`
+    fn target_level(&self, target: &str) -> Option<LevelFilter> {
+        self.targets
+            .iter()
+            .filter(|(t, _)| {
+                target
+                    .strip_prefix(t.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(t, _)| t.len())
+            .map(|(_, level)| *level)
+    }
+}
+
+struct Sinks {
+    console_level: LevelFilter,
+    file_level: LevelFilter,
+}
+
+struct FileSink {
+    config: FileSinkConfig,
+    writer: BufWriter<File>,
+    /// Bytes in the current file
+    size: u64,
+    opened_at: Instant,
+}
+
+impl FileSink {
+    fn open(config: FileSinkConfig) -> io::Result<Self> {
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+        Ok(Self {
+            size: file.metadata()?.len(),
+            writer: BufWriter::new(file),
+            opened_at: Instant::now(),
+            config,
+        })
+    }
+
+    fn write(&mut self, line: &str, flush: bool) -> io::Result<()> {
+        let should_rotate = match self.config.rotation {
+            Some(LogRotation::Size(max_size)) => self.size + line.len() as u64 > max_size,
+            Some(LogRotation::Interval(interval)) => self.opened_at.elapsed() >= interval,
+            None => false,
+        };
+        // Rotating an empty file would only lose older logs
+        if should_rotate && self.size > 0 {
+            self.rotate()?;
+        }
+
+        self.writer.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        if flush {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Shifts `path.N` to `path.N+1`, dropping the oldest, then starts a new file
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        let path = &self.config.path;
+        let max = self.config.max_rotated_files;
+        if max == 0 {
+            fs::remove_file(path)?;
+        } else {
+            let _ = fs::remove_file(rotated_path(path, max));
+            for i in (1..max).rev() {
+                let _ = fs::rename(rotated_path(path, i), rotated_path(path, i + 1));
+            }
+            fs::rename(path, rotated_path(path, 1))?;
+        }
+
+        let file = File::create(path)?;
+        self.writer = BufWriter::new(file);
+        self.size = 0;
+        self.opened_at = Instant::now();
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!(".{index}"));
+    path.into()
 }
 
 struct Logger;
 
 impl Log for Logger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= Level::Trace
+        let sinks = SINKS.read().unwrap_or_else(PoisonError::into_inner);
+        metadata.level() <= target_log_level(metadata.target())
+            && metadata.level() <= sinks.console_level.max(sinks.file_level)
     }
 
     fn log(&self, record: &Record) {
         let md = record.metadata();
-        if self.enabled(md) {
-            if let Some(path) = record.module_path() {
-                let ignore_list: Flatten<vec::IntoIter<slice::Iter<&str>>> = vec![
-                    #[cfg(feature = "ignore_wgpu")]
-                    WGPU_IGNORE_LIST.iter(),
-                ]
-                .into_iter()
-                .flatten();
-
-                for e in ignore_list {
-                    if path.contains(e) {
-                        return;
-                    }
+        let filters = FILTERS.read().unwrap_or_else(PoisonError::into_inner);
+        let level_filter = filters.target_level(md.target());
+        if md.level() > level_filter.unwrap_or(filters.default) {
+            return;
+        }
+        drop(filters);
+
+        // Targets given a level explicitly are not ignored
+        if let (Some(path), None) = (record.module_path(), level_filter) {
+            let ignore_list: Flatten<vec::IntoIter<slice::Iter<&str>>> = vec![
+                #[cfg(feature = "ignore_wgpu")]
+                WGPU_IGNORE_LIST.iter(),
+            ]
+            .into_iter()
+            .flatten();
+
+            for e in ignore_list {
+                if path.contains(e) {
+                    return;
                 }
             }
+        }
+
+        let mut log_origin = String::new();
+        if let Some(file) = record.file() {
+            log_origin += &format!("{file}:");
+            if let Some(line) = record.line() {
+                log_origin += &line.to_string();
+            }
+        }
+        // Custom targets are shown, module paths are already given by the file
+        if record.module_path() != Some(record.target()) {
+            log_origin += &format!(" [{}]", record.target());
+        }
 
+        let sinks = SINKS.read().unwrap_or_else(PoisonError::into_inner);
+        let file_level = sinks.file_level;
+        if record.level() <= sinks.console_level {
             let level = record.level().to_string();
             let level = match record.level() {
                 Level::Trace => level.magenta(),
@@ -49,19 +336,50 @@ impl Log for Logger {
                 Level::Warn => level.yellow(),
                 Level::Error => level.red(),
             };
+            #[rustfmt::skip]
+            /* LOGGER PRINTLN! */ println!("{} {} | {}", log_origin, level, record.args());
+        }
+
+        drop(sinks);
 
-            let mut log_origin = String::new();
-            if let Some(file) = record.file() {
-                log_origin += &format!("{file}:");
-                if let Some(line) = record.line() {
-                    log_origin += &line.to_string();
+        if record.level() <= file_level {
+            let mut file_sink = FILE_SINK.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(sink) = file_sink.as_mut() {
+                let line = format!("{} {} | {}\n", log_origin, record.level(), record.args());
+                if let Err(e) = sink.write(&line, record.level() <= Level::Warn) {
+                    // The next records would most likely fail the same way, the error is
+                    // logged once the locks are released since logging needs them
+                    *file_sink = None;
+                    drop(file_sink);
+                    SINKS
+                        .write()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .file_level = LevelFilter::Off;
+                    error!("Unable to write to the log file, it is disabled! error: {e}");
                 }
             }
+        }
+    }
 
-            #[rustfmt::skip]
-            /* LOGGER PRINTLN! */ println!("{} {} | {}", log_origin, level, record.args());
+    fn flush(&self) {
+        if let Some(sink) = FILE_SINK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+        {
+            let _ = sink.writer.flush();
         }
     }
+}
 
-    fn flush(&self) {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_directives_are_returned() {
+        let invalid = parse_directives("directives_test=warn, directives_test::a=loud,,");
+        assert_eq!(invalid, ["directives_test::a=loud"]);
+        assert_eq!(target_log_level("directives_test::a"), LevelFilter::Warn);
+    }
 }
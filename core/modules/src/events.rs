@@ -1,11 +1,15 @@
 use std::{
     any::{Any, TypeId},
+    cell::RefCell,
     collections::HashMap,
+    error::Error,
+    rc::Rc,
 };
 
-use crate::ModuleListener;
+use crate::{AnyResult, Module, ModuleListener};
 #[cfg(debug_assertions)]
 pub(crate) trait DebugName {
+    #[cfg_attr(not(feature = "debuglog"), allow(dead_code))]
     fn of(&self) -> String;
 }
 impl<T> DebugName for T {
@@ -17,7 +21,7 @@ impl<T> DebugName for T {
             "{} (inside of {})",
             parts.last().unwrap(),
             &parts[..parts.len() - 1]
-                .into_iter()
+                .iter()
                 .map(|p| format!("::{}", p))
                 .collect::<Vec<_>>()
                 .concat()[2..]
@@ -34,6 +38,12 @@ impl<T: Any + DebugName> Event for T {
     }
 }
 
+/// Marks events that listeners can consume using [`EventQueue::consume`], for example to prevent
+/// a click handled by the UI from also reaching the world.
+///
+/// Events are broadcasted to every listener unless they implement this trait.
+pub trait ConsumableEvent: Event {}
+
 /// Allows for module to listen to Event `T`.
 ///
 /// **WARNING: For this to work you need to add the event type to the associated type `<Self as Module>::ListeningTo`**
@@ -41,14 +51,82 @@ pub trait Listener<T: Event>: 'static {
     fn on_event(&mut self, event: &mut T, queue: &mut EventQueue);
 }
 
+/// Fallible alternative to [`Listener<T>`], implementing it implements [`Listener<T>`].
+///
+/// Returned errors are handled by the [`Engine`](crate::Engine) according to its [`ErrorPolicy`](crate::ErrorPolicy).
+pub trait TryListener<T: Event>: 'static {
+    fn try_on_event(&mut self, event: &mut T, queue: &mut EventQueue) -> AnyResult<()>;
+}
+impl<T: Event, L: TryListener<T>> Listener<T> for L {
+    fn on_event(&mut self, event: &mut T, queue: &mut EventQueue) {
+        if let Err(e) = self.try_on_event(event, queue) {
+            queue.errors.push(e);
+        }
+    }
+}
+
+/// Event asking its listeners for responses of type `R`, sent with [`Engine::query`](crate::Engine::query)
+/// or [`EventQueue::query`].
+///
+/// Listeners respond with [`Query::respond`], a listener can also [`EventQueue::consume`] the
+/// query so that the next ones don't receive it.
+pub struct Query<Q, R> {
+    pub query: Q,
+    responses: Rc<RefCell<Vec<R>>>,
+}
+
+impl<Q, R> Query<Q, R> {
+    pub fn new(query: Q) -> (Self, QueryResponses<R>) {
+        let responses = Rc::new(RefCell::new(Vec::new()));
+        (
+            Self {
+                query,
+                responses: responses.clone(),
+            },
+            QueryResponses(responses),
+        )
+    }
+
+    pub fn respond(&mut self, response: R) {
+        self.responses.borrow_mut().push(response);
+    }
+}
+
+impl<Q: 'static, R: 'static> ConsumableEvent for Query<Q, R> {}
+
+/// Responses to a [`Query`], in the order the listeners responded
+pub struct QueryResponses<R>(Rc<RefCell<Vec<R>>>);
+
+impl<R> QueryResponses<R> {
+    /// Takes the responses received so far
+    pub fn take(&self) -> Vec<R> {
+        std::mem::take(&mut self.0.borrow_mut())
+    }
+}
+
 /// Queue of events to be dispatched
 pub struct EventQueue {
     inner: Vec<Box<dyn Event>>,
+    pub(crate) deferred: Vec<Box<dyn Event>>,
+    pub(crate) errors: Vec<Box<dyn Error>>,
+    /// Modules to enable or disable once the current listener returns
+    pub(crate) module_toggles: Vec<(TypeId, bool)>,
+    consumed: bool,
 }
 
 impl EventQueue {
     pub(crate) fn new() -> Self {
-        Self { inner: Vec::new() }
+        Self {
+            inner: Vec::new(),
+            deferred: Vec::new(),
+            errors: Vec::new(),
+            module_toggles: Vec::new(),
+            consumed: false,
+        }
+    }
+
+    pub(crate) fn consumed(&self) -> bool {
+        self.consumed
     }
 
     pub(crate) fn take_last(&mut self) -> Option<Box<dyn Event>> {
@@ -61,7 +139,14 @@ impl EventQueue {
 
     pub(crate) fn extend(&mut self, mut other: Self) {
         other.inner.reverse();
-        self.inner.extend(other.inner)
+        self.inner.extend(other.inner);
+        self.deferred.extend(other.deferred);
+    }
+
+    /// Queues `events` to be dispatched in order
+    pub(crate) fn extend_from_deferred(&mut self, mut events: Vec<Box<dyn Event>>) {
+        events.reverse();
+        self.inner.extend(events);
     }
 
     pub fn is_empty(&mut self) -> bool {
@@ -72,6 +157,37 @@ impl EventQueue {
     pub fn push<T: Event>(&mut self, event: T) {
         self.inner.push(Box::new(event))
     }
+
+    /// Defers the event `T` to the next [`Engine::run_deferred`](crate::Engine::run_deferred) call
+    /// instead of dispatching it within the current schedule (once per frame on the window platform).
+    ///
+    /// Deferred events are dispatched in the order they were deferred, events they lead to being
+    /// dispatched right after each of them like in any schedule.
+    pub fn defer<T: Event>(&mut self, event: T) {
+        self.deferred.push(Box::new(event))
+    }
+
+    /// Enables or disables the module `T` once the current listener returns,
+    /// see [`Engine::set_module_enabled`](crate::Engine::set_module_enabled)
+    pub fn set_module_enabled<T: Module>(&mut self, enabled: bool) {
+        self.module_toggles.push((TypeId::of::<T>(), enabled))
+    }
+
+    /// Pushes a [`Query`] to be dispatched like any event.
+    ///
+    /// Its responses are available once it was dispatched, for example when handling an event
+    /// pushed right after it.
+    pub fn query<Q: 'static, R: 'static>(&mut self, query: Q) -> QueryResponses<R> {
+        let (query, responses) = Query::new(query);
+        self.push(query);
+        responses
+    }
+
+    /// Stops the dispatch of the event being handled, the listeners that did not handle it yet
+    /// won't receive it. Events pushed to this queue are still dispatched.
+    pub fn consume<T: ConsumableEvent>(&mut self, _event: &T) {
+        self.consumed = true;
+    }
 }
 
 /// Simply a tuple of Events, for examples: `()`, `(EventA,)` or `(EventA, EventB, EventC)`.
@@ -81,11 +197,16 @@ impl EventQueue {
 /// `(A, .., Z,): EventList<T>` is valid only if `T: Listener<A> + .. + Listener<Z>`
 pub trait EventList<T> {
     fn raw_listeners() -> ModuleListener<T>;
+    /// Type names of the events, for debugging
+    fn event_names() -> Vec<(TypeId, &'static str)>;
 }
 impl<T> EventList<T> for () {
     fn raw_listeners() -> ModuleListener<T> {
         HashMap::new()
     }
+    fn event_names() -> Vec<(TypeId, &'static str)> {
+        Vec::new()
+    }
 }
 
 macro_rules! _impl {
@@ -101,8 +222,11 @@ macro_rules! _impl {
                 )*
                 map
             }
+            fn event_names() -> Vec<(TypeId, &'static str)> {
+                vec![$((TypeId::of::<$name>(), std::any::type_name::<$name>())),*]
+            }
         }
     };
 }
 
-#[rustfmt::skip] mod _impl16 { use super::*; _impl!(A);_impl!(A B); _impl!(A B C);_impl!(A B C D);_impl!(A B C D E);_impl!(A B C D E F);_impl!(A B C D E F G);_impl!(A B C D E F G H);_impl!(A B C D E F G H I);_impl!(A B C D E F G H I J);_impl!(A B C D E F G H I J K);_impl!(A B C D E F G H I J K L);_impl!(A B C D E F G H I J K L M);_impl!(A B C D E F G H I J K L M N);_impl!(A B C D E F G H I J K L M N O);_impl!(A B C D E F G H I J K L M N O P);}
+#[rustfmt::skip] mod _impl32 { use super::*; _impl!(A);_impl!(A B);_impl!(A B C);_impl!(A B C D);_impl!(A B C D E);_impl!(A B C D E F);_impl!(A B C D E F G);_impl!(A B C D E F G H);_impl!(A B C D E F G H I);_impl!(A B C D E F G H I J);_impl!(A B C D E F G H I J K);_impl!(A B C D E F G H I J K L);_impl!(A B C D E F G H I J K L M);_impl!(A B C D E F G H I J K L M N);_impl!(A B C D E F G H I J K L M N O);_impl!(A B C D E F G H I J K L M N O P);_impl!(A B C D E F G H I J K L M N O P Q);_impl!(A B C D E F G H I J K L M N O P Q R);_impl!(A B C D E F G H I J K L M N O P Q R S);_impl!(A B C D E F G H I J K L M N O P Q R S U);_impl!(A B C D E F G H I J K L M N O P Q R S U V);_impl!(A B C D E F G H I J K L M N O P Q R S U V W);_impl!(A B C D E F G H I J K L M N O P Q R S U V W X);_impl!(A B C D E F G H I J K L M N O P Q R S U V W X Y);_impl!(A B C D E F G H I J K L M N O P Q R S U V W X Y Z);_impl!(A B C D E F G H I J K L M N O P Q R S U V W X Y Z A1);_impl!(A B C D E F G H I J K L M N O P Q R S U V W X Y Z A1 B1);_impl!(A B C D E F G H I J K L M N O P Q R S U V W X Y Z A1 B1 C1);_impl!(A B C D E F G H I J K L M N O P Q R S U V W X Y Z A1 B1 C1 D1);_impl!(A B C D E F G H I J K L M N O P Q R S U V W X Y Z A1 B1 C1 D1 E1);_impl!(A B C D E F G H I J K L M N O P Q R S U V W X Y Z A1 B1 C1 D1 E1 F1);_impl!(A B C D E F G H I J K L M N O P Q R S U V W X Y Z A1 B1 C1 D1 E1 F1 G1);}
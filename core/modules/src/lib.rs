@@ -13,11 +13,12 @@
 //!
 //! # Optional features
 //! - `standards`: often used events (game engine related), useful for compatibility between modules (enabled by default)
+//! - `replay`: recording and replay of serializable events, see [`replay`]
 
 use std::{
     any::{Any, TypeId},
     cell::{Ref, RefCell, RefMut},
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     error::Error,
     fmt::Display,
     marker::PhantomData,
@@ -25,21 +26,26 @@ use std::{
 };
 
 use events::Event;
-#[cfg(feature = "debuglog")]
-use rgine_logger::debug;
-use rgine_logger::init_logger;
+use rgine_logger::{debug, error, flush_logger, init_logger, init_logger_with, LoggerConfig};
 
-use crate::events::{EventList, EventQueue};
+use crate::{
+    events::{EventList, EventQueue, Query},
+    resources::Resources,
+};
 
 pub mod events;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod resources;
 #[cfg(feature = "standards")]
 pub mod standards;
 pub mod utils;
 
 pub mod prelude {
     pub use crate::{
-        events::{EventQueue, Listener},
-        AnyResult, Dependency, Engine, Module,
+        events::{ConsumableEvent, EventQueue, Listener, Query, TryListener},
+        resources::Resource,
+        AnyResult, Dependency, Engine, ErrorPolicy, Module,
     };
 
     #[cfg(feature = "standards")]
@@ -51,14 +57,21 @@ pub mod prelude {
 ///
 /// - Self is the Module `State`
 /// - ListeningTo is a list of events that the module is listening to `(EventA, .., EventZ,)` using the `Listener<SomeEvent>` trait
+/// - PRIORITY orders the modules listening to the same event: higher priorities are dispatched first,
+///   ties are dispatched in load order, a module being loaded after its dependencies.
 ///
 /// TODO:
 ///  - Allow for debug informations on a per module basis.
 ///  - Maybe allow for hot reloading if possible
 pub trait Module: Any + Sized {
     type ListeningTo: EventList<Self>;
+    const PRIORITY: i32 = 0;
 
     fn new(ctx: &mut Engine) -> AnyResult<Self>;
+
+    /// Called by [`Engine::remove_module`] once the module stopped listening to events,
+    /// right before it is dropped. Events pushed to `queue` are dispatched afterward.
+    fn teardown(&mut self, _queue: &mut EventQueue) {}
 }
 
 #[derive(Debug)]
@@ -70,20 +83,27 @@ pub enum ModuleError {
     AlreadyExist,
     /// Error occured because the target module could not be found
     NotFound,
-    /// Error occured because the target module is in use and thus can't be unloaded
+    /// Error occured because the target module is in use and thus can't be unloaded:
+    /// other modules depend on it or a [`Dependency`] handle to it is still alive
     InUse,
+    /// Error occured because modules depend on each other, the names of the modules in the cycle
+    /// are given in dependency order, starting and ending with the same module
+    Cycle(Vec<&'static str>),
 }
 
 impl Display for ModuleError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::InitError(_) => write!(f, "Failed to initialize module"),
+            Self::InitError(e) => write!(f, "Failed to initialize module: {e}"),
             Self::AlreadyExist => write!(
                 f,
                 "The engine can't support two instances of the same module"
             ),
             Self::NotFound => write!(f, "The target module could not be found"),
             Self::InUse => write!(f, "The target module is in use and thus can't be unloaded"),
+            Self::Cycle(modules) => {
+                write!(f, "Modules depend on each other: {}", modules.join(" -> "))
+            }
         }
     }
 }
@@ -93,13 +113,46 @@ impl Error for ModuleError {}
 /// A result with any error
 pub type AnyResult<T> = Result<T, Box<dyn Error>>;
 
+/// What the [`Engine`] does when a [`TryListener`](events::TryListener) returns an error
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Logs the error and keeps dispatching events
+    #[default]
+    Log,
+    /// Drops the remaining events of the schedule and keeps the error until [`Engine::take_error`],
+    /// the window platform stops and returns it
+    Abort,
+}
+
 type Modules = HashMap<TypeId, AnyModule>;
 type EventModuleSubscribers = HashMap<TypeId, Vec<TypeId>>;
+type ModuleDependencies = HashMap<TypeId, HashSet<TypeId>>;
 
 /// Allows for instantiation, storage and event dispatching of modules
 pub struct Engine {
     modules: Modules,
     subscribers: EventModuleSubscribers,
+    /// Type names of the events listened to, for debugging
+    event_names: HashMap<TypeId, &'static str>,
+    /// Modules each module asked for with [`Engine::dependency`] while initializing
+    dependencies: ModuleDependencies,
+    deferred: Vec<Box<dyn Event>>,
+    resources: Resources,
+    error_policy: ErrorPolicy,
+    error: Option<Box<dyn Error>>,
+    /// Modules being initialized, each one depending on the next
+    loading: Vec<(TypeId, &'static str)>,
+    /// Module the engine was created with, the modules it loads are not tracked as its dependencies
+    entrypoint: TypeId,
+    #[cfg(feature = "replay")]
+    replay: replay::Replay,
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        // The last logs would be lost if the process exits right after
+        flush_logger();
+    }
 }
 
 impl Engine {
@@ -108,10 +161,26 @@ impl Engine {
         Self::new_without_logger::<Entrypoint>()
     }
 
+    /// Same as [`Engine::new`], with the logger sinks of `config`
+    pub fn new_with_logger<Entrypoint: Module>(config: LoggerConfig) -> Self {
+        init_logger_with(config);
+        Self::new_without_logger::<Entrypoint>()
+    }
+
     pub fn new_without_logger<Entrypoint: Module>() -> Self {
         let mut _self = Self {
             modules: Modules::new(),
             subscribers: EventModuleSubscribers::new(),
+            event_names: HashMap::new(),
+            dependencies: ModuleDependencies::new(),
+            deferred: Vec::new(),
+            resources: Resources::new(),
+            error_policy: ErrorPolicy::default(),
+            error: None,
+            loading: Vec::new(),
+            entrypoint: TypeId::of::<Entrypoint>(),
+            #[cfg(feature = "replay")]
+            replay: replay::Replay::default(),
         };
         _self
             .dependency::<Entrypoint>()
@@ -121,38 +190,172 @@ impl Engine {
 
     /// Returns the module `T` as a `Dependency<T>`, loading it if not found.
     ///
-    /// In case the initialization fail, an error is returned instead.
+    /// Dependencies loaded by `T::new` are always initialized before `T`.
+    /// In case the initialization fail or `T` ends up depending on itself, an error is returned instead.
     pub fn dependency<T: Module>(&mut self) -> Result<Dependency<T>, ModuleError> {
         let tid = TypeId::of::<T>();
+        // The entrypoint is never removed, the modules it keeps are held by their `Dependency`
+        if let Some((dependent, _)) = self.loading.last().filter(|(t, _)| *t != self.entrypoint) {
+            self.dependencies.entry(*dependent).or_default().insert(tid);
+        }
         if !self.is_loaded::<T>() {
-            let module = AnyModule::new(T::new(self).map_err(ModuleError::InitError)?);
+            if let Some(start) = self.loading.iter().position(|(t, _)| *t == tid) {
+                let mut cycle = self.loading[start..]
+                    .iter()
+                    .map(|(_, name)| *name)
+                    .collect::<Vec<_>>();
+                cycle.push(std::any::type_name::<T>());
+                return Err(ModuleError::Cycle(cycle));
+            }
+
+            self.loading.push((tid, std::any::type_name::<T>()));
+            let state = T::new(self);
+            self.loading.pop();
+
+            let state = state.map_err(|e| {
+                self.dependencies.remove(&tid);
+                ModuleError::InitError(e)
+            })?;
+            let module = AnyModule::new(state);
             for event in module.listeners.keys() {
-                self.subscribers
-                    .entry(*event)
-                    .or_default()
-                    .push(TypeId::of::<T>());
+                let subscribers = self.subscribers.entry(*event).or_default();
+                // After every module of higher or equal priority, so that ties keep the load order
+                let index = subscribers
+                    .partition_point(|tid| self.modules[tid].priority >= module.priority);
+                subscribers.insert(index, tid);
             }
+            self.event_names
+                .extend(<T::ListeningTo as EventList<T>>::event_names());
             self.modules.insert(tid, module);
-            return Ok(self.dependency()?);
+            return self.dependency();
         }
         Ok(Dependency::new(self.modules.get(&tid).unwrap()))
     }
 
-    /// Unloads the module `T` and returns its current state.
+    /// Unloads the module `T` and returns its current state, without calling [`Module::teardown`].
     ///
-    /// In case the module is not already loaded, an error is returned instead.
+    /// In case the module is not loaded or is in use, an error is returned instead
+    /// and the module stays loaded.
     pub fn unload_module<T: Module>(&mut self) -> Result<T, ModuleError> {
         let tid = TypeId::of::<T>();
+        let Some(module) = self.modules.get(&tid) else {
+            return Err(ModuleError::NotFound);
+        };
+        let depended_on = self
+            .dependencies
+            .iter()
+            .any(|(dependent, deps)| *dependent != tid && deps.contains(&tid));
+        if depended_on || Rc::strong_count(&module.state) > 1 {
+            return Err(ModuleError::InUse);
+        }
 
-        if self.is_loaded::<T>() {
-            let module = self.modules.remove(&tid).unwrap();
-            for event in module.listeners.keys() {
-                self.subscribers.remove(event);
+        let module = self.modules.remove(&tid).unwrap();
+        self.dependencies.remove(&tid);
+        for event in module.listeners.keys() {
+            if let Some(subscribers) = self.subscribers.get_mut(event) {
+                subscribers.retain(|t| *t != tid);
+                if subscribers.is_empty() {
+                    self.subscribers.remove(event);
+                }
             }
-            let state = Rc::into_inner(module.state).ok_or(ModuleError::InUse)?;
-            Ok(*state.into_inner().downcast::<T>().unwrap())
-        } else {
-            Err(ModuleError::NotFound)
+        }
+        let state = Rc::into_inner(module.state).unwrap();
+        Ok(*state.into_inner().downcast::<T>().unwrap())
+    }
+
+    /// Unloads the module `T`, calls its [`Module::teardown`] then drops it.
+    ///
+    /// The modules depending on `T` must be removed first, otherwise [`ModuleError::InUse`]
+    /// is returned and `T` stays loaded.
+    pub fn remove_module<T: Module>(&mut self) -> Result<(), ModuleError> {
+        let mut state = self.unload_module::<T>()?;
+        let mut queue = EventQueue::new();
+        state.teardown(&mut queue);
+        drop(state);
+        apply_module_toggles(&mut self.modules, &mut queue);
+        self.run_schedule(queue);
+        Ok(())
+    }
+
+    /// Borrows the state of the module `T`, `None` if it is not loaded.
+    ///
+    /// To read a module from within a listener, keep the [`Dependency<T>`] returned when loading it instead.
+    pub fn module<T: Module>(&self) -> Option<Ref<'_, T>> {
+        self.modules
+            .get(&TypeId::of::<T>())
+            .map(|m| Ref::map((*m.state).borrow(), |s| s.downcast_ref::<T>().unwrap()))
+    }
+
+    /// Borrows the state of the module `T` mutably, `None` if it is not loaded.
+    pub fn module_mut<T: Module>(&mut self) -> Option<RefMut<'_, T>> {
+        self.modules
+            .get(&TypeId::of::<T>())
+            .map(|m| RefMut::map((*m.state).borrow_mut(), |s| s.downcast_mut::<T>().unwrap()))
+    }
+
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        self.error_policy = policy;
+    }
+
+    /// Whether a listener failed with [`ErrorPolicy::Abort`]
+    pub fn has_error(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// Takes the error of the listener that failed with [`ErrorPolicy::Abort`], if any
+    pub fn take_error(&mut self) -> Option<Box<dyn Error>> {
+        self.error.take()
+    }
+
+    /// Enables or disables the module `T`, a disabled module keeps its state but its listeners
+    /// are skipped until it is enabled again. Modules are enabled when loaded.
+    ///
+    /// In case the module is not loaded, an error is returned instead.
+    pub fn set_module_enabled<T: Module>(&mut self, enabled: bool) -> Result<(), ModuleError> {
+        let module = self
+            .modules
+            .get_mut(&TypeId::of::<T>())
+            .ok_or(ModuleError::NotFound)?;
+        module.enabled = enabled;
+        Ok(())
+    }
+
+    /// Whether the module `T` is loaded and enabled, see [`Engine::set_module_enabled`]
+    pub fn is_enabled<T: Module>(&self) -> bool {
+        self.modules
+            .get(&TypeId::of::<T>())
+            .is_some_and(|m| m.enabled)
+    }
+
+    /// Type names of the modules listening to the event `E`, in dispatch order
+    pub fn listeners_of<E: Event>(&self) -> Vec<&'static str> {
+        self.subscribers
+            .get(&TypeId::of::<E>())
+            .into_iter()
+            .flatten()
+            .map(|tid| self.modules[tid].name)
+            .collect()
+    }
+
+    /// Logs every event listened to with its listeners in dispatch order, at the debug level
+    pub fn log_listeners(&self) {
+        let mut events = self
+            .subscribers
+            .iter()
+            .map(|(event, modules)| (self.event_names[event], modules))
+            .collect::<Vec<_>>();
+        events.sort_by_key(|(name, _)| *name);
+
+        debug!("Listeners of {} event(s):", events.len());
+        for (event, modules) in events {
+            let listeners = modules
+                .iter()
+                .map(|tid| match self.modules[tid].enabled {
+                    true => self.modules[tid].name.to_string(),
+                    false => format!("{} (disabled)", self.modules[tid].name),
+                })
+                .collect::<Vec<_>>();
+            debug!(" - {event}: {}", listeners.join(", "));
         }
     }
 
@@ -166,15 +369,49 @@ impl Engine {
     pub fn run_with<T: Event>(&mut self, event: T) {
         let mut root_event_queue = EventQueue::new();
         root_event_queue.push(event);
+        self.run_schedule(root_event_queue);
+    }
 
+    /// Dispatches a [`Query`] like [`Engine::run_with`], returning the responses of its listeners
+    pub fn query<Q: 'static, R: 'static>(&mut self, query: Q) -> Vec<R> {
+        let (query, responses) = Query::new(query);
+        self.run_with(query);
+        responses.take()
+    }
+
+    /// Dispatch the events deferred using [`EventQueue::defer`] since the last call,
+    /// events deferred during this call are only dispatched on the next one.
+    ///
+    /// With the `replay` feature, the replayed events due by then are dispatched first.
+    pub fn run_deferred(&mut self) {
+        #[cfg(feature = "replay")]
+        let mut events = self.replay.next_frame();
+        #[cfg(not(feature = "replay"))]
+        let mut events = Vec::new();
+        events.append(&mut self.deferred);
+        if !events.is_empty() {
+            let mut root_event_queue = EventQueue::new();
+            root_event_queue.extend_from_deferred(events);
+            self.run_schedule(root_event_queue);
+        }
+        #[cfg(feature = "replay")]
+        self.replay.end_frame();
+    }
+
+    fn run_schedule(&mut self, mut root_event_queue: EventQueue) {
         #[cfg(feature = "debuglog")]
         debug!("NEW SCHEDULE:");
         while let Some(event) = root_event_queue.take_last() {
             #[cfg(feature = "debuglog")]
             let debug_name = events::DebugName::of(&*event);
 
+            #[cfg(not(feature = "replay"))]
             let mut event = event.as_any();
-            let Some(modules) = self.subscribers.get(&(&*event).type_id()) else {
+            #[cfg(feature = "replay")]
+            let Some(mut event) = self.replay.on_dispatch(event.as_any()) else {
+                continue;
+            };
+            let Some(modules) = self.subscribers.get(&(*event).type_id()) else {
                 #[cfg(feature = "debuglog")]
                 debug!(" ~ No listener for {}", debug_name);
                 continue;
@@ -190,13 +427,41 @@ impl Engine {
             let mut event_queue = EventQueue::new();
 
             for tid in modules {
-                self.modules
-                    .get_mut(tid)
-                    .map(|m| m.handle_event(event.as_mut(), &mut event_queue));
+                if let Some(m) = self.modules.get_mut(tid).filter(|m| m.enabled) {
+                    m.handle_event(event.as_mut(), &mut event_queue);
+                }
+                apply_module_toggles(&mut self.modules, &mut event_queue);
+                for e in event_queue.errors.drain(..) {
+                    match self.error_policy {
+                        ErrorPolicy::Log => error!("A listener failed to handle an event: {e}"),
+                        ErrorPolicy::Abort => {
+                            self.error.get_or_insert(e);
+                        }
+                    }
+                }
+                if self.error.is_some() {
+                    return;
+                }
+                if event_queue.consumed() {
+                    #[cfg(feature = "debuglog")]
+                    debug!(" ~ {} consumed", debug_name);
+                    break;
+                }
             }
 
             root_event_queue.extend(event_queue);
         }
+
+        self.deferred.append(&mut root_event_queue.deferred);
+    }
+}
+
+/// Applies the [`EventQueue::set_module_enabled`] calls made through `queue`
+fn apply_module_toggles(modules: &mut Modules, queue: &mut EventQueue) {
+    for (toggled, enabled) in queue.module_toggles.drain(..) {
+        if let Some(m) = modules.get_mut(&toggled) {
+            m.enabled = enabled;
+        }
     }
 }
 
@@ -206,14 +471,20 @@ type AnyListener = Box<dyn Fn(RefMut<Box<dyn Any>>, &mut dyn Any, &mut EventQueu
 type ModuleState = Rc<RefCell<Box<dyn Any>>>;
 
 struct AnyModule {
+    name: &'static str,
     state: ModuleState,
-    listeners: HashMap<TypeId, Box<dyn Fn(RefMut<Box<dyn Any>>, &mut dyn Any, &mut EventQueue)>>,
+    priority: i32,
+    enabled: bool,
+    listeners: HashMap<TypeId, AnyListener>,
 }
 
 impl AnyModule {
     fn new<T: Module>(state: T) -> AnyModule {
         Self {
+            name: std::any::type_name::<T>(),
             state: Rc::new(RefCell::new(Box::new(state))),
+            priority: T::PRIORITY,
+            enabled: true,
             listeners: T::ListeningTo::raw_listeners()
                 .into_iter()
                 .map(|(tid, callback)| {
@@ -238,7 +509,7 @@ impl AnyModule {
 
     // Should only be called if the module have subscribed to the event!
     fn handle_event(&mut self, event: &mut dyn Any, event_queue: &mut EventQueue) {
-        if let Some(callback) = self.listeners.get(&(&*event).type_id()) {
+        if let Some(callback) = self.listeners.get(&(*event).type_id()) {
             callback((*self.state).borrow_mut(), event, event_queue)
         };
     }
@@ -261,9 +532,293 @@ impl<T: Module> Dependency<T> {
     }
 
     /// Read the module state immutably
+    ///
+    /// Panics if the module is handling an event, see [`Dependency::try_read_state`].
     pub fn read_state(&self) -> Ref<'_, T> {
         Ref::map((*self.state).borrow(), |state| {
             state.downcast_ref::<T>().unwrap()
         })
     }
+
+    /// Read the module state immutably, `None` if it is borrowed mutably,
+    /// which only happens while the module itself is handling an event.
+    pub fn try_read_state(&self) -> Option<Ref<'_, T>> {
+        let state = (*self.state).try_borrow().ok()?;
+        Some(Ref::map(state, |state| state.downcast_ref::<T>().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Root;
+    impl Module for Root {
+        type ListeningTo = ();
+        fn new(_: &mut Engine) -> AnyResult<Self> {
+            Ok(Self)
+        }
+    }
+
+    struct A;
+    impl Module for A {
+        type ListeningTo = ();
+        fn new(ctx: &mut Engine) -> AnyResult<Self> {
+            ctx.dependency::<B>()?;
+            Ok(Self)
+        }
+    }
+
+    struct B;
+    impl Module for B {
+        type ListeningTo = ();
+        fn new(ctx: &mut Engine) -> AnyResult<Self> {
+            ctx.dependency::<C>()?;
+            Ok(Self)
+        }
+    }
+
+    struct C;
+    impl Module for C {
+        type ListeningTo = ();
+        fn new(ctx: &mut Engine) -> AnyResult<Self> {
+            ctx.dependency::<A>()?;
+            Ok(Self)
+        }
+    }
+
+    struct Ping;
+    struct TornDown;
+
+    struct Listening;
+    impl Module for Listening {
+        type ListeningTo = (Ping,);
+        fn new(_: &mut Engine) -> AnyResult<Self> {
+            Ok(Self)
+        }
+        fn teardown(&mut self, queue: &mut EventQueue) {
+            queue.push(TornDown);
+        }
+    }
+    impl events::Listener<Ping> for Listening {
+        fn on_event(&mut self, _: &mut Ping, _: &mut EventQueue) {}
+    }
+
+    struct Dependent;
+    impl Module for Dependent {
+        type ListeningTo = ();
+        fn new(ctx: &mut Engine) -> AnyResult<Self> {
+            ctx.dependency::<Listening>()?;
+            Ok(Self)
+        }
+    }
+
+    #[derive(Default)]
+    struct Bystander {
+        pings: u32,
+        teardowns: u32,
+    }
+    impl Module for Bystander {
+        type ListeningTo = (Ping, TornDown);
+        fn new(_: &mut Engine) -> AnyResult<Self> {
+            Ok(Self::default())
+        }
+    }
+    impl events::Listener<Ping> for Bystander {
+        fn on_event(&mut self, _: &mut Ping, _: &mut EventQueue) {
+            self.pings += 1;
+        }
+    }
+    impl events::Listener<TornDown> for Bystander {
+        fn on_event(&mut self, _: &mut TornDown, _: &mut EventQueue) {
+            self.teardowns += 1;
+        }
+    }
+
+    #[test]
+    fn module_in_use_is_not_removed() {
+        let mut engine = Engine::new_without_logger::<Root>();
+        engine.dependency::<Dependent>().unwrap();
+
+        let Err(ModuleError::InUse) = engine.remove_module::<Listening>() else {
+            panic!("A module depended on was removed");
+        };
+        assert!(engine.is_loaded::<Listening>());
+
+        engine.remove_module::<Dependent>().unwrap();
+        engine.remove_module::<Listening>().unwrap();
+        let Err(ModuleError::NotFound) = engine.remove_module::<Listening>() else {
+            panic!("A module was removed twice");
+        };
+    }
+
+    #[test]
+    fn module_loaded_by_the_entrypoint_is_removed() {
+        struct Loader;
+        impl Module for Loader {
+            type ListeningTo = ();
+            fn new(ctx: &mut Engine) -> AnyResult<Self> {
+                ctx.dependency::<Listening>()?;
+                Ok(Self)
+            }
+        }
+
+        let mut engine = Engine::new_without_logger::<Loader>();
+        engine.remove_module::<Listening>().unwrap();
+        assert!(!engine.is_loaded::<Listening>());
+    }
+
+    #[test]
+    fn removed_module_is_torn_down() {
+        let mut engine = Engine::new_without_logger::<Root>();
+        engine.dependency::<Bystander>().unwrap();
+        engine.dependency::<Listening>().unwrap();
+
+        engine.run_with(Ping);
+        engine.remove_module::<Listening>().unwrap();
+        engine.run_with(Ping);
+
+        // The other listeners of the same event are kept
+        let bystander = engine.module::<Bystander>().unwrap();
+        assert_eq!(bystander.pings, 2);
+        assert_eq!(bystander.teardowns, 1);
+    }
+
+    #[test]
+    fn listeners_are_listed_in_dispatch_order() {
+        struct Urgent;
+        impl Module for Urgent {
+            type ListeningTo = (Ping,);
+            const PRIORITY: i32 = 1;
+            fn new(_: &mut Engine) -> AnyResult<Self> {
+                Ok(Self)
+            }
+        }
+        impl events::Listener<Ping> for Urgent {
+            fn on_event(&mut self, _: &mut Ping, _: &mut EventQueue) {}
+        }
+
+        let mut engine = Engine::new_without_logger::<Root>();
+        engine.dependency::<Bystander>().unwrap();
+        engine.dependency::<Listening>().unwrap();
+        engine.dependency::<Urgent>().unwrap();
+
+        assert_eq!(
+            engine.listeners_of::<Ping>(),
+            [
+                std::any::type_name::<Urgent>(),
+                std::any::type_name::<Bystander>(),
+                std::any::type_name::<Listening>(),
+            ]
+        );
+        assert!(engine.listeners_of::<Root>().is_empty());
+    }
+
+    #[test]
+    fn query_collects_responses() {
+        struct Double;
+        impl Module for Double {
+            type ListeningTo = (Query<u32, u32>,);
+            fn new(_: &mut Engine) -> AnyResult<Self> {
+                Ok(Self)
+            }
+        }
+        impl events::Listener<Query<u32, u32>> for Double {
+            fn on_event(&mut self, event: &mut Query<u32, u32>, _: &mut EventQueue) {
+                event.respond(event.query * 2);
+            }
+        }
+
+        let mut engine = Engine::new_without_logger::<Root>();
+        assert!(engine.query::<u32, u32>(1).is_empty());
+        engine.dependency::<Double>().unwrap();
+        assert_eq!(engine.query::<u32, u32>(2), [4]);
+    }
+
+    #[test]
+    fn disabled_module_keeps_its_state() {
+        let mut engine = Engine::new_without_logger::<Root>();
+        engine.dependency::<Bystander>().unwrap();
+
+        engine.run_with(Ping);
+        engine.set_module_enabled::<Bystander>(false).unwrap();
+        assert!(!engine.is_enabled::<Bystander>());
+        engine.run_with(Ping);
+        engine.set_module_enabled::<Bystander>(true).unwrap();
+        engine.run_with(Ping);
+
+        assert_eq!(engine.module::<Bystander>().unwrap().pings, 2);
+        let Err(ModuleError::NotFound) = engine.set_module_enabled::<Listening>(false) else {
+            panic!("A module that is not loaded was disabled");
+        };
+    }
+
+    #[test]
+    fn module_state_is_read_from_another_listener() {
+        struct Observer {
+            bystander: Dependency<Bystander>,
+            seen: Vec<u32>,
+        }
+        impl Module for Observer {
+            type ListeningTo = (Ping,);
+            // Dispatched after the bystander counted the ping
+            const PRIORITY: i32 = -1;
+            fn new(ctx: &mut Engine) -> AnyResult<Self> {
+                Ok(Self {
+                    bystander: ctx.dependency()?,
+                    seen: Vec::new(),
+                })
+            }
+        }
+        impl events::Listener<Ping> for Observer {
+            fn on_event(&mut self, _: &mut Ping, _: &mut EventQueue) {
+                self.seen.push(self.bystander.read_state().pings);
+            }
+        }
+
+        let mut engine = Engine::new_without_logger::<Root>();
+        engine.dependency::<Observer>().unwrap();
+        engine.run_with(Ping);
+        engine.module_mut::<Bystander>().unwrap().pings = 10;
+        engine.run_with(Ping);
+
+        assert_eq!(engine.module::<Observer>().unwrap().seen, [1, 11]);
+        assert!(engine.module::<Listening>().is_none());
+    }
+
+    /// Returns the innermost module error, unwrapping initialization errors
+    fn root_cause(e: &ModuleError) -> &ModuleError {
+        match e {
+            ModuleError::InitError(e) => match e.downcast_ref::<ModuleError>() {
+                Some(e) => root_cause(e),
+                None => panic!("Unexpected initialization error: {e}"),
+            },
+            e => e,
+        }
+    }
+
+    #[test]
+    fn dependency_cycle_is_reported() {
+        let mut engine = Engine::new_without_logger::<Root>();
+
+        let Err(e) = engine.dependency::<A>() else {
+            panic!("A dependency cycle was not detected");
+        };
+        let ModuleError::Cycle(cycle) = root_cause(&e) else {
+            panic!("Unexpected error: {e}");
+        };
+        assert_eq!(
+            cycle,
+            &[
+                std::any::type_name::<A>(),
+                std::any::type_name::<B>(),
+                std::any::type_name::<C>(),
+                std::any::type_name::<A>(),
+            ]
+        );
+
+        assert!(!engine.is_loaded::<A>());
+        assert!(!engine.is_loaded::<B>());
+        assert!(!engine.is_loaded::<C>());
+    }
 }
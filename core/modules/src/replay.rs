@@ -0,0 +1,329 @@
+//! Recording of the events given to the [`Engine`] and their replay, to reproduce a session.
+//!
+//! Only the events of the types registered with [`Engine::register_replayable`] are recorded,
+//! whenever they are dispatched: given to the engine, pushed by listeners or deferred.
+//! During a replay, the other events of those types are dropped so that the ones pushed again
+//! by the listeners of replayed events are only dispatched once.
+
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+use rgine_logger::{info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{events::Event, Engine};
+
+/// When the events of a replay are dispatched again
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReplayTiming {
+    /// After the same time as when recorded, since the replay started
+    #[default]
+    Realtime,
+    /// Before the deferred events of the same [`Engine::run_deferred`] call as when recorded,
+    /// counted since the replay started, events dispatched between two calls being replayed
+    /// by the second one. The platforms call it once per frame, which makes headless replays
+    /// deterministic
+    Frames,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    /// Calls to `run_deferred` completed since the recording started
+    frame: u64,
+    /// Seconds since the recording started
+    time: f64,
+    event: String,
+    data: Vec<u8>,
+}
+
+struct ReplayableEvent {
+    name: &'static str,
+    serialize: fn(&dyn Any) -> bincode::Result<Vec<u8>>,
+}
+type EventDeserializer = fn(&[u8]) -> bincode::Result<Box<dyn Event>>;
+
+struct Recording {
+    file: BufWriter<File>,
+    start: Instant,
+    start_frame: u64,
+}
+
+struct Replaying {
+    records: VecDeque<Record>,
+    timing: ReplayTiming,
+    start: Instant,
+    start_frame: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct Replay {
+    events: HashMap<TypeId, ReplayableEvent>,
+    deserializers: HashMap<&'static str, EventDeserializer>,
+    recording: Option<Recording>,
+    replaying: Option<Replaying>,
+    /// Calls to `run_deferred` completed so far
+    frame: u64,
+}
+
+/// Event read from a replay, dispatched even though its type is replayable
+struct Replayed(Box<dyn Event>);
+
+fn serialize<T: Serialize + 'static>(event: &dyn Any) -> bincode::Result<Vec<u8>> {
+    bincode::serialize(event.downcast_ref::<T>().unwrap())
+}
+
+fn deserialize<T: Event + DeserializeOwned>(data: &[u8]) -> bincode::Result<Box<dyn Event>> {
+    Ok(Box::new(bincode::deserialize::<T>(data)?))
+}
+
+impl Replay {
+    /// Records `event` if needed, returns it unless it should be dropped.
+    ///
+    /// Events of replayable types are dropped during a replay, except the replayed ones.
+    pub(crate) fn on_dispatch(&mut self, event: Box<dyn Any>) -> Option<Box<dyn Any>> {
+        let event = match event.downcast::<Replayed>() {
+            Ok(replayed) => return Some(replayed.0.as_any()),
+            Err(event) => event,
+        };
+        let Some(replayable) = self.events.get(&(*event).type_id()) else {
+            return Some(event);
+        };
+        if self.replaying.is_some() {
+            return None;
+        }
+        let Some(recording) = &mut self.recording else {
+            return Some(event);
+        };
+
+        let record = (replayable.serialize)(&*event).map(|data| Record {
+            frame: self.frame - recording.start_frame,
+            time: recording.start.elapsed().as_secs_f64(),
+            event: replayable.name.to_string(),
+            data,
+        });
+        if let Err(e) = record.and_then(|r| bincode::serialize_into(&mut recording.file, &r)) {
+            warn!(
+                "Unable to record {}, recording stopped! error: {e}",
+                replayable.name
+            );
+            self.recording = None;
+        }
+        Some(event)
+    }
+
+    /// Returns the replayed events due before the deferred events of the current frame
+    pub(crate) fn next_frame(&mut self) -> Vec<Box<dyn Event>> {
+        let Some(replaying) = &mut self.replaying else {
+            return Vec::new();
+        };
+
+        let mut events = Vec::new();
+        let frame = self.frame - replaying.start_frame;
+        let time = replaying.start.elapsed().as_secs_f64();
+        while let Some(record) = replaying.records.front() {
+            let due = match replaying.timing {
+                ReplayTiming::Realtime => record.time <= time,
+                ReplayTiming::Frames => record.frame <= frame,
+            };
+            if !due {
+                break;
+            }
+            let record = replaying.records.pop_front().unwrap();
+            // Registered types are checked when the replay starts
+            match (self.deserializers[record.event.as_str()])(&record.data) {
+                Ok(event) => events.push(Box::new(Replayed(event)) as Box<dyn Event>),
+                Err(e) => warn!("Unable to replay {}! error: {e}", record.event),
+            }
+        }
+
+        if replaying.records.is_empty() {
+            info!("Replay finished");
+            self.replaying = None;
+        }
+        events
+    }
+
+    /// Ends the current frame, once its deferred events are dispatched
+    pub(crate) fn end_frame(&mut self) {
+        self.frame += 1;
+    }
+}
+
+impl Engine {
+    /// Allows the events `T` to be recorded and replayed
+    pub fn register_replayable<T: Event + Serialize + DeserializeOwned>(&mut self) {
+        let name = std::any::type_name::<T>();
+        self.replay.events.insert(
+            TypeId::of::<T>(),
+            ReplayableEvent {
+                name,
+                serialize: serialize::<T>,
+            },
+        );
+        self.replay.deserializers.insert(name, deserialize::<T>);
+    }
+
+    /// Records the replayable events dispatched by the engine to the file at `path`, until
+    /// [`Engine::stop_recording`] is called or the engine is dropped.
+    pub fn start_recording(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.replay.recording = Some(Recording {
+            file: BufWriter::new(File::create(path)?),
+            start: Instant::now(),
+            start_frame: self.replay.frame,
+        });
+        Ok(())
+    }
+
+    /// Stops the recording, writing the events left to its file
+    pub fn stop_recording(&mut self) -> io::Result<()> {
+        match self.replay.recording.take() {
+            Some(mut recording) => recording.file.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Dispatches the events recorded to the file at `path` again, with their original `timing`.
+    ///
+    /// The other replayable events are dropped until the replay is finished.
+    /// Every event type of the recording must have been registered with
+    /// [`Engine::register_replayable`], otherwise an error is returned.
+    pub fn start_replay(&mut self, path: impl AsRef<Path>, timing: ReplayTiming) -> io::Result<()> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut records = VecDeque::new();
+        loop {
+            match bincode::deserialize_from::<_, Record>(&mut file) {
+                Ok(record) => records.push_back(record),
+                Err(e) => match *e {
+                    bincode::ErrorKind::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                    e => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                },
+            }
+        }
+        if let Some(record) = records
+            .iter()
+            .find(|r| !self.replay.deserializers.contains_key(r.event.as_str()))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the event {} is not registered as replayable", record.event),
+            ));
+        }
+
+        self.replay.replaying = Some(Replaying {
+            records,
+            timing,
+            start: Instant::now(),
+            start_frame: self.replay.frame,
+        });
+        Ok(())
+    }
+
+    /// Whether recorded events are being replayed, see [`Engine::start_replay`]
+    pub fn is_replaying(&self) -> bool {
+        self.replay.replaying.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::{EventQueue, Listener},
+        AnyResult, Module,
+    };
+
+    #[derive(Serialize, Deserialize)]
+    struct Input(u32);
+
+    /// Not replayable, its listener pushes an input
+    struct Tick(u32);
+
+    #[derive(Default)]
+    struct Inputs(Vec<u32>);
+    impl Module for Inputs {
+        type ListeningTo = (Input, Tick);
+        fn new(_: &mut Engine) -> AnyResult<Self> {
+            Ok(Self::default())
+        }
+    }
+    impl Listener<Input> for Inputs {
+        fn on_event(&mut self, event: &mut Input, _: &mut EventQueue) {
+            self.0.push(event.0);
+        }
+    }
+    impl Listener<Tick> for Inputs {
+        fn on_event(&mut self, event: &mut Tick, queue: &mut EventQueue) {
+            queue.push(Input(event.0 * 10));
+        }
+    }
+
+    /// Path in the temp dir unique to this test process and `test`
+    fn replay_path(test: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("rgine_replay_{}_{test}.bin", std::process::id()))
+    }
+
+    #[test]
+    fn recorded_events_are_replayed_on_the_same_frames() {
+        let path = replay_path("recorded_events_are_replayed_on_the_same_frames");
+
+        let mut engine = Engine::new_without_logger::<Inputs>();
+        engine.register_replayable::<Input>();
+        engine.start_recording(&path).unwrap();
+        engine.run_with(Input(1));
+        engine.run_deferred();
+        engine.run_deferred();
+        engine.run_with(Input(2));
+        engine.stop_recording().unwrap();
+
+        let mut engine = Engine::new_without_logger::<Inputs>();
+        engine.register_replayable::<Input>();
+        engine.start_replay(&path, ReplayTiming::Frames).unwrap();
+        // Events given to the engine are ignored while replaying
+        engine.run_with(Input(3));
+        engine.run_deferred();
+        assert_eq!(engine.module::<Inputs>().unwrap().0, [1]);
+        engine.run_deferred();
+        engine.run_deferred();
+        assert_eq!(engine.module::<Inputs>().unwrap().0, [1, 2]);
+        assert!(!engine.is_replaying());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn events_pushed_by_listeners_are_recorded_and_replayed_once() {
+        let path = replay_path("events_pushed_by_listeners_are_recorded_and_replayed_once");
+
+        let mut engine = Engine::new_without_logger::<Inputs>();
+        engine.register_replayable::<Input>();
+        engine.start_recording(&path).unwrap();
+        engine.run_with(Tick(1));
+        engine.run_deferred();
+        engine.run_with(Tick(2));
+        engine.stop_recording().unwrap();
+        assert_eq!(engine.module::<Inputs>().unwrap().0, [10, 20]);
+
+        let mut engine = Engine::new_without_logger::<Inputs>();
+        engine.register_replayable::<Input>();
+        engine.start_replay(&path, ReplayTiming::Frames).unwrap();
+        // The inputs pushed again by the ticks are dropped in favor of the recorded ones
+        engine.run_with(Tick(3));
+        assert!(engine.module::<Inputs>().unwrap().0.is_empty());
+        engine.run_deferred();
+        assert_eq!(engine.module::<Inputs>().unwrap().0, [10]);
+        engine.run_deferred();
+        assert_eq!(engine.module::<Inputs>().unwrap().0, [10, 20]);
+        assert!(!engine.is_replaying());
+        // Once the replay is finished, pushed inputs are dispatched again
+        engine.run_with(Tick(4));
+        assert_eq!(engine.module::<Inputs>().unwrap().0, [10, 20, 40]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    any::{Any, TypeId},
+    cell::{Ref, RefCell, RefMut},
+    collections::HashMap,
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use crate::Engine;
+
+type ResourceState = Rc<RefCell<Box<dyn Any>>>;
+pub(crate) type Resources = HashMap<TypeId, ResourceState>;
+
+/// Shared state without listeners nor lifecycle, unlike modules.
+///
+/// Resources are stored by type in the [`Engine`]:
+/// - [`Engine::insert_resource`] inserts or replaces one
+/// - [`Engine::resource`] and [`Engine::resource_mut`] borrow one
+/// - [`Engine::resource_handle`] returns a [`Resource<T>`] that can be kept by a module to
+///   borrow the resource from its listeners
+impl Engine {
+    /// Inserts the resource `T`, replacing the previous one if any.
+    ///
+    /// Existing handles to a replaced resource see the new value.
+    pub fn insert_resource<T: 'static>(&mut self, value: T) {
+        match self.resources.get(&TypeId::of::<T>()) {
+            Some(state) => *state.borrow_mut() = Box::new(value),
+            None => {
+                self.resources
+                    .insert(TypeId::of::<T>(), Rc::new(RefCell::new(Box::new(value))));
+            }
+        }
+    }
+
+    /// Removes the resource `T` and returns it, if nothing else holds a handle to it.
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        let state = self.resources.remove(&TypeId::of::<T>())?;
+        match Rc::try_unwrap(state) {
+            Ok(state) => Some(*state.into_inner().downcast::<T>().unwrap()),
+            Err(state) => {
+                self.resources.insert(TypeId::of::<T>(), state);
+                None
+            }
+        }
+    }
+
+    pub fn resource<T: 'static>(&self) -> Option<Ref<'_, T>> {
+        let state = self.resources.get(&TypeId::of::<T>())?;
+        Some(Ref::map(state.borrow(), |s| s.downcast_ref::<T>().unwrap()))
+    }
+
+    pub fn resource_mut<T: 'static>(&mut self) -> Option<RefMut<'_, T>> {
+        let state = self.resources.get(&TypeId::of::<T>())?;
+        Some(RefMut::map(state.borrow_mut(), |s| {
+            s.downcast_mut::<T>().unwrap()
+        }))
+    }
+
+    /// Returns a handle to the resource `T`, `None` if it was not inserted.
+    pub fn resource_handle<T: 'static>(&self) -> Option<Resource<T>> {
+        Some(Resource {
+            _marker: PhantomData,
+            state: self.resources.get(&TypeId::of::<T>())?.clone(),
+        })
+    }
+}
+
+/// A handle to the resource `T`, that can be borrowed during event dispatch.
+pub struct Resource<T> {
+    _marker: PhantomData<T>,
+    state: ResourceState,
+}
+
+impl<T: 'static> Resource<T> {
+    /// Panics if the resource is borrowed mutably.
+    pub fn read(&self) -> Ref<'_, T> {
+        Ref::map(self.state.borrow(), |s| s.downcast_ref::<T>().unwrap())
+    }
+
+    /// Panics if the resource is already borrowed.
+    pub fn write(&self) -> RefMut<'_, T> {
+        RefMut::map(self.state.borrow_mut(), |s| s.downcast_mut::<T>().unwrap())
+    }
+}
+
+impl<T> Clone for Resource<T> {
+    fn clone(&self) -> Self {
+        Self {
+            _marker: PhantomData,
+            state: self.state.clone(),
+        }
+    }
+}
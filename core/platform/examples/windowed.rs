@@ -5,8 +5,8 @@ use rgine_platform::window::{
     WindowPlatformEngineExt,
 };
 
-fn main() {
-    Engine::new::<ExampleModule>().run_windowed(WindowPlatformConfig::default());
+fn main() -> AnyResult<()> {
+    Engine::new::<ExampleModule>().run_windowed(WindowPlatformConfig::default())
 }
 
 struct ExampleModule;
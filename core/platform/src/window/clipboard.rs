@@ -0,0 +1,83 @@
+use std::cell::{OnceCell, RefCell};
+
+use arboard::Clipboard;
+use rgine_logger::warn;
+use rgine_modules::{
+    events::{EventQueue, Listener},
+    Engine, Module,
+};
+
+/// Replaces the text of the system clipboard
+pub struct SetClipboardTextEvent {
+    pub text: String,
+}
+
+/// Reads and writes the text of the system clipboard, loaded by the platform on startup
+pub struct ClipboardModule {
+    /// Opened on first use, `None` if not available. It must stay alive for the copied text to
+    /// stay available on X11
+    clipboard: OnceCell<Option<RefCell<Clipboard>>>,
+}
+
+impl ClipboardModule {
+    /// Text of the system clipboard, `None` if it is empty, holds non-text data or is
+    /// not available on this platform
+    pub fn get_clipboard_text(&self) -> Option<String> {
+        self.with_clipboard(|clipboard| match clipboard.get_text() {
+            Ok(text) => Some(text),
+            Err(arboard::Error::ContentNotAvailable) => None,
+            Err(e) => {
+                warn!("Unable to read the clipboard: {e}");
+                None
+            }
+        })
+        .flatten()
+    }
+
+    /// Replaces the text of the system clipboard, see also [`ClipboardEventQueueExt::set_clipboard_text`]
+    pub fn set_clipboard_text(&self, text: &str) {
+        self.with_clipboard(|clipboard| {
+            if let Err(e) = clipboard.set_text(text) {
+                warn!("Unable to write to the clipboard: {e}");
+            }
+        });
+    }
+
+    fn with_clipboard<R>(&self, f: impl FnOnce(&mut Clipboard) -> R) -> Option<R> {
+        let clipboard = self.clipboard.get_or_init(|| {
+            Clipboard::new()
+                .map_err(|e| warn!("The clipboard is not available! error: {e}"))
+                .ok()
+                .map(RefCell::new)
+        });
+        clipboard
+            .as_ref()
+            .map(|clipboard| f(&mut clipboard.borrow_mut()))
+    }
+}
+
+impl Module for ClipboardModule {
+    type ListeningTo = (SetClipboardTextEvent,);
+    fn new(_: &mut Engine) -> rgine_modules::AnyResult<Self> {
+        Ok(Self {
+            clipboard: OnceCell::new(),
+        })
+    }
+}
+
+impl Listener<SetClipboardTextEvent> for ClipboardModule {
+    fn on_event(&mut self, event: &mut SetClipboardTextEvent, _: &mut EventQueue) {
+        self.set_clipboard_text(&event.text);
+    }
+}
+
+pub trait ClipboardEventQueueExt {
+    /// Replaces the text of the system clipboard, it can be read back with
+    /// [`ClipboardModule::get_clipboard_text`]
+    fn set_clipboard_text(&mut self, text: impl Into<String>);
+}
+impl ClipboardEventQueueExt for EventQueue {
+    fn set_clipboard_text(&mut self, text: impl Into<String>) {
+        self.push(SetClipboardTextEvent { text: text.into() })
+    }
+}
@@ -0,0 +1,118 @@
+use gilrs::{EventType, Gilrs};
+use rgine_logger::warn;
+use rgine_modules::{
+    events::{EventQueue, Listener},
+    Engine, Module,
+};
+use winit::event::ElementState;
+
+pub use gilrs::{Axis as GamepadAxis, Button as GamepadButton};
+
+use super::OnWindowPlatformUpdate;
+
+/// Emitted when a button of the gamepad `id` is pressed or released
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct GamepadButtonEvent {
+    pub id: usize,
+    pub button: GamepadButton,
+    pub state: ElementState,
+}
+
+/// Emitted when a stick or analog trigger of the gamepad `id` moves
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct GamepadAxisEvent {
+    pub id: usize,
+    pub axis: GamepadAxis,
+    /// Position between -1 and 1, positive values meaning right and up for sticks
+    pub value: f32,
+}
+
+/// Emitted when the gamepad `id` is connected or disconnected.
+///
+/// A gamepad connected again keeps its id, gamepads already connected on startup are
+/// reported on the first platform update.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct GamepadConnectionEvent {
+    pub id: usize,
+    pub connected: bool,
+}
+
+/// Polls the gamepads on every [`OnWindowPlatformUpdate`], loaded by the platform on startup
+pub struct GamepadModule {
+    /// `None` if the gamepad backend is not available on this platform
+    gilrs: Option<Gilrs>,
+    reported_initial: bool,
+}
+
+impl GamepadModule {
+    /// Ids of the gamepads currently connected
+    pub fn connected(&self) -> Vec<usize> {
+        self.gilrs
+            .iter()
+            .flat_map(|gilrs| gilrs.gamepads())
+            .map(|(id, _)| id.into())
+            .collect()
+    }
+}
+
+impl Module for GamepadModule {
+    type ListeningTo = (OnWindowPlatformUpdate,);
+    fn new(_: &mut Engine) -> rgine_modules::AnyResult<Self> {
+        let gilrs = Gilrs::new()
+            .inspect_err(|e| warn!("Gamepads are not available! error: {e}"))
+            .ok();
+        Ok(Self {
+            gilrs,
+            reported_initial: false,
+        })
+    }
+}
+
+impl Listener<OnWindowPlatformUpdate> for GamepadModule {
+    fn on_event(&mut self, _: &mut OnWindowPlatformUpdate, queue: &mut EventQueue) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+
+        if !self.reported_initial {
+            self.reported_initial = true;
+            for (id, _) in gilrs.gamepads() {
+                queue.push(GamepadConnectionEvent {
+                    id: id.into(),
+                    connected: true,
+                });
+            }
+        }
+
+        while let Some(event) = gilrs.next_event() {
+            let id = event.id.into();
+            match event.event {
+                EventType::ButtonPressed(button, _) => queue.push(GamepadButtonEvent {
+                    id,
+                    button,
+                    state: ElementState::Pressed,
+                }),
+                EventType::ButtonReleased(button, _) => queue.push(GamepadButtonEvent {
+                    id,
+                    button,
+                    state: ElementState::Released,
+                }),
+                EventType::AxisChanged(axis, value, _) => {
+                    queue.push(GamepadAxisEvent { id, axis, value })
+                }
+                EventType::Connected => queue.push(GamepadConnectionEvent {
+                    id,
+                    connected: true,
+                }),
+                EventType::Disconnected => queue.push(GamepadConnectionEvent {
+                    id,
+                    connected: false,
+                }),
+                _ => (),
+            }
+        }
+    }
+}
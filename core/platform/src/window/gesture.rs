@@ -0,0 +1,240 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use rgine_modules::{
+    events::{EventQueue, Listener},
+    Engine, Module,
+};
+use winit::{
+    event::{ElementState, MouseButton},
+    window::WindowId,
+};
+
+use super::{
+    input::{CursorMovedEvent, MouseButtonEvent},
+    module::WindowFocusedEvent,
+};
+
+/// Thresholds used to recognize gestures, see [`SetGestureConfigEvent`]
+#[derive(Clone, Copy, Debug)]
+pub struct GestureConfig {
+    /// Longest time between the two presses of a double click
+    pub double_click_interval: Duration,
+    /// Farthest distance in physical pixels between the two presses of a double click
+    pub double_click_distance: f64,
+    /// Distance in physical pixels the cursor must move while pressed to start a drag
+    pub drag_threshold: f64,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            double_click_interval: Duration::from_millis(500),
+            double_click_distance: 4.,
+            drag_threshold: 4.,
+        }
+    }
+}
+
+pub struct SetGestureConfigEvent {
+    pub config: GestureConfig,
+}
+
+/// Emitted on the second press of `button` when it follows the first one closely enough
+#[derive(Clone, Copy, Debug)]
+pub struct DoubleClickEvent {
+    /// Window the event happened in
+    pub window: WindowId,
+    pub button: MouseButton,
+    /// Cursor position in physical pixels from the top left corner of the window
+    pub position: (f64, f64),
+}
+
+/// Emitted whenever the cursor moves while `button` is held down, once it moved farther
+/// than [`GestureConfig::drag_threshold`] from where it was pressed
+#[derive(Clone, Copy, Debug)]
+pub struct DragEvent {
+    /// Window the event happened in
+    pub window: WindowId,
+    pub button: MouseButton,
+    /// Cursor position where the button was pressed, in physical pixels
+    pub start: (f64, f64),
+    /// Current cursor position, in physical pixels
+    pub current: (f64, f64),
+}
+
+/// Emitted when `button` is released after a [`DragEvent`], or the window lost the focus
+#[derive(Clone, Copy, Debug)]
+pub struct DragEndedEvent {
+    /// Window the event happened in
+    pub window: WindowId,
+    pub button: MouseButton,
+    /// Cursor position where the button was pressed, in physical pixels
+    pub start: (f64, f64),
+    /// Cursor position when the drag ended, in physical pixels
+    pub end: (f64, f64),
+}
+
+struct Press {
+    window: WindowId,
+    start: (f64, f64),
+    current: (f64, f64),
+    dragging: bool,
+}
+
+struct Click {
+    window: WindowId,
+    button: MouseButton,
+    position: (f64, f64),
+    time: Instant,
+}
+
+/// Recognizes double clicks and drags from the mouse events, loaded by the windowed platform
+pub struct GestureModule {
+    pub config: GestureConfig,
+    presses: HashMap<MouseButton, Press>,
+    last_click: Option<Click>,
+}
+
+impl Module for GestureModule {
+    type ListeningTo = (
+        MouseButtonEvent,
+        CursorMovedEvent,
+        WindowFocusedEvent,
+        SetGestureConfigEvent,
+    );
+    fn new(_: &mut Engine) -> rgine_modules::AnyResult<Self> {
+        Ok(Self {
+            config: GestureConfig::default(),
+            presses: HashMap::new(),
+            last_click: None,
+        })
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+impl GestureModule {
+    fn end_drag(button: MouseButton, press: Press, queue: &mut EventQueue) {
+        if press.dragging {
+            queue.push(DragEndedEvent {
+                window: press.window,
+                button,
+                start: press.start,
+                end: press.current,
+            });
+        }
+    }
+}
+
+impl Listener<MouseButtonEvent> for GestureModule {
+    fn on_event(&mut self, event: &mut MouseButtonEvent, queue: &mut EventQueue) {
+        let MouseButtonEvent {
+            window,
+            button,
+            state,
+            position,
+        } = *event;
+
+        match state {
+            ElementState::Pressed => {
+                let now = Instant::now();
+                let double_click = self.last_click.as_ref().is_some_and(|click| {
+                    click.window == window
+                        && click.button == button
+                        && now - click.time <= self.config.double_click_interval
+                        && distance(click.position, position) <= self.config.double_click_distance
+                });
+                // A third press starts a new double click instead of completing another one
+                self.last_click = if double_click {
+                    queue.push(DoubleClickEvent {
+                        window,
+                        button,
+                        position,
+                    });
+                    None
+                } else {
+                    Some(Click {
+                        window,
+                        button,
+                        position,
+                        time: now,
+                    })
+                };
+
+                self.presses.insert(
+                    button,
+                    Press {
+                        window,
+                        start: position,
+                        current: position,
+                        dragging: false,
+                    },
+                );
+            }
+            ElementState::Released => {
+                if let Some(press) = self.presses.remove(&button) {
+                    Self::end_drag(button, press, queue);
+                }
+            }
+        }
+    }
+}
+
+impl Listener<CursorMovedEvent> for GestureModule {
+    fn on_event(&mut self, event: &mut CursorMovedEvent, queue: &mut EventQueue) {
+        for (button, press) in &mut self.presses {
+            if press.window != event.window {
+                continue;
+            }
+            press.current = event.position;
+            if !press.dragging && distance(press.start, press.current) > self.config.drag_threshold
+            {
+                press.dragging = true;
+                // Dragging between two presses does not make a double click
+                if self
+                    .last_click
+                    .as_ref()
+                    .is_some_and(|c| c.button == *button)
+                {
+                    self.last_click = None;
+                }
+            }
+            if press.dragging {
+                queue.push(DragEvent {
+                    window: press.window,
+                    button: *button,
+                    start: press.start,
+                    current: press.current,
+                });
+            }
+        }
+    }
+}
+
+impl Listener<WindowFocusedEvent> for GestureModule {
+    fn on_event(&mut self, event: &mut WindowFocusedEvent, queue: &mut EventQueue) {
+        // Releases are not reported to windows without the focus
+        if event.focused {
+            return;
+        }
+        let presses = std::mem::take(&mut self.presses);
+        for (button, press) in presses {
+            if press.window == event.window {
+                Self::end_drag(button, press, queue);
+            } else {
+                self.presses.insert(button, press);
+            }
+        }
+    }
+}
+
+impl Listener<SetGestureConfigEvent> for GestureModule {
+    fn on_event(&mut self, event: &mut SetGestureConfigEvent, _: &mut EventQueue) {
+        self.config = event.config;
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use rgine_modules::{
+    standards::{ShutdownEvent, StartEvent},
+    AnyResult, Engine,
+};
+
+use super::{
+    module::{WindowPlatformModule, WindowRenderReadyEvent},
+    UpdateTimer, WindowPlatformConfig, WindowReadyEvent,
+};
+
+/// Size of the rendered frames when [`WindowPlatformConfig::size`] is `None`
+pub const HEADLESS_DEFAULT_SIZE: (u32, u32) = (800, 600);
+
+/// Runs the engine without a window nor an event loop, for tests and servers.
+///
+/// The same events as the windowed platform are emitted, except window and input events.
+/// Frames are rendered to an offscreen texture of [`WindowPlatformConfig::size`] pixels,
+/// which can be read back with `GraphicsCtx::read_frame`.
+pub trait HeadlessEngineExt {
+    /// Starts the engine, emitting the [`StartEvent`] then the [`WindowReadyEvent`]
+    fn start_headless(&mut self, config: WindowPlatformConfig);
+    /// Advances the engine by `dt` then renders a frame, the engine must have been started
+    /// with [`HeadlessEngineExt::start_headless`]
+    fn step_headless(&mut self, dt: Duration);
+    /// Runs `frames` steps of `1 / fixed_update_rate` seconds then shuts the engine down,
+    /// returning the error of a listener failing with
+    /// [`ErrorPolicy::Abort`](rgine_modules::ErrorPolicy::Abort).
+    fn run_headless(self, config: WindowPlatformConfig, frames: u32) -> AnyResult<()>;
+}
+
+/// State of the headless loop, kept by the [`WindowPlatformModule`] between the steps
+pub(crate) struct HeadlessState {
+    fixed_update_rate: u32,
+    timer: UpdateTimer,
+}
+
+impl HeadlessEngineExt for Engine {
+    fn start_headless(&mut self, config: WindowPlatformConfig) {
+        self.dependency::<WindowPlatformModule>().expect(
+            "Failed to load window platform module from platform layer on headless platform.",
+        );
+        #[cfg(feature = "clipboard")]
+        self.dependency::<super::clipboard::ClipboardModule>()
+            .expect("Failed to load clipboard module from platform layer on headless platform.");
+        #[cfg(feature = "gamepad")]
+        self.dependency::<super::gamepad::GamepadModule>()
+            .expect("Failed to load gamepad module from platform layer on headless platform.");
+        self.dependency::<super::gesture::GestureModule>()
+            .expect("Failed to load gesture module from platform layer on headless platform.");
+        self.log_listeners();
+        self.run_with(StartEvent);
+
+        let mut platform = self.module_mut::<WindowPlatformModule>().unwrap();
+        platform.headless = Some(HeadlessState {
+            fixed_update_rate: config.fixed_update_rate,
+            timer: UpdateTimer::default(),
+        });
+        platform.config.set(config).unwrap();
+        drop(platform);
+        self.run_with(WindowReadyEvent);
+    }
+
+    fn step_headless(&mut self, dt: Duration) {
+        // Taken during the step, the engine is borrowed to update it
+        let mut state = self
+            .module_mut::<WindowPlatformModule>()
+            .and_then(|mut platform| platform.headless.take())
+            .expect("Tried to step a headless engine that was not started!");
+
+        self.run_deferred();
+        state.timer.update(self, dt, state.fixed_update_rate);
+        let fixed_update_alpha = self
+            .dependency::<WindowPlatformModule>()
+            .unwrap()
+            .read_state()
+            .fixed_update_alpha
+            .get();
+        self.run_with(WindowRenderReadyEvent { fixed_update_alpha });
+
+        self.module_mut::<WindowPlatformModule>().unwrap().headless = Some(state);
+    }
+
+    fn run_headless(mut self, config: WindowPlatformConfig, frames: u32) -> AnyResult<()> {
+        let dt = Duration::from_secs(1) / config.fixed_update_rate.max(1);
+        self.start_headless(config);
+        for _ in 0..frames {
+            if self.has_error() {
+                break;
+            }
+            self.step_headless(dt);
+        }
+        self.run_with(ShutdownEvent);
+        self.take_error().map_or(Ok(()), Err)
+    }
+}
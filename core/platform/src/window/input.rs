@@ -0,0 +1,153 @@
+use std::path::PathBuf;
+
+use rgine_modules::events::ConsumableEvent;
+pub use winit::{
+    event::{ElementState, MouseButton},
+    keyboard::{KeyCode, ModifiersState},
+    window::WindowId,
+};
+
+/// Emitted when a key is pressed or released while the window is focused
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyboardInputEvent {
+    /// Window the event happened in
+    #[cfg_attr(feature = "replay", serde(with = "super::serde_window_id"))]
+    pub window: WindowId,
+    /// Physical key, independent of the keyboard layout
+    pub key: KeyCode,
+    pub state: ElementState,
+    /// Whether the key is held down and this event is an automatic repetition
+    pub repeat: bool,
+    /// Modifier keys held down when the event happened
+    pub modifiers: ModifiersState,
+}
+
+/// Emitted when a mouse button is pressed or released over the window
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct MouseButtonEvent {
+    /// Window the event happened in
+    #[cfg_attr(feature = "replay", serde(with = "super::serde_window_id"))]
+    pub window: WindowId,
+    pub button: MouseButton,
+    pub state: ElementState,
+    /// Cursor position in physical pixels from the top left corner of the window
+    pub position: (f64, f64),
+}
+
+/// Emitted when the cursor moves over the window
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct CursorMovedEvent {
+    /// Window the event happened in
+    #[cfg_attr(feature = "replay", serde(with = "super::serde_window_id"))]
+    pub window: WindowId,
+    /// Cursor position in physical pixels from the top left corner of the window
+    pub position: (f64, f64),
+}
+
+/// Emitted when the cursor enters the window
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct CursorEnteredEvent {
+    #[cfg_attr(feature = "replay", serde(with = "super::serde_window_id"))]
+    pub window: WindowId,
+}
+/// Emitted when the cursor leaves the window
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct CursorLeftEvent {
+    #[cfg_attr(feature = "replay", serde(with = "super::serde_window_id"))]
+    pub window: WindowId,
+}
+
+/// Unit of the deltas of a [`MouseWheelEvent`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScrollUnit {
+    /// Lines or rows to scroll, as reported by most mouse wheels
+    Line,
+    /// Physical pixels to scroll, as reported by touchpads
+    Pixel,
+}
+
+/// Emitted when the mouse wheel or touchpad is scrolled over the window,
+/// positive deltas meaning right and up
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct MouseWheelEvent {
+    /// Window the event happened in
+    #[cfg_attr(feature = "replay", serde(with = "super::serde_window_id"))]
+    pub window: WindowId,
+    pub delta_x: f32,
+    pub delta_y: f32,
+    pub unit: ScrollUnit,
+}
+
+/// Emitted when a file is dropped on the window, once per file
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileDroppedEvent {
+    /// Window the event happened in
+    #[cfg_attr(feature = "replay", serde(with = "super::serde_window_id"))]
+    pub window: WindowId,
+    pub path: PathBuf,
+    /// Cursor position in physical pixels from the top left corner of the window
+    pub position: (f64, f64),
+}
+
+/// Emitted when a file is dragged over the window, once per file
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileHoveredEvent {
+    /// Window the event happened in
+    #[cfg_attr(feature = "replay", serde(with = "super::serde_window_id"))]
+    pub window: WindowId,
+    pub path: PathBuf,
+    /// Cursor position in physical pixels from the top left corner of the window
+    pub position: (f64, f64),
+}
+
+/// Emitted when the files dragged over the window leave it without being dropped
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileHoverCancelledEvent {
+    #[cfg_attr(feature = "replay", serde(with = "super::serde_window_id"))]
+    pub window: WindowId,
+}
+
+/// Emitted when text is typed while the window is focused, either by a key press or once
+/// composed with an input method (see [`WindowEventQueueExt::set_ime_allowed`]).
+///
+/// Control characters such as backspace or enter are not included, they only emit a
+/// [`KeyboardInputEvent`].
+///
+/// [`WindowEventQueueExt::set_ime_allowed`]: super::module::WindowEventQueueExt::set_ime_allowed
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextInputEvent {
+    /// Window the event happened in
+    #[cfg_attr(feature = "replay", serde(with = "super::serde_window_id"))]
+    pub window: WindowId,
+    pub text: String,
+}
+
+/// Emitted while text is being composed with an input method, before the [`TextInputEvent`]
+/// of the composed text. An empty `text` means the composition was cleared
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextPreeditEvent {
+    /// Window the event happened in
+    #[cfg_attr(feature = "replay", serde(with = "super::serde_window_id"))]
+    pub window: WindowId,
+    pub text: String,
+    /// Byte range of `text` to highlight as the cursor or selection, `None` to hide the cursor
+    pub cursor: Option<(usize, usize)>,
+}
+
+impl ConsumableEvent for KeyboardInputEvent {}
+impl ConsumableEvent for TextInputEvent {}
+impl ConsumableEvent for MouseButtonEvent {}
+impl ConsumableEvent for MouseWheelEvent {}
+impl ConsumableEvent for FileDroppedEvent {}
@@ -1,85 +1,425 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use self::module::WindowPlatformModule;
+use self::module::{
+    apply_pending_cursor, apply_window_mode, WindowOpenedEvent, WindowPlatformModule,
+};
+use rgine_logger::warn;
 use rgine_modules::{
     standards::{ShutdownEvent, StartEvent},
-    Engine,
+    AnyResult, Engine,
 };
 use winit::{
     application::ApplicationHandler,
+    dpi::{PhysicalPosition, PhysicalSize},
     event::WindowEvent,
     event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
-    window::WindowId,
+    monitor::MonitorHandle,
 };
 
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+pub mod gesture;
+pub mod headless;
+pub mod input;
 pub mod module;
-pub use winit::window::{Window, WindowAttributes};
+#[cfg(feature = "replay")]
+mod serde_window_id;
+pub use winit::{
+    raw_window_handle,
+    window::{CursorGrabMode, CursorIcon, Window, WindowAttributes, WindowId},
+};
 
 pub trait WindowPlatformEngineExt {
     // Take self as owned so that it can't be called when running the engine
-    fn run_windowed(self, config: WindowPlatformConfig);
+    /// Runs the engine until the window is closed or a listener fails with
+    /// [`ErrorPolicy::Abort`](rgine_modules::ErrorPolicy::Abort), returning its error.
+    fn run_windowed(self, config: WindowPlatformConfig) -> AnyResult<()>;
+
+    /// Text of the system clipboard, see [`ClipboardModule::get_clipboard_text`](clipboard::ClipboardModule::get_clipboard_text)
+    #[cfg(feature = "clipboard")]
+    fn get_clipboard_text(&mut self) -> Option<String>;
+
+    /// Replaces the text of the system clipboard, see [`ClipboardModule::set_clipboard_text`](clipboard::ClipboardModule::set_clipboard_text)
+    #[cfg(feature = "clipboard")]
+    fn set_clipboard_text(&mut self, text: &str);
 }
 
 impl WindowPlatformEngineExt for Engine {
-    fn run_windowed(mut self, config: WindowPlatformConfig) {
+    fn run_windowed(mut self, config: WindowPlatformConfig) -> AnyResult<()> {
         let event_loop = EventLoop::new().unwrap();
         event_loop.set_control_flow(ControlFlow::Poll);
 
         self.dependency::<WindowPlatformModule>().expect(
             "Failed to load window platform module from platform layer on window platform.",
         );
+        #[cfg(feature = "clipboard")]
+        self.dependency::<clipboard::ClipboardModule>()
+            .expect("Failed to load clipboard module from platform layer on window platform.");
+        #[cfg(feature = "gamepad")]
+        self.dependency::<gamepad::GamepadModule>()
+            .expect("Failed to load gamepad module from platform layer on window platform.");
+        self.dependency::<gesture::GestureModule>()
+            .expect("Failed to load gesture module from platform layer on window platform.");
+        self.log_listeners();
         self.run_with(StartEvent);
 
         let mut platform_layer = EngineWindowPlatformWrapper::new(&mut self, config);
-        event_loop.run_app(&mut platform_layer).unwrap();
+        event_loop.run_app(&mut platform_layer)?;
+        self.take_error().map_or(Ok(()), Err)
+    }
+
+    #[cfg(feature = "clipboard")]
+    fn get_clipboard_text(&mut self) -> Option<String> {
+        let clipboard = self.dependency::<clipboard::ClipboardModule>().ok()?;
+        let text = clipboard.read_state().get_clipboard_text();
+        text
+    }
+
+    #[cfg(feature = "clipboard")]
+    fn set_clipboard_text(&mut self, text: &str) {
+        if let Ok(clipboard) = self.dependency::<clipboard::ClipboardModule>() {
+            clipboard.read_state().set_clipboard_text(text);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    /// Fullscreen window covering the monitor, without changing its video mode
+    BorderlessFullscreen,
+    /// Fullscreen using the monitor video mode nearest to `resolution` (in physical pixels)
+    ExclusiveFullscreen { resolution: (u32, u32) },
+}
+
+/// How rendered frames are synchronized with the display
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Waits for the vertical blank (VSync), saving power. Supported everywhere
+    #[default]
+    Fifo,
+    /// Waits for the vertical blank but replaces queued frames, low latency without tearing
+    Mailbox,
+    /// Presents frames right away (no VSync), tearing may occur
+    Immediate,
+}
+
+/// Monitor connected to the system, see [`WindowPlatformModule::available_monitors`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    /// Resolution in physical pixels
+    pub size: (u32, u32),
+    /// Top left corner on the desktop, in physical pixels
+    pub position: (i32, i32),
+    /// Refresh rate of the current video mode in hertz, if known
+    pub refresh_rate: Option<f32>,
+    /// Physical pixels per logical pixel
+    pub scale_factor: f64,
+}
+
+impl From<&MonitorHandle> for MonitorInfo {
+    fn from(monitor: &MonitorHandle) -> Self {
+        Self {
+            name: monitor.name(),
+            size: monitor.size().into(),
+            position: monitor.position().into(),
+            refresh_rate: monitor
+                .refresh_rate_millihertz()
+                .map(|mhz| mhz as f32 / 1000.),
+            scale_factor: monitor.scale_factor(),
+        }
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct WindowPlatformConfig {
+    pub title: String,
+    /// Initial inner size in physical pixels, `None` lets the platform choose
+    pub size: Option<(u32, u32)>,
+    /// Index in [`WindowPlatformModule::available_monitors`] of the monitor the window opens on,
+    /// fullscreen modes included. `None` lets the platform choose
+    pub monitor: Option<usize>,
+    /// Initial position of the top left corner of the window in physical pixels, relative to
+    /// the monitor if any. `None` centers the window on the monitor, or lets the platform choose
+    pub position: Option<(i32, i32)>,
+    pub resizable: bool,
+    /// Smallest inner size in physical pixels the window can be resized to
+    pub min_size: Option<(u32, u32)>,
+    /// Largest inner size in physical pixels the window can be resized to
+    pub max_size: Option<(u32, u32)>,
+    /// Width over height ratio the inner size is kept at while resizing, the height follows the
+    /// width. It must be positive and finite, and is not applied to maximized or fullscreen windows
+    pub aspect_ratio: Option<f32>,
+    /// Whether the window has a title bar and borders
+    pub decorations: bool,
+    pub mode: WindowMode,
+    /// Falls back to [`PresentMode::Fifo`] if unsupported
+    pub present_mode: PresentMode,
+    /// Number of [`OnFixedUpdate`] per second
+    pub fixed_update_rate: u32,
+    /// Base attributes of the window, overridden by the fields above
     pub window_attributes: WindowAttributes,
 }
 
 impl Default for WindowPlatformConfig {
     fn default() -> Self {
         Self {
-            window_attributes: WindowAttributes::default().with_title("Rgine window"),
+            title: "Rgine window".to_string(),
+            size: None,
+            monitor: None,
+            position: None,
+            resizable: true,
+            min_size: None,
+            max_size: None,
+            aspect_ratio: None,
+            decorations: true,
+            mode: WindowMode::Windowed,
+            present_mode: PresentMode::Fifo,
+            fixed_update_rate: 60,
+            window_attributes: WindowAttributes::default(),
         }
     }
 }
 
+impl WindowPlatformConfig {
+    /// Attributes of the window to create, and the monitor to center it on once created when
+    /// its size is only known then
+    fn window_attributes(
+        &self,
+        event_loop: &ActiveEventLoop,
+    ) -> (WindowAttributes, Option<MonitorHandle>) {
+        let mut attributes = self
+            .window_attributes
+            .clone()
+            .with_title(&self.title)
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations);
+        if let Some((width, height)) = self.size {
+            attributes = attributes.with_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some((width, height)) = self.min_size {
+            attributes = attributes.with_min_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some((width, height)) = self.max_size {
+            attributes = attributes.with_max_inner_size(PhysicalSize::new(width, height));
+        }
+
+        let monitor = self.monitor.and_then(|index| {
+            let monitor = event_loop.available_monitors().nth(index);
+            if monitor.is_none() {
+                warn!("There is no monitor {index}, the platform chooses where the window opens!");
+            }
+            monitor
+        });
+        let mut center_on = None;
+        let position = match (monitor, self.position) {
+            (Some(monitor), Some((x, y))) => {
+                Some((monitor.position().x + x, monitor.position().y + y))
+            }
+            (Some(monitor), None) => match self.size {
+                Some(size) => Some(centered_position(&monitor, size.into())),
+                // Opens on the monitor, to be centered once the platform chose the size
+                None => {
+                    let position = monitor.position().into();
+                    center_on = Some(monitor);
+                    Some(position)
+                }
+            },
+            (None, position) => position,
+        };
+        if let Some((x, y)) = position {
+            attributes = attributes.with_position(PhysicalPosition::new(x, y));
+        }
+        (attributes, center_on)
+    }
+}
+
+/// Position of the top left corner of a window of `size` centered on `monitor`
+fn centered_position(monitor: &MonitorHandle, size: PhysicalSize<u32>) -> (i32, i32) {
+    let monitor_size = monitor.size();
+    (
+        monitor.position().x + monitor_size.width.saturating_sub(size.width) as i32 / 2,
+        monitor.position().y + monitor_size.height.saturating_sub(size.height) as i32 / 2,
+    )
+}
+
+/// Creates the window of `config`, centered on its monitor if needed
+fn create_window(
+    event_loop: &ActiveEventLoop,
+    config: &WindowPlatformConfig,
+) -> Result<Window, winit::error::OsError> {
+    let (attributes, center_on) = config.window_attributes(event_loop);
+    let window = event_loop.create_window(attributes)?;
+    if let Some(monitor) = center_on {
+        let (x, y) = centered_position(&monitor, window.inner_size());
+        window.set_outer_position(PhysicalPosition::new(x, y));
+    }
+    Ok(window)
+}
+
 struct EngineWindowPlatformWrapper<'a> {
     engine: &'a mut Engine,
     config: WindowPlatformConfig,
+    last_update: Instant,
+    timer: UpdateTimer,
+    /// Whether updates were paused because the window was minimized
+    paused: bool,
+    suspended: bool,
 }
 
 impl<'a> EngineWindowPlatformWrapper<'a> {
     fn new(engine: &'a mut Engine, config: WindowPlatformConfig) -> Self {
-        Self { engine, config }
+        Self {
+            engine,
+            config,
+            last_update: Instant::now(),
+            timer: UpdateTimer::default(),
+            paused: false,
+            suspended: false,
+        }
     }
 }
 
-pub struct WindowReadyEvent;
-pub struct OnWindowPlatformUpdate;
+/// Emits the update events of the platform, shared by the windowed and headless platforms
+#[derive(Default)]
+pub(crate) struct UpdateTimer {
+    elapsed: Duration,
+    /// Time not yet simulated by fixed updates
+    fixed_update_lag: Duration,
+}
 
-impl<'a> ApplicationHandler for EngineWindowPlatformWrapper<'a> {
-    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        self.engine
+impl UpdateTimer {
+    /// Runs the fixed updates due after `dt`, then an [`OnWindowPlatformUpdate`]
+    pub(crate) fn update(&mut self, engine: &mut Engine, dt: Duration, fixed_update_rate: u32) {
+        self.elapsed += dt;
+
+        let fixed_dt = Duration::from_secs(1) / fixed_update_rate.max(1);
+        self.fixed_update_lag += dt;
+        for _ in 0..MAX_FIXED_UPDATES_PER_UPDATE {
+            if self.fixed_update_lag < fixed_dt {
+                break;
+            }
+            self.fixed_update_lag -= fixed_dt;
+            engine.run_with(OnFixedUpdate { dt: fixed_dt });
+        }
+        // Drop the time that could not be caught up with
+        self.fixed_update_lag = self.fixed_update_lag.min(fixed_dt);
+        engine
             .dependency::<WindowPlatformModule>()
             .unwrap()
             .read_state()
-            .window
-            .set(Arc::new(
-                event_loop
-                    .create_window(self.config.window_attributes.clone())
-                    .unwrap(),
-            ))
-            .unwrap();
+            .fixed_update_alpha
+            .set(self.fixed_update_lag.as_secs_f32() / fixed_dt.as_secs_f32());
+
+        engine.run_with(OnWindowPlatformUpdate {
+            dt,
+            elapsed: self.elapsed,
+        });
+    }
+}
+
+pub struct WindowReadyEvent;
+/// Emitted when the application is suspended by the system, mobile platforms destroy the
+/// window surface then. Nothing is rendered until the [`ResumedEvent`]
+pub struct SuspendedEvent;
+/// Emitted when the application resumes after a [`SuspendedEvent`]
+pub struct ResumedEvent;
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct OnWindowPlatformUpdate {
+    /// Time since the previous update
+    pub dt: Duration,
+    /// Time since the event loop started
+    pub elapsed: Duration,
+}
+/// Emitted [`WindowPlatformConfig::fixed_update_rate`] times per second on average,
+/// before [`OnWindowPlatformUpdate`]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+pub struct OnFixedUpdate {
+    /// Constant time step
+    pub dt: Duration,
+}
+
+/// Fixed updates done at most per platform update, to catch up without freezing when lagging
+const MAX_FIXED_UPDATES_PER_UPDATE: u32 = 8;
+
+impl<'a> EngineWindowPlatformWrapper<'a> {
+    /// Creates the windows requested with an `OpenWindowEvent`, which needs the event loop
+    fn open_pending_windows(&mut self, event_loop: &ActiveEventLoop) {
+        let pending = std::mem::take(
+            &mut self
+                .engine
+                .module_mut::<WindowPlatformModule>()
+                .unwrap()
+                .pending_windows,
+        );
+        for config in pending {
+            let window = match create_window(event_loop, &config) {
+                Ok(window) => window,
+                Err(e) => {
+                    warn!("Unable to open a window! error: {e}");
+                    continue;
+                }
+            };
+            apply_window_mode(&window, config.mode);
+
+            let id = window.id();
+            let mut platform = self.engine.module_mut::<WindowPlatformModule>().unwrap();
+            platform.secondary_windows.insert(id, Arc::new(window));
+            if let Some(aspect_ratio) = config.aspect_ratio {
+                platform.set_aspect_ratio(id, aspect_ratio);
+            }
+            drop(platform);
+            self.engine.run_with(WindowOpenedEvent { window: id });
+        }
+    }
+}
+
+impl<'a> ApplicationHandler for EngineWindowPlatformWrapper<'a> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        // The window is only created on the first resume
+        if self.suspended {
+            self.suspended = false;
+            self.engine.run_with(ResumedEvent);
+            return;
+        }
+        let window = create_window(event_loop, &self.config).unwrap();
+        apply_window_mode(&window, self.config.mode);
+
+        let platform = self.engine.dependency::<WindowPlatformModule>().unwrap();
+        let platform = platform.read_state();
+        platform.window.set(Arc::new(window)).unwrap();
+        platform.config.set(self.config.clone()).unwrap();
+        drop(platform);
+        if let Some(aspect_ratio) = self.config.aspect_ratio {
+            let mut platform = self.engine.module_mut::<WindowPlatformModule>().unwrap();
+            let id = platform.window.get().unwrap().id();
+            platform.set_aspect_ratio(id, aspect_ratio);
+        }
 
         self.engine.run_with(WindowReadyEvent);
     }
 
-    fn window_event(&mut self, _event_loop: &ActiveEventLoop, _wid: WindowId, event: WindowEvent) {
+    fn suspended(&mut self, _: &ActiveEventLoop) {
+        self.suspended = true;
+        self.engine.run_with(SuspendedEvent);
+    }
+
+    fn window_event(&mut self, _event_loop: &ActiveEventLoop, wid: WindowId, event: WindowEvent) {
+        let mut platform = self.engine.module_mut::<WindowPlatformModule>().unwrap();
+        // Events of windows closed since are dropped
+        if platform.window_by_id(wid).is_none() {
+            return;
+        }
+        platform.event_window = Some(wid);
+        drop(platform);
+
         self.engine.run_with(event);
     }
 
@@ -93,13 +433,41 @@ impl<'a> ApplicationHandler for EngineWindowPlatformWrapper<'a> {
     }
 
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
-        self.engine.run_with(OnWindowPlatformUpdate);
-        if self
+        // Events deferred during the previous frame come before this frame updates
+        self.engine.run_deferred();
+        self.open_pending_windows(event_loop);
+        apply_pending_cursor(
+            &mut self.engine.module_mut::<WindowPlatformModule>().unwrap(),
+            event_loop,
+        );
+
+        let now = Instant::now();
+        let dt = now - self.last_update;
+        self.last_update = now;
+        let minimized = self
             .engine
             .dependency::<WindowPlatformModule>()
             .unwrap()
             .read_state()
-            .should_close
+            .minimized;
+        // Updates are paused while minimized, the loop only wakes up on window events
+        if minimized {
+            event_loop.set_control_flow(ControlFlow::Wait);
+        } else {
+            event_loop.set_control_flow(ControlFlow::Poll);
+            // The time spent minimized is skipped
+            let dt = if self.paused { Duration::ZERO } else { dt };
+            self.timer
+                .update(self.engine, dt, self.config.fixed_update_rate);
+        }
+        self.paused = minimized;
+        if self.engine.has_error()
+            || self
+                .engine
+                .dependency::<WindowPlatformModule>()
+                .unwrap()
+                .read_state()
+                .should_close
         {
             event_loop.exit();
         }
@@ -1,55 +1,550 @@
-use std::{cell::OnceCell, sync::Arc};
+use std::{
+    cell::{Cell, OnceCell},
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
+use rgine_logger::warn;
 use rgine_modules::{
     events::{EventQueue, Listener},
     Engine, Module,
 };
 use winit::{
-    event::{DeviceEvent, WindowEvent},
-    window::Window,
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceEvent, Ime, MouseScrollDelta, WindowEvent},
+    event_loop::ActiveEventLoop,
+    keyboard::{ModifiersState, PhysicalKey},
+    raw_window_handle::{HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle},
+    window::{
+        Cursor, CursorGrabMode, CursorIcon, CustomCursor, CustomCursorSource, Fullscreen, Window,
+        WindowId,
+    },
+};
+
+use super::{
+    headless::{HeadlessState, HEADLESS_DEFAULT_SIZE},
+    input::{
+        CursorEnteredEvent, CursorLeftEvent, CursorMovedEvent, FileDroppedEvent,
+        FileHoverCancelledEvent, FileHoveredEvent, KeyboardInputEvent, MouseButtonEvent,
+        MouseWheelEvent, ScrollUnit, TextInputEvent, TextPreeditEvent,
+    },
+    MonitorInfo, WindowMode, WindowPlatformConfig,
 };
 
 pub struct RequestWindowRedrawEvent;
-pub struct WindowRenderReadyEvent;
-pub struct WindowResizeEvent;
+/// Emitted when a window opened with [`OpenWindowEvent`] should be rendered again,
+/// the main window emits [`WindowRenderReadyEvent`] instead
+pub struct SecondaryWindowRenderReadyEvent {
+    pub window: WindowId,
+}
+pub struct WindowRenderReadyEvent {
+    /// Progress from the last fixed update to the next one, between 0 and 1,
+    /// to interpolate the rendered state between two fixed updates
+    pub fixed_update_alpha: f32,
+}
+/// Emitted when the window inner size changes, including when its scale factor changes.
+///
+/// Anything sized after the window, such as `SpriteRenderer::resize`, should be updated on it.
+#[derive(Clone, Copy, Debug)]
+pub struct WindowResizedEvent {
+    pub window: WindowId,
+    /// New inner width in physical pixels
+    pub width: u32,
+    /// New inner height in physical pixels
+    pub height: u32,
+}
+
+/// Emitted when the window moves to a monitor of another pixel density or the system
+/// scale changes, before the [`WindowResizedEvent`] of its new size if it changed
+#[derive(Clone, Copy, Debug)]
+pub struct ScaleFactorChangedEvent {
+    pub window: WindowId,
+    /// Physical pixels per logical pixel, see [`WindowPlatformModule::scale_factor`]
+    pub scale_factor: f64,
+}
+
+/// Emitted when a window is minimized or restored, the main one or a secondary one.
+///
+/// While the main window is minimized, no frame is rendered and the updates are paused.
+/// Minimized secondary windows are not rendered either.
+#[derive(Clone, Copy, Debug)]
+pub struct WindowMinimizedEvent {
+    pub window: WindowId,
+    pub minimized: bool,
+}
+/// Emitted when a window gains or loses the keyboard focus
+#[derive(Clone, Copy, Debug)]
+pub struct WindowFocusedEvent {
+    pub window: WindowId,
+    pub focused: bool,
+}
+
+/// Switches the window to `mode`, a [`WindowResizedEvent`] follows once the switch is done
+pub struct SetWindowModeEvent {
+    pub mode: WindowMode,
+}
+
+pub struct SetCursorVisibleEvent {
+    pub visible: bool,
+}
+pub struct SetCursorGrabEvent {
+    pub mode: CursorGrabMode,
+}
+/// Replaces the cursor over the main window with a system cursor
+pub struct SetCursorIconEvent {
+    pub icon: CursorIcon,
+}
+/// Replaces the cursor over the main window with an image, the system cursor is kept
+/// if the image is invalid
+pub struct SetCursorImageEvent {
+    /// Non-premultiplied RGBA8 pixels, row by row from the top left corner
+    pub rgba: Vec<u8>,
+    /// Width and height of the image in pixels
+    pub size: (u16, u16),
+    /// Pixel of the image pointing at the cursor position, from its top left corner
+    pub hotspot: (u16, u16),
+}
+
+/// Allows the input method editor over the main window, used to compose text in languages
+/// such as Chinese or Japanese. It should only be allowed while a text field has the focus
+pub struct SetImeAllowedEvent {
+    pub allowed: bool,
+}
+/// Moves the candidate box of the input method next to the area being edited, in physical
+/// pixels from the top left corner of the main window
+pub struct SetImeCursorAreaEvent {
+    pub position: (f64, f64),
+    pub size: (f64, f64),
+}
+
+/// Opens a secondary window next to the main one, a [`WindowOpenedEvent`] follows once it
+/// is created. [`PresentMode`](super::PresentMode) and `fixed_update_rate` of the config
+/// are not used.
+///
+/// The graphics module gives it its own context and frames, but renderers such as the 2D
+/// renderer only draw to the main window: the secondary window is rendered to on its
+/// [`SecondaryWindowRenderReadyEvent`].
+pub struct OpenWindowEvent {
+    pub config: WindowPlatformConfig,
+}
+/// Emitted once a window requested with [`OpenWindowEvent`] is created
+pub struct WindowOpenedEvent {
+    pub window: WindowId,
+}
+/// Closes a secondary window, closing the main window shuts the engine down instead
+pub struct CloseWindowEvent {
+    pub window: WindowId,
+}
+/// Emitted when a secondary window was closed, by the user or with [`CloseWindowEvent`]
+pub struct WindowClosedEvent {
+    pub window: WindowId,
+}
 
 pub struct WindowPlatformModule {
     pub should_close: bool,
+    /// Whether the main window is minimized, see [`WindowMinimizedEvent`]
+    pub minimized: bool,
+    /// Secondary windows that are minimized
+    pub(crate) minimized_windows: HashSet<WindowId>,
     pub window: OnceCell<Arc<Window>>,
+    /// Windows opened with [`OpenWindowEvent`], the main window excluded
+    pub secondary_windows: HashMap<WindowId, Arc<Window>>,
+    pub modifiers: ModifiersState,
+    /// Last known cursor position in physical pixels, `None` when outside of every window
+    pub cursor_position: Option<(f64, f64)>,
+    /// Window the cursor is over, which [`WindowPlatformModule::cursor_position`] is relative to
+    pub cursor_window: Option<WindowId>,
+    /// Cursor grab applied again to any window of the engine that gains focus
+    pub cursor_grab: CursorGrabMode,
+    /// Config the window was created with
+    pub config: OnceCell<WindowPlatformConfig>,
+    pub(crate) fixed_update_alpha: Cell<f32>,
+    /// Windows requested with an [`OpenWindowEvent`], created by the event loop
+    pub(crate) pending_windows: Vec<WindowPlatformConfig>,
+    /// Aspect ratios the windows are locked to, see [`WindowPlatformConfig::aspect_ratio`]
+    pub(crate) aspect_ratios: HashMap<WindowId, f32>,
+    /// Window of the [`WindowEvent`] being dispatched
+    pub(crate) event_window: Option<WindowId>,
+    /// Cursor image set with a [`SetCursorImageEvent`], created by the event loop
+    pub(crate) pending_cursor: Option<CustomCursorSource>,
+    /// Windows where the input method is enabled, which then handles the text input
+    pub(crate) ime_windows: HashSet<WindowId>,
+    /// Set when running headless, see [`HeadlessEngineExt`](super::headless::HeadlessEngineExt)
+    pub(crate) headless: Option<HeadlessState>,
 }
 impl WindowPlatformModule {
+    /// Inner size of the window, or size of the rendered frames when running headless
     pub fn window_size(&self) -> Option<(u32, u32)> {
-        self.window.get().map(|w| w.inner_size().into())
+        match self.window.get() {
+            Some(window) => Some(window.inner_size().into()),
+            None => self
+                .config
+                .get()
+                .map(|c| c.size.unwrap_or(HEADLESS_DEFAULT_SIZE)),
+        }
+    }
+
+    /// Window the engine renders to, `None` before the [`WindowReadyEvent`](super::WindowReadyEvent)
+    /// and when running headless.
+    ///
+    /// Holding the returned window keeps it alive, but it stops receiving events once the
+    /// engine shuts down.
+    pub fn window(&self) -> Option<Arc<Window>> {
+        self.window.get().cloned()
+    }
+
+    /// Main window when `id` is its id, otherwise the secondary window of id `id`
+    pub fn window_by_id(&self, id: WindowId) -> Option<Arc<Window>> {
+        match self.window.get() {
+            Some(window) if window.id() == id => Some(window.clone()),
+            _ => self.secondary_windows.get(&id).cloned(),
+        }
+    }
+
+    /// Monitors connected to the system, empty when running headless
+    pub fn available_monitors(&self) -> Vec<MonitorInfo> {
+        self.window
+            .get()
+            .into_iter()
+            .flat_map(|window| window.available_monitors())
+            .map(|monitor| MonitorInfo::from(&monitor))
+            .collect()
+    }
+
+    /// Monitor the main window is on, `None` if unknown or when running headless
+    pub fn current_monitor(&self) -> Option<MonitorInfo> {
+        let monitor = self.window.get()?.current_monitor()?;
+        Some(MonitorInfo::from(&monitor))
+    }
+
+    /// Whether `id` is the id of the main window
+    pub fn is_main_window(&self, id: WindowId) -> bool {
+        self.window.get().is_some_and(|window| window.id() == id)
+    }
+
+    /// Whether the window `id` is minimized, see [`WindowMinimizedEvent`]
+    pub fn is_minimized(&self, id: WindowId) -> bool {
+        match self.is_main_window(id) {
+            true => self.minimized,
+            false => self.minimized_windows.contains(&id),
+        }
+    }
+
+    /// Locks the inner size of `window` to `aspect_ratio`, ignored with a warning unless it is
+    /// positive and finite
+    pub(crate) fn set_aspect_ratio(&mut self, window: WindowId, aspect_ratio: f32) {
+        if !aspect_ratio.is_finite() || aspect_ratio <= 0. {
+            warn!(
+                "The aspect ratio {aspect_ratio} is not positive, the window size is not locked!"
+            );
+            return;
+        }
+        self.aspect_ratios.insert(window, aspect_ratio);
+    }
+
+    /// Requests `window` to be resized back to its locked aspect ratio if `size` does not match it.
+    ///
+    /// Returns the size of the window, another resize event follows if the request is applied later.
+    fn lock_aspect_ratio(&self, window: WindowId, size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+        let (Some(aspect_ratio), Some(w)) =
+            (self.aspect_ratios.get(&window), self.window_by_id(window))
+        else {
+            return size;
+        };
+        // Maximized and fullscreen windows are sized by the platform
+        if w.fullscreen().is_some() || w.is_maximized() {
+            return size;
+        }
+        let height = (size.width as f32 / aspect_ratio).round() as u32;
+        if size.height.abs_diff(height) <= 1 {
+            return size;
+        }
+        // The new size is given back when applied right away, without any resize event
+        w.request_inner_size(PhysicalSize::new(size.width, height))
+            .unwrap_or(size)
+    }
+
+    /// Cursor position over `window`, the origin if the cursor is elsewhere
+    fn cursor_position_in(&self, window: WindowId) -> (f64, f64) {
+        match self.cursor_window == Some(window) {
+            true => self.cursor_position.unwrap_or_default(),
+            false => (0., 0.),
+        }
+    }
+
+    /// Native handle of the window, to create surfaces or overlays with other libraries.
+    ///
+    /// The handle is only valid while the window is alive, which is until the
+    /// [`ShutdownEvent`](rgine_modules::standards::ShutdownEvent) unless the window returned
+    /// by [`WindowPlatformModule::window`] is kept.
+    pub fn raw_window_handle(&self) -> Option<RawWindowHandle> {
+        let handle = self.window.get()?.window_handle();
+        handle
+            .inspect_err(|e| warn!("The window handle is not available! error: {e}"))
+            .ok()
+            .map(|h| h.as_raw())
+    }
+
+    /// Native handle of the display server of the window, see [`WindowPlatformModule::raw_window_handle`]
+    pub fn raw_display_handle(&self) -> Option<RawDisplayHandle> {
+        let handle = self.window.get()?.display_handle();
+        handle
+            .inspect_err(|e| warn!("The display handle is not available! error: {e}"))
+            .ok()
+            .map(|h| h.as_raw())
+    }
+
+    /// Physical pixels per logical pixel of the window, `2.0` on most HiDPI displays.
+    ///
+    /// Sizes and positions reported by the platform are in physical pixels, divide them by
+    /// this factor to get logical pixels. It is `1.0` when running headless.
+    pub fn scale_factor(&self) -> f64 {
+        self.window.get().map_or(1., |window| window.scale_factor())
+    }
+
+    /// Whether the engine runs without a window, see [`HeadlessEngineExt`](super::headless::HeadlessEngineExt)
+    pub fn is_headless(&self) -> bool {
+        self.window.get().is_none() && self.config.get().is_some()
     }
 }
 impl Module for WindowPlatformModule {
-    type ListeningTo = (WindowEvent, DeviceEvent, RequestWindowRedrawEvent);
+    type ListeningTo = (
+        WindowEvent,
+        DeviceEvent,
+        RequestWindowRedrawEvent,
+        SetWindowModeEvent,
+        SetCursorVisibleEvent,
+        SetCursorGrabEvent,
+        SetCursorIconEvent,
+        SetCursorImageEvent,
+        SetImeAllowedEvent,
+        SetImeCursorAreaEvent,
+        OpenWindowEvent,
+        CloseWindowEvent,
+    );
     fn new(_: &mut Engine) -> rgine_modules::AnyResult<Self> {
         Ok(Self {
             should_close: false,
+            minimized: false,
+            minimized_windows: HashSet::new(),
             window: OnceCell::new(),
+            secondary_windows: HashMap::new(),
+            modifiers: ModifiersState::empty(),
+            cursor_position: None,
+            cursor_window: None,
+            cursor_grab: CursorGrabMode::None,
+            config: OnceCell::new(),
+            fixed_update_alpha: Cell::new(0.),
+            pending_windows: Vec::new(),
+            aspect_ratios: HashMap::new(),
+            event_window: None,
+            pending_cursor: None,
+            ime_windows: HashSet::new(),
+            headless: None,
         })
     }
 }
 impl Listener<WindowEvent> for WindowPlatformModule {
     fn on_event(&mut self, event: &mut WindowEvent, queue: &mut EventQueue) {
+        let Some(window) = self
+            .event_window
+            .or_else(|| self.window.get().map(|w| w.id()))
+        else {
+            return;
+        };
+        let is_main = self.is_main_window(window);
         match event {
-            WindowEvent::CloseRequested => {
+            WindowEvent::CloseRequested if is_main => {
                 self.should_close = true;
             }
+            WindowEvent::CloseRequested => {
+                queue.push(CloseWindowEvent { window });
+            }
+            // Minimized windows may have a null surface, rendering resumes when restored
+            WindowEvent::RedrawRequested if self.is_minimized(window) => (),
+            WindowEvent::RedrawRequested if is_main => {
+                queue.push(WindowRenderReadyEvent {
+                    fixed_update_alpha: self.fixed_update_alpha.get(),
+                });
+            }
             WindowEvent::RedrawRequested => {
-                queue.push(WindowRenderReadyEvent);
+                queue.push(SecondaryWindowRenderReadyEvent { window });
+            }
+            WindowEvent::Resized(size) => {
+                let minimized = size.width == 0
+                    || size.height == 0
+                    || self
+                        .window_by_id(window)
+                        .and_then(|w| w.is_minimized())
+                        .unwrap_or(false);
+                if minimized != self.is_minimized(window) {
+                    if is_main {
+                        self.minimized = minimized;
+                    } else if minimized {
+                        self.minimized_windows.insert(window);
+                    } else {
+                        self.minimized_windows.remove(&window);
+                    }
+                    queue.push(WindowMinimizedEvent { window, minimized });
+                    if let Some(window) = self.window_by_id(window).filter(|_| !minimized) {
+                        window.request_redraw();
+                    }
+                }
+                if minimized {
+                    return;
+                }
+                let size = self.lock_aspect_ratio(window, *size);
+                queue.push(WindowResizedEvent {
+                    window,
+                    width: size.width,
+                    height: size.height,
+                });
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // The platform reports the new size with a `Resized` event right after
+                queue.push(ScaleFactorChangedEvent {
+                    window,
+                    scale_factor: *scale_factor,
+                });
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                // Keys unknown to winit can't be represented by a key code
+                if let PhysicalKey::Code(key) = event.physical_key {
+                    queue.push(KeyboardInputEvent {
+                        window,
+                        key,
+                        state: event.state,
+                        repeat: event.repeat,
+                        modifiers: self.modifiers,
+                    });
+                }
+                // The text is committed by the input method instead while it is enabled
+                if let Some(text) = &event.text {
+                    if event.state.is_pressed() && !self.ime_windows.contains(&window) {
+                        push_text_input(queue, window, text);
+                    }
+                }
             }
-            WindowEvent::Resized(..) | WindowEvent::ScaleFactorChanged { .. } => {
-                queue.push(WindowResizeEvent);
+            WindowEvent::Ime(ime) => match ime {
+                Ime::Enabled => {
+                    self.ime_windows.insert(window);
+                }
+                Ime::Preedit(text, cursor) => queue.push(TextPreeditEvent {
+                    window,
+                    text: text.clone(),
+                    cursor: *cursor,
+                }),
+                Ime::Commit(text) => push_text_input(queue, window, text),
+                Ime::Disabled => {
+                    self.ime_windows.remove(&window);
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = (*position).into();
+                self.cursor_position = Some(position);
+                self.cursor_window = Some(window);
+                queue.push(CursorMovedEvent { window, position });
+            }
+            WindowEvent::CursorEntered { .. } => {
+                self.cursor_window = Some(window);
+                queue.push(CursorEnteredEvent { window });
+            }
+            WindowEvent::CursorLeft { .. } => {
+                // The cursor may already be over another window
+                if self.cursor_window == Some(window) {
+                    self.cursor_position = None;
+                    self.cursor_window = None;
+                }
+                queue.push(CursorLeftEvent { window });
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                queue.push(MouseButtonEvent {
+                    window,
+                    button: *button,
+                    state: *state,
+                    position: self.cursor_position_in(window),
+                });
+            }
+            // Winit only reports the cursor position while dragging through `CursorMoved`
+            WindowEvent::HoveredFile(path) => {
+                queue.push(FileHoveredEvent {
+                    window,
+                    path: path.clone(),
+                    position: self.cursor_position_in(window),
+                });
+            }
+            WindowEvent::HoveredFileCancelled => {
+                queue.push(FileHoverCancelledEvent { window });
+            }
+            WindowEvent::DroppedFile(path) => {
+                queue.push(FileDroppedEvent {
+                    window,
+                    path: path.clone(),
+                    position: self.cursor_position_in(window),
+                });
+            }
+            WindowEvent::Focused(focused) => {
+                // Platforms release the grab when the window loses focus
+                if *focused && self.cursor_grab != CursorGrabMode::None {
+                    if let Some(focused_window) = self.window_by_id(window) {
+                        apply_cursor_grab(&focused_window, self.cursor_grab);
+                    }
+                }
+                queue.push(WindowFocusedEvent {
+                    window,
+                    focused: *focused,
+                });
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (delta_x, delta_y, unit) = match *delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x, y, ScrollUnit::Line),
+                    MouseScrollDelta::PixelDelta(position) => {
+                        (position.x as f32, position.y as f32, ScrollUnit::Pixel)
+                    }
+                };
+                queue.push(MouseWheelEvent {
+                    window,
+                    delta_x,
+                    delta_y,
+                    unit,
+                });
             }
             _ => (),
         }
     }
 }
+impl Listener<OpenWindowEvent> for WindowPlatformModule {
+    fn on_event(&mut self, event: &mut OpenWindowEvent, _: &mut EventQueue) {
+        if self.is_headless() {
+            warn!("Windows can't be opened when running headless!");
+            return;
+        }
+        self.pending_windows.push(event.config.clone());
+    }
+}
+impl Listener<CloseWindowEvent> for WindowPlatformModule {
+    fn on_event(&mut self, event: &mut CloseWindowEvent, queue: &mut EventQueue) {
+        if self.secondary_windows.remove(&event.window).is_some() {
+            self.minimized_windows.remove(&event.window);
+            if self.cursor_window == Some(event.window) {
+                self.cursor_position = None;
+                self.cursor_window = None;
+            }
+            queue.push(WindowClosedEvent {
+                window: event.window,
+            });
+        }
+    }
+}
 impl Listener<RequestWindowRedrawEvent> for WindowPlatformModule {
     fn on_event(&mut self, _: &mut RequestWindowRedrawEvent, _: &mut EventQueue) {
-        self.window.get().unwrap().request_redraw()
+        // Headless frames are rendered on every step instead
+        if let Some(window) = self.window.get() {
+            window.request_redraw()
+        }
     }
 }
 impl Listener<DeviceEvent> for WindowPlatformModule {
@@ -57,3 +552,193 @@ impl Listener<DeviceEvent> for WindowPlatformModule {
         //...
     }
 }
+impl Listener<SetWindowModeEvent> for WindowPlatformModule {
+    fn on_event(&mut self, event: &mut SetWindowModeEvent, _: &mut EventQueue) {
+        if let Some(window) = self.window.get() {
+            apply_window_mode(window, event.mode);
+        }
+    }
+}
+
+impl Listener<SetCursorVisibleEvent> for WindowPlatformModule {
+    fn on_event(&mut self, event: &mut SetCursorVisibleEvent, _: &mut EventQueue) {
+        if let Some(window) = self.window.get() {
+            window.set_cursor_visible(event.visible);
+        }
+    }
+}
+impl Listener<SetCursorGrabEvent> for WindowPlatformModule {
+    fn on_event(&mut self, event: &mut SetCursorGrabEvent, _: &mut EventQueue) {
+        self.cursor_grab = event.mode;
+        if let Some(window) = self.window.get() {
+            apply_cursor_grab(window, event.mode);
+        }
+    }
+}
+
+impl Listener<SetCursorIconEvent> for WindowPlatformModule {
+    fn on_event(&mut self, event: &mut SetCursorIconEvent, _: &mut EventQueue) {
+        // A cursor image still waiting to be created would replace the icon
+        self.pending_cursor = None;
+        if let Some(window) = self.window.get() {
+            window.set_cursor(event.icon);
+        }
+    }
+}
+impl Listener<SetCursorImageEvent> for WindowPlatformModule {
+    fn on_event(&mut self, event: &mut SetCursorImageEvent, _: &mut EventQueue) {
+        let (width, height) = event.size;
+        let (hotspot_x, hotspot_y) = event.hotspot;
+        match CustomCursor::from_rgba(
+            std::mem::take(&mut event.rgba),
+            width,
+            height,
+            hotspot_x,
+            hotspot_y,
+        ) {
+            Ok(source) => self.pending_cursor = Some(source),
+            Err(e) => {
+                warn!("Unable to use the cursor image, the system cursor is kept! error: {e}")
+            }
+        }
+    }
+}
+
+impl Listener<SetImeAllowedEvent> for WindowPlatformModule {
+    fn on_event(&mut self, event: &mut SetImeAllowedEvent, _: &mut EventQueue) {
+        if let Some(window) = self.window.get() {
+            window.set_ime_allowed(event.allowed);
+        }
+    }
+}
+impl Listener<SetImeCursorAreaEvent> for WindowPlatformModule {
+    fn on_event(&mut self, event: &mut SetImeCursorAreaEvent, _: &mut EventQueue) {
+        if let Some(window) = self.window.get() {
+            window.set_ime_cursor_area(
+                PhysicalPosition::<f64>::from(event.position),
+                PhysicalSize::<f64>::from(event.size),
+            );
+        }
+    }
+}
+
+/// Emits `text` without its control characters, if anything is left
+fn push_text_input(queue: &mut EventQueue, window: WindowId, text: &str) {
+    let text: String = text.chars().filter(|c| !c.is_control()).collect();
+    if !text.is_empty() {
+        queue.push(TextInputEvent { window, text });
+    }
+}
+
+pub trait WindowEventQueueExt {
+    /// Switches the window between windowed and fullscreen modes
+    fn set_window_mode(&mut self, mode: WindowMode);
+    /// Shows or hides the cursor while it is over the window
+    fn set_cursor_visible(&mut self, visible: bool);
+    /// Confines or locks the cursor to the window, the grab is kept across focus changes
+    fn set_cursor_grab(&mut self, mode: CursorGrabMode);
+    /// Uses a system cursor over the window, see [`SetCursorIconEvent`]
+    fn set_cursor_icon(&mut self, icon: CursorIcon);
+    /// Uses an image as the cursor over the window, see [`SetCursorImageEvent`]
+    fn set_cursor_image(&mut self, rgba: Vec<u8>, size: (u16, u16), hotspot: (u16, u16));
+    /// Opens a secondary window, see [`OpenWindowEvent`]
+    fn open_window(&mut self, config: WindowPlatformConfig);
+    /// Closes a secondary window, see [`CloseWindowEvent`]
+    fn close_window(&mut self, window: WindowId);
+    /// Allows the input method over the main window, see [`SetImeAllowedEvent`]
+    fn set_ime_allowed(&mut self, allowed: bool);
+    /// Places the candidate box of the input method, see [`SetImeCursorAreaEvent`]
+    fn set_ime_cursor_area(&mut self, position: (f64, f64), size: (f64, f64));
+}
+impl WindowEventQueueExt for EventQueue {
+    fn set_window_mode(&mut self, mode: WindowMode) {
+        self.push(SetWindowModeEvent { mode })
+    }
+    fn set_cursor_visible(&mut self, visible: bool) {
+        self.push(SetCursorVisibleEvent { visible })
+    }
+    fn set_cursor_grab(&mut self, mode: CursorGrabMode) {
+        self.push(SetCursorGrabEvent { mode })
+    }
+    fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        self.push(SetCursorIconEvent { icon })
+    }
+    fn set_cursor_image(&mut self, rgba: Vec<u8>, size: (u16, u16), hotspot: (u16, u16)) {
+        self.push(SetCursorImageEvent {
+            rgba,
+            size,
+            hotspot,
+        })
+    }
+    fn open_window(&mut self, config: WindowPlatformConfig) {
+        self.push(OpenWindowEvent { config })
+    }
+    fn close_window(&mut self, window: WindowId) {
+        self.push(CloseWindowEvent { window })
+    }
+    fn set_ime_allowed(&mut self, allowed: bool) {
+        self.push(SetImeAllowedEvent { allowed })
+    }
+    fn set_ime_cursor_area(&mut self, position: (f64, f64), size: (f64, f64)) {
+        self.push(SetImeCursorAreaEvent { position, size })
+    }
+}
+
+/// Creates the cursor image requested with a [`SetCursorImageEvent`], which needs the event loop
+pub(crate) fn apply_pending_cursor(
+    platform: &mut WindowPlatformModule,
+    event_loop: &ActiveEventLoop,
+) {
+    let Some(source) = platform.pending_cursor.take() else {
+        return;
+    };
+    if let Some(window) = platform.window.get() {
+        window.set_cursor(Cursor::Custom(event_loop.create_custom_cursor(source)));
+    }
+}
+
+/// Grabs the cursor, falling back to the other grab mode if `mode` is unsupported
+fn apply_cursor_grab(window: &Window, mode: CursorGrabMode) {
+    let fallback = match mode {
+        CursorGrabMode::None => CursorGrabMode::None,
+        CursorGrabMode::Confined => CursorGrabMode::Locked,
+        CursorGrabMode::Locked => CursorGrabMode::Confined,
+    };
+    if let Err(e) = window
+        .set_cursor_grab(mode)
+        .or_else(|_| window.set_cursor_grab(fallback))
+    {
+        warn!("Unable to grab the cursor: {e}");
+    }
+}
+
+pub(crate) fn apply_window_mode(window: &Window, mode: WindowMode) {
+    let fullscreen = match mode {
+        WindowMode::Windowed => None,
+        WindowMode::BorderlessFullscreen => Some(Fullscreen::Borderless(window.current_monitor())),
+        WindowMode::ExclusiveFullscreen { resolution } => {
+            let monitor = window
+                .current_monitor()
+                .or_else(|| window.primary_monitor());
+            // Nearest resolution first, then highest refresh rate
+            let video_mode = monitor
+                .into_iter()
+                .flat_map(|m| m.video_modes())
+                .min_by_key(|m| {
+                    let size = m.size();
+                    (
+                        size.width.abs_diff(resolution.0) + size.height.abs_diff(resolution.1),
+                        u32::MAX - m.refresh_rate_millihertz(),
+                    )
+                });
+            match video_mode {
+                Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                None => {
+                    warn!("No video mode available for exclusive fullscreen, using borderless fullscreen instead!");
+                    Some(Fullscreen::Borderless(None))
+                }
+            }
+        }
+    };
+    window.set_fullscreen(fullscreen);
+}
@@ -0,0 +1,17 @@
+//! Window ids of the replayable events, serialized as their raw value.
+//!
+//! Raw ids are given by the system, so a replayed event only targets the same window
+//! when the system gives it the same id as in the recorded session.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use winit::window::WindowId;
+
+pub(crate) fn serialize<S: Serializer>(id: &WindowId, serializer: S) -> Result<S::Ok, S::Error> {
+    u64::from(*id).serialize(serializer)
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<WindowId, D::Error> {
+    u64::deserialize(deserializer).map(WindowId::from)
+}
@@ -2,8 +2,8 @@ use std::{f32::consts::PI, time::Instant};
 
 use rgine::prelude::*;
 
-fn main() {
-    Engine::new::<Example>().run_windowed(WindowPlatformConfig::default());
+fn main() -> AnyResult<()> {
+    Engine::new::<Example>().run_windowed(WindowPlatformConfig::default())
 }
 
 struct Example {
@@ -41,15 +41,16 @@ impl Listener<Render2DEvent> for Example {
 
         let mut draw = Draw2d(queue);
         draw.sprite_centered(
-            Sprite {
-                sheet: self.characters_sheet.unwrap(),
-                position: Vector2::zero(),
-                size: Vector2::one(),
-            },
+            Sprite::new(
+                self.characters_sheet.unwrap(),
+                Vector2::zero(),
+                Vector2::one(),
+            ),
             DrawParams {
                 depth: 0.,
                 tint: Color3::WHITE,
                 transform: Matrix3::from_angle_z(rotation),
+                ..Default::default()
             },
         );
     }
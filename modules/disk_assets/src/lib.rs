@@ -2,7 +2,7 @@ use std::{
     any::{type_name, Any, TypeId},
     collections::HashMap,
     ffi::OsStr,
-    path::{Path, PathBuf},
+    path::Path,
 };
 
 use rgine_assets::AssetsEventQueueExt;
@@ -27,7 +27,7 @@ impl AssetLookup {
         self.map.insert(TypeId::of::<R>(), Box::new(map));
     }
 
-    fn get<R: FileAssetsRegistry>(&self, key: &str) -> &R::Handle {
+    pub fn get<R: FileAssetsRegistry>(&self, key: &str) -> &R::Handle {
         self.map
             .get(&TypeId::of::<R>())
             .expect("Tried to access asset type that has not been loaded!")
@@ -101,10 +101,9 @@ pub fn assets_dir(subpath: &str) -> String {
 
 fn skip_last<T>(mut iter: impl Iterator<Item = T>) -> impl Iterator<Item = T> {
     let last = iter.next();
-    iter.scan(last, |state, item| std::mem::replace(state, Some(item)))
+    iter.scan(last, |state, item| state.replace(item))
 }
 
 fn is_snake_case(name: &str) -> bool {
-    name.chars()
-        .fold(true, |b, c| b && (c.is_ascii_alphanumeric() || c == '_'))
+    name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
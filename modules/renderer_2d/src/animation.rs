@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use crate::texture::Sprite;
+
+/// How an animation continues once its last frame is reached
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnimationMode {
+    /// Restarts from the first frame
+    #[default]
+    Loop,
+    /// Plays backward down to the first frame, then forward again
+    PingPong,
+}
+
+/// Ordered sprite frames, each one shown for its own duration
+#[derive(Clone)]
+pub struct Animation {
+    frames: Vec<(Sprite, Duration)>,
+    pub mode: AnimationMode,
+}
+
+impl Animation {
+    pub fn new(mode: AnimationMode) -> Self {
+        Self {
+            frames: Vec::new(),
+            mode,
+        }
+    }
+
+    /// Animation showing every sprite of `frames` for the same `frame_duration`
+    pub fn uniform(
+        frames: impl IntoIterator<Item = Sprite>,
+        frame_duration: Duration,
+        mode: AnimationMode,
+    ) -> Self {
+        Self {
+            frames: frames.into_iter().map(|f| (f, frame_duration)).collect(),
+            mode,
+        }
+    }
+
+    /// Appends a frame shown for `duration`
+    pub fn frame(mut self, sprite: Sprite, duration: Duration) -> Self {
+        self.frames.push((sprite, duration));
+        self
+    }
+
+    /// Duration of one cycle, going back and forth for [`AnimationMode::PingPong`]
+    pub fn duration(&self) -> Duration {
+        self.cycle_frames().map(|(_, d)| *d).sum()
+    }
+
+    /// Sprite to draw `elapsed` after the animation started.
+    ///
+    /// Panics if the animation has no frame.
+    pub fn current_sprite(&self, elapsed: Duration) -> Sprite {
+        assert!(
+            !self.frames.is_empty(),
+            "Tried to play an animation without frames!"
+        );
+
+        let total = self.duration().as_nanos();
+        if total == 0 {
+            return self.frames[0].0.clone();
+        }
+
+        let mut time = elapsed.as_nanos() % total;
+        for (sprite, duration) in self.cycle_frames() {
+            if time < duration.as_nanos() {
+                return sprite.clone();
+            }
+            time -= duration.as_nanos();
+        }
+        unreachable!()
+    }
+
+    /// Frames in the order they are shown during one cycle
+    fn cycle_frames(&self) -> impl Iterator<Item = &(Sprite, Duration)> {
+        // Ping-pong does not repeat the first and last frames when turning around
+        let last = self.frames.len().saturating_sub(1);
+        let back = match self.mode {
+            AnimationMode::Loop => 0..0,
+            AnimationMode::PingPong => last.min(1)..last,
+        };
+        self.frames.iter().chain(self.frames[back].iter().rev())
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    hash::Hash,
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use rgine_graphics::PreSubmitRenderEvent;
+use rgine_logger::error;
+use rgine_modules::{
+    events::{EventQueue, Listener},
+    utils::Take,
+    AnyResult, Engine, Module,
+};
+
+use crate::{
+    decoder::{decode_image, Decoding},
+    texture::{RgbaImage, SheetId, SpriteSheetData, SpriteSheetHandle},
+};
+
+/// Texture loaded in the background by the [`AssetServerModule`], drawn as a sprite sheet
+pub struct TextureAsset;
+
+/// Asset loaded in the background, usable right away even though it is not ready yet
+pub struct Handle<T> {
+    id: u64,
+    _asset: PhantomData<fn() -> T>,
+}
+
+static NEXT_HANDLE_ID: AtomicU64 = AtomicU64::new(0);
+
+impl<T> Handle<T> {
+    fn new() -> Self {
+        Self {
+            id: NEXT_HANDLE_ID.fetch_add(1, Ordering::Relaxed),
+            _asset: PhantomData,
+        }
+    }
+}
+
+// Derives would require `T` to implement the traits too
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for Handle<T> {}
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+impl<T> Eq for Handle<T> {}
+impl<T> Hash for Handle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handle<{}>({})", std::any::type_name::<T>(), self.id)
+    }
+}
+
+/// Sprites of a texture that is not uploaded yet are drawn with a placeholder
+impl From<Handle<TextureAsset>> for SpriteSheetHandle {
+    fn from(handle: Handle<TextureAsset>) -> Self {
+        SpriteSheetHandle(SheetId::Asset(handle.id))
+    }
+}
+
+/// Loads the texture of `data` in the background, see [`AssetServerEventQueueExt::load_texture`]
+pub struct LoadTextureEvent {
+    pub handle: Handle<TextureAsset>,
+    pub data: SpriteSheetData,
+}
+
+/// Emitted once the asset of `handle` is ready to be used
+pub struct AssetLoadedEvent<T> {
+    pub handle: Handle<T>,
+}
+
+/// Emitted when the asset of `handle` can't be loaded, its sprites keep being drawn with a
+/// placeholder
+pub struct AssetLoadFailedEvent<T> {
+    pub handle: Handle<T>,
+    pub error: String,
+}
+
+/// Emitted when a texture is decoded, for the renderer to upload it
+pub struct TextureDecodedEvent {
+    pub(crate) handle: Handle<TextureAsset>,
+    pub(crate) data: SpriteSheetData,
+    pub(crate) image: Take<RgbaImage>,
+}
+
+/// Decodes the assets on background threads, so that loading them does not stall the frames.
+///
+/// The decoded textures are uploaded by the
+/// [`Renderer2DModule`](crate::Renderer2DModule), which then emits an [`AssetLoadedEvent`].
+/// Decoding errors are logged and emitted as an [`AssetLoadFailedEvent`], the sprites of the
+/// texture keep being drawn with a placeholder.
+pub struct AssetServerModule {
+    decoding: Vec<(LoadTextureEvent, Decoding<RgbaImage>)>,
+}
+
+impl Module for AssetServerModule {
+    type ListeningTo = (LoadTextureEvent, PreSubmitRenderEvent);
+    fn new(_: &mut Engine) -> AnyResult<Self> {
+        Ok(Self {
+            decoding: Vec::new(),
+        })
+    }
+}
+
+impl AssetServerModule {
+    /// Number of assets still being decoded
+    pub fn pending(&self) -> usize {
+        self.decoding.len()
+    }
+}
+
+impl Listener<LoadTextureEvent> for AssetServerModule {
+    fn on_event(&mut self, event: &mut LoadTextureEvent, _: &mut EventQueue) {
+        let decoding = decode_image(event.data.path.clone());
+        let event = LoadTextureEvent {
+            handle: event.handle,
+            data: event.data.clone(),
+        };
+        self.decoding.push((event, decoding));
+    }
+}
+
+impl Listener<PreSubmitRenderEvent> for AssetServerModule {
+    fn on_event(&mut self, _: &mut PreSubmitRenderEvent, queue: &mut EventQueue) {
+        for (event, decoding) in std::mem::take(&mut self.decoding) {
+            let LoadTextureEvent { handle, data } = &event;
+            match decoding.poll() {
+                None => self.decoding.push((event, decoding)),
+                Some(Ok(image)) => queue.push(TextureDecodedEvent {
+                    handle: *handle,
+                    data: data.clone(),
+                    image: Take::new(image),
+                }),
+                Some(Err(error)) => {
+                    error!("Unable to load texture at {} ! error: {error}", data.path);
+                    queue.push(AssetLoadFailedEvent {
+                        handle: *handle,
+                        error,
+                    });
+                }
+            }
+        }
+    }
+}
+
+pub trait AssetServerEventQueueExt {
+    /// Starts loading the texture of `data` in the background, the returned handle can be
+    /// drawn as a sprite sheet right away, see [`AssetServerModule`].
+    fn load_texture(&mut self, data: SpriteSheetData) -> Handle<TextureAsset>;
+}
+impl AssetServerEventQueueExt for EventQueue {
+    fn load_texture(&mut self, data: SpriteSheetData) -> Handle<TextureAsset> {
+        let handle = Handle::new();
+        self.push(LoadTextureEvent { handle, data });
+        handle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use cgmath::Vector2;
+
+    use super::*;
+
+    /// Records the failures of the asset server
+    struct Failures(Vec<Handle<TextureAsset>>);
+    impl Module for Failures {
+        type ListeningTo = (AssetLoadFailedEvent<TextureAsset>,);
+        fn new(ctx: &mut Engine) -> AnyResult<Self> {
+            ctx.dependency::<AssetServerModule>()?;
+            Ok(Self(Vec::new()))
+        }
+    }
+    impl Listener<AssetLoadFailedEvent<TextureAsset>> for Failures {
+        fn on_event(&mut self, event: &mut AssetLoadFailedEvent<TextureAsset>, _: &mut EventQueue) {
+            self.0.push(event.handle);
+        }
+    }
+
+    #[test]
+    fn missing_textures_emit_a_failure() {
+        let mut engine = Engine::new_without_logger::<Failures>();
+        let handle = Handle::new();
+        engine.run_with(LoadTextureEvent {
+            handle,
+            data: SpriteSheetData {
+                path: "does/not/exist.png".to_string(),
+                sprite_px_size: Vector2::new(8, 8),
+            },
+        });
+
+        let start = Instant::now();
+        while engine.module::<AssetServerModule>().unwrap().pending() > 0 {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "decoding timed out"
+            );
+            engine.run_with(PreSubmitRenderEvent);
+        }
+        assert_eq!(engine.module::<Failures>().unwrap().0, [handle]);
+    }
+}
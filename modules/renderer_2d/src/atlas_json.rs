@@ -0,0 +1,653 @@
+//! Sprite sheets described by the JSON files of TexturePacker and Aseprite.
+
+use std::{collections::HashMap, fmt::Display, time::Duration};
+
+use cgmath::Vector2;
+
+use crate::{
+    animation::{Animation, AnimationMode},
+    texture::{RgbaImage, Sprite, SpriteSheetHandle, SpriteSheetsRegistry},
+};
+
+/// Duration of the frames without one, such as the TexturePacker ones
+const DEFAULT_FRAME_DURATION: Duration = Duration::from_millis(100);
+
+/// Arrays and objects nested deeper are rejected, sheet descriptions only nest a few levels
+const MAX_DEPTH: usize = 64;
+
+/// Named sprites and animations of a sheet, see [`SpriteSheetsRegistry::add_from_atlas_json`]
+#[derive(Clone)]
+pub struct SpriteAtlas {
+    pub sheet: SpriteSheetHandle,
+    /// Frames in the order of the file, with their name and duration
+    pub frames: Vec<(String, Sprite, Duration)>,
+    sprites: HashMap<String, usize>,
+    animations: HashMap<String, Animation>,
+}
+
+impl SpriteAtlas {
+    /// Sprite of the frame called `name` in the file, such as `"player_idle.png"`
+    pub fn sprite(&self, name: &str) -> Option<Sprite> {
+        self.sprites.get(name).map(|&i| self.frames[i].1.clone())
+    }
+
+    /// Animation of the frame tag called `name`, made of its frames with their durations
+    pub fn animation(&self, name: &str) -> Option<&Animation> {
+        self.animations.get(name)
+    }
+
+    pub fn animation_names(&self) -> impl Iterator<Item = &str> {
+        self.animations.keys().map(String::as_str)
+    }
+}
+
+/// Invalid or unsupported sprite sheet description
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasJsonError(String);
+
+impl Display for AtlasJsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid sprite sheet JSON: {}", self.0)
+    }
+}
+
+impl std::error::Error for AtlasJsonError {}
+
+fn error<T>(message: impl Into<String>) -> Result<T, AtlasJsonError> {
+    Err(AtlasJsonError(message.into()))
+}
+
+impl SpriteSheetsRegistry {
+    /// Registers `image` like [`SpriteSheetsRegistry::register_image`], with the frames and
+    /// animations described by `json` as exported by TexturePacker or Aseprite.
+    ///
+    /// Both the hash and array forms of `"frames"` are supported, `"meta"."frameTags"` become
+    /// animations. Frames are also named in the registry, see [`SpriteSheetsRegistry::sprite`].
+    /// Trimmed and rotated frames are not supported and return an error, the sheet must be
+    /// exported without trimming or rotating its sprites. Frames outside of `image` are errors.
+    pub fn add_from_atlas_json(
+        &mut self,
+        image: RgbaImage,
+        json: &str,
+    ) -> Result<SpriteAtlas, AtlasJsonError> {
+        let root = Parser::parse(json)?;
+        let frames = parse_frames(
+            root.get("frames")
+                .ok_or_else(|| AtlasJsonError("missing \"frames\"".to_string()))?,
+        )?;
+        let tags = match root.get("meta").and_then(|meta| meta.get("frameTags")) {
+            Some(tags) => parse_tags(tags, frames.len())?,
+            None => Vec::new(),
+        };
+        let (width, height) = image.dimensions();
+        for frame in &frames {
+            let end = |start: u32, size: u32| start.checked_add(size);
+            let inside = end(frame.position.x, frame.size.x).is_some_and(|x| x <= width)
+                && end(frame.position.y, frame.size.y).is_some_and(|y| y <= height);
+            if !inside {
+                return error(format!(
+                    "the frame {} is outside of the {width}x{height} image",
+                    frame.name
+                ));
+            }
+        }
+
+        let sheet = self.register_image(image, Vector2::new(1, 1));
+        let frames = frames
+            .into_iter()
+            .map(|frame| {
+                let sprite = Sprite::from_pixel_rect(sheet, frame.position, frame.size);
+                (frame.name, sprite, frame.duration)
+            })
+            .collect::<Vec<_>>();
+        for (name, sprite, _) in &frames {
+            self.name_sprite(name.clone(), sprite.clone());
+        }
+        let sprites = frames
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _, _))| (name.clone(), i))
+            .collect();
+        let animations = tags
+            .into_iter()
+            .map(|tag| {
+                let mut indices = (tag.from..=tag.to).collect::<Vec<_>>();
+                if tag.reverse {
+                    indices.reverse();
+                }
+                let animation = indices.into_iter().fold(Animation::new(tag.mode), |a, i| {
+                    a.frame(frames[i].1.clone(), frames[i].2)
+                });
+                (tag.name, animation)
+            })
+            .collect();
+
+        Ok(SpriteAtlas {
+            sheet,
+            frames,
+            sprites,
+            animations,
+        })
+    }
+}
+
+struct Frame {
+    name: String,
+    position: Vector2<u32>,
+    size: Vector2<u32>,
+    duration: Duration,
+}
+
+struct Tag {
+    name: String,
+    from: usize,
+    to: usize,
+    reverse: bool,
+    mode: AnimationMode,
+}
+
+fn parse_frames(frames: &Json) -> Result<Vec<Frame>, AtlasJsonError> {
+    let named = match frames {
+        Json::Object(fields) => fields.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        Json::Array(frames) => frames
+            .iter()
+            .map(|frame| match frame.get("filename") {
+                Some(Json::String(name)) => Ok((name.clone(), frame)),
+                _ => error("a frame of the \"frames\" array has no \"filename\""),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return error("\"frames\" must be an object or an array"),
+    };
+
+    named
+        .into_iter()
+        .map(|(name, frame)| {
+            if matches!(frame.get("rotated"), Some(Json::Bool(true))) {
+                return error(format!(
+                    "the frame {name} is rotated, which is not supported"
+                ));
+            }
+            if is_trimmed(frame) {
+                return error(format!(
+                    "the frame {name} is trimmed, which is not supported"
+                ));
+            }
+            let rect = frame
+                .get("frame")
+                .ok_or_else(|| AtlasJsonError(format!("the frame {name} has no \"frame\"")))?;
+            let field = |key| {
+                integer(rect.get(key)).ok_or_else(|| {
+                    AtlasJsonError(format!("the frame {name} has an invalid \"{key}\""))
+                })
+            };
+            let duration = match frame.get("duration") {
+                Some(Json::Number(ms)) if *ms >= 0. => Duration::from_secs_f64(ms / 1000.),
+                None => DEFAULT_FRAME_DURATION,
+                _ => return error(format!("the frame {name} has an invalid \"duration\"")),
+            };
+            Ok(Frame {
+                position: Vector2::new(field("x")?, field("y")?),
+                size: Vector2::new(field("w")?, field("h")?),
+                name,
+                duration,
+            })
+        })
+        .collect()
+}
+
+/// Value of `json` if it is a whole number fitting in a `u32`
+fn integer(json: Option<&Json>) -> Option<u32> {
+    match json {
+        Some(Json::Number(n)) if n.fract() == 0. && (0. ..=u32::MAX as f64).contains(n) => {
+            Some(*n as u32)
+        }
+        _ => None,
+    }
+}
+
+/// Whether transparent margins were removed from the frame, its offset in the original
+/// sprite being its `"spriteSourceSize"`
+fn is_trimmed(frame: &Json) -> bool {
+    let number = |json: &Json, key| match json.get(key) {
+        Some(Json::Number(n)) => Some(*n),
+        _ => None,
+    };
+    match (frame.get("spriteSourceSize"), frame.get("sourceSize")) {
+        // The flag can be set even when no margin was removed
+        (Some(trim), Some(source)) => {
+            number(trim, "x") != Some(0.)
+                || number(trim, "y") != Some(0.)
+                || number(trim, "w") != number(source, "w")
+                || number(trim, "h") != number(source, "h")
+        }
+        _ => matches!(frame.get("trimmed"), Some(Json::Bool(true))),
+    }
+}
+
+fn parse_tags(tags: &Json, frame_count: usize) -> Result<Vec<Tag>, AtlasJsonError> {
+    let Json::Array(tags) = tags else {
+        return error("\"frameTags\" must be an array");
+    };
+    tags.iter()
+        .map(|tag| {
+            let Some(Json::String(name)) = tag.get("name") else {
+                return error("a frame tag has no \"name\"");
+            };
+            let index = |key| match integer(tag.get(key)) {
+                Some(i) if (i as usize) < frame_count => Ok(i as usize),
+                _ => error(format!("the frame tag {name} has an invalid \"{key}\"")),
+            };
+            let (from, to) = (index("from")?, index("to")?);
+            if from > to {
+                return error(format!("the frame tag {name} ends before it starts"));
+            }
+            let (reverse, mode) = match tag.get("direction") {
+                None => (false, AnimationMode::Loop),
+                Some(Json::String(direction)) => match direction.as_str() {
+                    "forward" => (false, AnimationMode::Loop),
+                    "reverse" => (true, AnimationMode::Loop),
+                    "pingpong" => (false, AnimationMode::PingPong),
+                    "pingpong_reverse" => (true, AnimationMode::PingPong),
+                    _ => return error(format!("the frame tag {name} has an unknown direction")),
+                },
+                _ => return error(format!("the frame tag {name} has an invalid \"direction\"")),
+            };
+            Ok(Tag {
+                name: name.clone(),
+                from,
+                to,
+                reverse,
+                mode,
+            })
+        })
+        .collect()
+}
+
+/// JSON value, objects keep the order of their fields since frame tags refer to frames by index
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// Minimal JSON parser, enough for the sheet descriptions
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    /// Arrays and objects being parsed
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(json: &'a str) -> Result<Json, AtlasJsonError> {
+        let mut parser = Parser {
+            chars: json.char_indices().peekable(),
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(value),
+            Some((i, _)) => error(format!("unexpected content at byte {i}")),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), AtlasJsonError> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            Some((i, c)) => error(format!("expected '{expected}' at byte {i}, found '{c}'")),
+            None => error(format!("expected '{expected}', found the end")),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, AtlasJsonError> {
+        self.skip_whitespace();
+        let Some(&(i, c)) = self.chars.peek() else {
+            return error("unexpected end");
+        };
+        match c {
+            '{' => self.object(),
+            '[' => self.array(),
+            '"' => Ok(Json::String(self.string()?)),
+            't' => self.keyword("true", Json::Bool(true)),
+            'f' => self.keyword("false", Json::Bool(false)),
+            'n' => self.keyword("null", Json::Null),
+            '-' | '0'..='9' => self.number(),
+            c => error(format!("unexpected '{c}' at byte {i}")),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, AtlasJsonError> {
+        for expected in keyword.chars() {
+            match self.chars.next() {
+                Some((_, c)) if c == expected => {}
+                _ => return error(format!("invalid keyword, expected {keyword}")),
+            }
+        }
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Json, AtlasJsonError> {
+        let mut number = String::new();
+        while let Some((_, c)) = self
+            .chars
+            .next_if(|(_, c)| matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+        {
+            number.push(c);
+        }
+        number
+            .parse()
+            .map(Json::Number)
+            .or_else(|_| error(format!("invalid number {number}")))
+    }
+
+    fn string(&mut self) -> Result<String, AtlasJsonError> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(string),
+                Some((_, '\\')) => match self.chars.next() {
+                    Some((_, 'n')) => string.push('\n'),
+                    Some((_, 't')) => string.push('\t'),
+                    Some((_, 'r')) => string.push('\r'),
+                    Some((_, 'b')) => string.push('\u{8}'),
+                    Some((_, 'f')) => string.push('\u{c}'),
+                    Some((_, 'u')) => string.push(self.unicode_escape()?),
+                    Some((_, c @ ('"' | '\\' | '/'))) => string.push(c),
+                    Some((i, c)) => return error(format!("invalid escape '\\{c}' at byte {i}")),
+                    None => return error("unterminated string"),
+                },
+                Some((_, c)) => string.push(c),
+                None => return error("unterminated string"),
+            }
+        }
+    }
+
+    /// Character of a `\u` escape, after the `u`, characters outside of the basic plane being
+    /// escaped as a pair of UTF-16 surrogates
+    fn unicode_escape(&mut self) -> Result<char, AtlasJsonError> {
+        let code = match self.hex_code()? {
+            high @ 0xd800..=0xdbff => {
+                let low = match (self.chars.next(), self.chars.next()) {
+                    (Some((_, '\\')), Some((_, 'u'))) => self.hex_code()?,
+                    _ => return error("unpaired surrogate in a \\u escape"),
+                };
+                if !(0xdc00..=0xdfff).contains(&low) {
+                    return error("unpaired surrogate in a \\u escape");
+                }
+                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+            }
+            code => code,
+        };
+        char::from_u32(code).ok_or(AtlasJsonError(
+            "unpaired surrogate in a \\u escape".to_string(),
+        ))
+    }
+
+    /// Four hexadecimal digits of a `\u` escape
+    fn hex_code(&mut self) -> Result<u32, AtlasJsonError> {
+        (0..4)
+            .map(|_| self.chars.next().and_then(|(_, c)| c.to_digit(16)))
+            .try_fold(0, |code, digit| Some(code * 16 + digit?))
+            .ok_or(AtlasJsonError("invalid \\u escape".to_string()))
+    }
+
+    /// Items separated by commas until `end`, each one parsed by `item`
+    fn list(
+        &mut self,
+        end: char,
+        mut item: impl FnMut(&mut Self) -> Result<(), AtlasJsonError>,
+    ) -> Result<(), AtlasJsonError> {
+        if self.depth == MAX_DEPTH {
+            return error(format!("values are nested deeper than {MAX_DEPTH} levels"));
+        }
+        self.depth += 1;
+        let result = self.items(end, &mut item);
+        self.depth -= 1;
+        result
+    }
+
+    fn items(
+        &mut self,
+        end: char,
+        item: &mut impl FnMut(&mut Self) -> Result<(), AtlasJsonError>,
+    ) -> Result<(), AtlasJsonError> {
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, c)| *c == end).is_some() {
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => {}
+                Some((_, c)) if c == end => return Ok(()),
+                Some((i, c)) => {
+                    return error(format!("expected ',' or '{end}' at byte {i}, found '{c}'"))
+                }
+                None => return error(format!("expected '{end}', found the end")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, AtlasJsonError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.list(']', |parser| {
+            items.push(parser.value()?);
+            Ok(())
+        })?;
+        Ok(Json::Array(items))
+    }
+
+    fn object(&mut self) -> Result<Json, AtlasJsonError> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.list('}', |parser| {
+            parser.skip_whitespace();
+            let key = parser.string()?;
+            parser.expect(':')?;
+            fields.push((key, parser.value()?));
+            Ok(())
+        })?;
+        Ok(Json::Object(fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atlas(json: &str) -> Result<SpriteAtlas, AtlasJsonError> {
+        SpriteSheetsRegistry::new().add_from_atlas_json(RgbaImage::new(64, 16), json)
+    }
+
+    fn rect(sprite: &Sprite) -> ([u32; 2], [u32; 2]) {
+        (sprite.position.into(), sprite.size.into())
+    }
+
+    #[test]
+    fn frames_hash_keeps_the_file_order() {
+        let atlas = atlas(
+            r#"{"frames": {
+                "b.png": {"frame": {"x": 16, "y": 0, "w": 16, "h": 8}},
+                "a.png": {"frame": {"x": 0, "y": 8, "w": 8, "h": 8}, "duration": 250}
+            }}"#,
+        )
+        .unwrap();
+
+        let names = atlas
+            .frames
+            .iter()
+            .map(|f| f.0.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["b.png", "a.png"]);
+        assert_eq!(rect(&atlas.sprite("b.png").unwrap()), ([16, 0], [16, 8]));
+        assert_eq!(rect(&atlas.sprite("a.png").unwrap()), ([0, 8], [8, 8]));
+        assert_eq!(atlas.frames[0].2, DEFAULT_FRAME_DURATION);
+        assert_eq!(atlas.frames[1].2, Duration::from_millis(250));
+    }
+
+    #[test]
+    fn frames_array_is_named_by_filename() {
+        let atlas = atlas(
+            r#"{"frames": [
+                {"filename": "idle 0", "frame": {"x": 0, "y": 0, "w": 16, "h": 16}},
+                {"filename": "idle 1", "frame": {"x": 16, "y": 0, "w": 16, "h": 16}}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(rect(&atlas.sprite("idle 1").unwrap()), ([16, 0], [16, 16]));
+        assert!(atlas.sprite("idle 2").is_none());
+    }
+
+    #[test]
+    fn frame_tags_follow_their_direction() {
+        let frame = |x| format!(r#"{{"frame": {{"x": {x}, "y": 0, "w": 16, "h": 16}}}}"#);
+        let tag = |name, direction| {
+            format!(r#"{{"name": "{name}", "from": 1, "to": 3, "direction": "{direction}"}}"#)
+        };
+        let json = format!(
+            r#"{{"frames": [{}], "meta": {{"frameTags": [{}, {}, {}, {}]}}}}"#,
+            (0..4).map(|i| frame(i * 16)).collect::<Vec<_>>().join(","),
+            tag("forward", "forward"),
+            tag("reverse", "reverse"),
+            tag("pingpong", "pingpong"),
+            tag("pingpong_reverse", "pingpong_reverse"),
+        )
+        // Array frames need a filename
+        .replace(r#"{"frame""#, r#"{"filename": "f", "frame""#);
+        let atlas = atlas(&json).unwrap();
+
+        let first = |name| {
+            let animation = atlas.animation(name).unwrap();
+            (
+                animation.current_sprite(Duration::ZERO).position.x,
+                animation.mode,
+            )
+        };
+        assert_eq!(first("forward"), (16, AnimationMode::Loop));
+        assert_eq!(first("reverse"), (48, AnimationMode::Loop));
+        assert_eq!(first("pingpong"), (16, AnimationMode::PingPong));
+        assert_eq!(first("pingpong_reverse"), (48, AnimationMode::PingPong));
+        assert_eq!(
+            atlas.animation("forward").unwrap().duration(),
+            3 * DEFAULT_FRAME_DURATION
+        );
+    }
+
+    #[test]
+    fn untrimmed_frames_are_accepted() {
+        let atlas = atlas(
+            r#"{"frames": {"a": {
+                "frame": {"x": 0, "y": 0, "w": 16, "h": 16}, "trimmed": true,
+                "spriteSourceSize": {"x": 0, "y": 0, "w": 16, "h": 16},
+                "sourceSize": {"w": 16, "h": 16}
+            }}}"#,
+        );
+        assert!(atlas.is_ok());
+    }
+
+    #[test]
+    fn unsupported_frames_are_rejected() {
+        for frame in [
+            r#"{"frame": {"x": 0, "y": 0, "w": 8, "h": 8}, "rotated": true}"#,
+            r#"{"frame": {"x": 0, "y": 0, "w": 8, "h": 8}, "trimmed": true}"#,
+            r#"{"frame": {"x": 0, "y": 0, "w": 8, "h": 8},
+                "spriteSourceSize": {"x": 4, "y": 4, "w": 8, "h": 8},
+                "sourceSize": {"w": 16, "h": 16}}"#,
+        ] {
+            let json = format!(r#"{{"frames": {{"a": {frame}}}}}"#);
+            assert!(atlas(&json).is_err(), "{frame}");
+        }
+    }
+
+    #[test]
+    fn malformed_descriptions_are_rejected() {
+        for json in [
+            "",
+            "{",
+            r#"{"frames": {}} trailing"#,
+            r#"{"frames": {"a": {"frame": {"x": 0, "y": 0, "w": 8}}}}"#,
+            r#"{"frames": {"a": {"frame": {"x": -1, "y": 0, "w": 8, "h": 8}}}}"#,
+            r#"{"frames": [{"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}]}"#,
+            r#"{"frames": "a"}"#,
+            r#"{"meta": {}}"#,
+            r#"{"frames": {}, "meta": {"frameTags": [{"name": "a", "from": 0, "to": 0}]}}"#,
+            r#"{"frames": {"a": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}},
+                "meta": {"frameTags": [{"name": "a", "from": 0, "to": 0, "direction": "up"}]}}"#,
+            r#"{"frames": {"a\u12"}}"#,
+            r#"{"frames": {"a\q": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}}}"#,
+            r#"{"frames": {"a\ud83d": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}}}"#,
+            r#"{"frames": {"a\ude00": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}}}"#,
+            r#"{"frames": {"a": {"frame": {"x": 0.5, "y": 0, "w": 8, "h": 8}}}}"#,
+            r#"{"frames": {"a": {"frame": {"x": 1e10, "y": 0, "w": 8, "h": 8}}}}"#,
+            r#"{"frames": {"a": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}},
+                "meta": {"frameTags": [{"name": "a", "from": 0.5, "to": 0}]}}"#,
+        ] {
+            assert!(atlas(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn escapes_are_decoded() {
+        let atlas = atlas(
+            r#"{"frames": {"\"\\\/\u00e9\ud83d\ude00": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}}}"#,
+        )
+        .unwrap();
+        assert_eq!(atlas.frames[0].0, "\"\\/\u{e9}\u{1f600}");
+    }
+
+    #[test]
+    fn frames_outside_of_the_image_are_rejected() {
+        for rect in [
+            r#"{"x": 60, "y": 0, "w": 8, "h": 8}"#,
+            r#"{"x": 0, "y": 16, "w": 8, "h": 1}"#,
+            r#"{"x": 4294967295, "y": 0, "w": 1, "h": 1}"#,
+        ] {
+            let json = format!(r#"{{"frames": {{"a": {{"frame": {rect}}}}}}}"#);
+            let Err(error) = atlas(&json) else {
+                panic!("{rect} was accepted");
+            };
+            assert!(error.0.contains("outside"), "{error}");
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let json = format!(
+            r#"{{"frames": {{}}, "meta": {}{}}}"#,
+            "[".repeat(100),
+            "]".repeat(100)
+        );
+        let Err(error) = atlas(&json) else {
+            panic!("deeply nested JSON was accepted");
+        };
+        assert!(error.0.contains("nested"), "{error}");
+
+        let json = format!(
+            r#"{{"frames": {{}}, "meta": {}{}}}"#,
+            "[".repeat(10),
+            "]".repeat(10)
+        );
+        assert!(atlas(&json).is_ok());
+    }
+}
@@ -0,0 +1,35 @@
+// Bloom in four passes: bright pixels are extracted at half resolution, blurred horizontally
+// then vertically at quarter resolution, and added back to the input of the effect
+
+struct BloomUniforms {
+    direction: vec2<f32>,
+    // Blur radius in texels of the input texture
+    radius: f32,
+    // Brightness above which pixels glow
+    threshold: f32,
+    intensity: f32,
+    pass_index: u32,
+    _padding: vec2<f32>,
+};
+@group(0) @binding(2)
+var<uniform> bloom: BloomUniforms;
+
+@fragment
+fn fs_main(in: PostVertexOutput) -> @location(0) vec4<f32> {
+    switch bloom.pass_index {
+        case 0u: {
+            let color = textureSampleLevel(input_texture, input_sampler, in.tex_coords, 0.0);
+            let brightness = max(color.r, max(color.g, color.b));
+            let contribution = max(brightness - bloom.threshold, 0.0) / max(brightness, 0.0001);
+            return vec4<f32>(color.rgb * contribution, 1.0);
+        }
+        case 1u, 2u: {
+            return gaussian_blur(in.tex_coords, bloom.direction, bloom.radius);
+        }
+        default: {
+            let scene = textureSampleLevel(effect_input_texture, input_sampler, in.tex_coords, 0.0);
+            let glow = textureSampleLevel(input_texture, input_sampler, in.tex_coords, 0.0);
+            return vec4<f32>(scene.rgb + glow.rgb * bloom.intensity, scene.a);
+        }
+    }
+}
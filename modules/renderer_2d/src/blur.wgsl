@@ -0,0 +1,27 @@
+// Gaussian blur of the input texture along one axis, shared by the blur and bloom effects
+
+// Taps on each side of the center, wider radii space them out
+const MAX_BLUR_TAPS: i32 = 32;
+
+// `direction` is the pixel step between two taps, (1, 0) to blur horizontally and (0, 1) vertically
+fn gaussian_blur(tex_coords: vec2<f32>, direction: vec2<f32>, radius: f32) -> vec4<f32> {
+    if radius < 0.5 {
+        return textureSampleLevel(input_texture, input_sampler, tex_coords, 0.0);
+    }
+
+    let texel = direction / vec2<f32>(textureDimensions(input_texture));
+    let taps = min(i32(ceil(radius)), MAX_BLUR_TAPS);
+    let spacing = radius / f32(taps);
+    // The radius covers two standard deviations, past which the weights are negligible
+    let sigma = radius / 2.0;
+
+    var color = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var i = -taps; i <= taps; i++) {
+        let offset = f32(i) * spacing;
+        let weight = exp(-(offset * offset) / (2.0 * sigma * sigma));
+        color += textureSampleLevel(input_texture, input_sampler, tex_coords + texel * offset, 0.0) * weight;
+        total += weight;
+    }
+    return color / total;
+}
@@ -0,0 +1,15 @@
+// Separable gaussian blur, run once horizontally then once vertically
+
+struct BlurUniforms {
+    direction: vec2<f32>,
+    // Blur radius in pixels
+    radius: f32,
+    _padding: f32,
+};
+@group(0) @binding(2)
+var<uniform> blur: BlurUniforms;
+
+@fragment
+fn fs_main(in: PostVertexOutput) -> @location(0) vec4<f32> {
+    return gaussian_blur(in.tex_coords, blur.direction, blur.radius);
+}
@@ -0,0 +1,44 @@
+// Remaps the colors with a LUT strip of N tiles of N x N pixels, blue growing from tile to tile,
+// red along x and green along y within a tile
+
+struct ColorGradeUniforms {
+    // Blend between the original colors (0) and the graded ones (1)
+    amount: f32,
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
+};
+@group(0) @binding(2)
+var<uniform> grade: ColorGradeUniforms;
+// sRGB texture, so that the graded colors are sampled in linear space like the frame
+@group(0) @binding(4)
+var lut_texture: texture_2d<f32>;
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+// Color of the tile `tile` at `red_green`, in pixels within the tile
+fn lut_sample(tile: f32, red_green: vec2<f32>, size: f32) -> vec3<f32> {
+    let uv = vec2<f32>((tile * size + red_green.x + 0.5) / (size * size), (red_green.y + 0.5) / size);
+    return textureSampleLevel(lut_texture, input_sampler, uv, 0.0).rgb;
+}
+
+@fragment
+fn fs_main(in: PostVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(input_texture, input_sampler, in.tex_coords, 0.0);
+    let size = f32(textureDimensions(lut_texture).y);
+
+    // LUTs are authored on sRGB colors
+    let lut_color = clamp(linear_to_srgb(color.rgb), vec3<f32>(0.0), vec3<f32>(1.0)) * (size - 1.0);
+    let tile = floor(lut_color.b);
+    let next_tile = min(tile + 1.0, size - 1.0);
+    let graded = mix(
+        lut_sample(tile, lut_color.rg, size),
+        lut_sample(next_tile, lut_color.rg, size),
+        lut_color.b - tile
+    );
+    return vec4<f32>(mix(color.rgb, graded, grade.amount), color.a);
+}
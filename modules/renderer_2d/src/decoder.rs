@@ -0,0 +1,120 @@
+use std::{
+    any::Any,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+};
+
+use crate::texture::{try_load_sheet_image, RgbaImage};
+
+/// Decoding threads are capped so that loading many sheets at once doesn't flood the system
+const MAX_WORKERS: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Queue of the threads shared by every decoding job, spawned on first use
+fn pool() -> &'static Mutex<Sender<Job>> {
+    static POOL: OnceLock<Mutex<Sender<Job>>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(MAX_WORKERS);
+        for k in 0..workers {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("rgine_decoder_{k}"))
+                .spawn(move || loop {
+                    // The lock is released before running the job
+                    let job = receiver.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                })
+                .expect("Failed to spawn an image decoding thread!");
+        }
+        Mutex::new(sender)
+    })
+}
+
+/// Result of a job running on the decoding threads
+pub(crate) struct Decoding<T>(Receiver<Result<T, String>>);
+
+impl<T> Decoding<T> {
+    /// Result of the job, `None` while it is running
+    pub(crate) fn poll(&self) -> Option<Result<T, String>> {
+        match self.0.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(stopped())),
+        }
+    }
+
+    /// Blocks until the job is done
+    pub(crate) fn wait(self) -> Result<T, String> {
+        self.0.recv().unwrap_or_else(|_| Err(stopped()))
+    }
+}
+
+fn stopped() -> String {
+    "the decoding thread stopped".to_string()
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown error".to_string());
+    format!("the decoder panicked: {message}")
+}
+
+/// Runs `job` on the decoding threads, a panic of the job is returned as an error.
+pub(crate) fn decode<T: Send + 'static>(
+    job: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Decoding<T> {
+    let (sender, receiver) = mpsc::channel();
+    let job = Box::new(move || {
+        let result = catch_unwind(AssertUnwindSafe(job)).unwrap_or_else(|p| Err(panic_message(p)));
+        // The result is dropped if nobody waits for it anymore
+        let _ = sender.send(result);
+    });
+    // Workers never stop while the sender is alive, so sending can't fail
+    let _ = pool().lock().unwrap().send(job);
+    Decoding(receiver)
+}
+
+/// Reads and decodes the image file at `path` on the decoding threads
+pub(crate) fn decode_image(path: String) -> Decoding<RgbaImage> {
+    decode(move || try_load_sheet_image(&path).map_err(|e| e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_result_is_returned() {
+        assert_eq!(decode(|| Ok(1 + 1)).wait(), Ok(2));
+    }
+
+    #[test]
+    fn panics_are_returned_as_errors() {
+        let error = decode::<()>(|| panic!("corrupted")).wait().unwrap_err();
+        assert!(error.contains("corrupted"), "{error}");
+        // The workers survive the panic
+        assert_eq!(decode(|| Ok("still decoding")).wait(), Ok("still decoding"));
+    }
+
+    #[test]
+    fn missing_files_are_errors() {
+        assert!(decode_image("does/not/exist.png".to_string())
+            .wait()
+            .is_err());
+    }
+}
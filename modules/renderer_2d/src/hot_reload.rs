@@ -0,0 +1,103 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rgine_graphics::ctx::GraphicsCtx;
+use rgine_logger::{info, warn};
+
+use crate::texture::{try_load_sheet_image, Atlas, SpriteSheetHandle, SpriteSheetsRegistry};
+
+/// Watches the files of the sheets of a registry to upload them again when they change
+pub(crate) struct SheetWatcher {
+    _watcher: RecommendedWatcher,
+    changes: Receiver<notify::Result<notify::Event>>,
+    sheets: HashMap<PathBuf, Vec<SpriteSheetHandle>>,
+}
+
+impl SheetWatcher {
+    pub(crate) fn new(registry: &SpriteSheetsRegistry) -> notify::Result<Self> {
+        let mut sheets = HashMap::<_, Vec<_>>::new();
+        for (handle, path) in registry.file_sheets() {
+            sheets
+                .entry(canonical(Path::new(path)))
+                .or_default()
+                .push(handle);
+        }
+
+        let (sender, changes) = channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })?;
+        // Directories are watched rather than files, editors often save by replacing the file
+        let dirs = sheets
+            .keys()
+            .filter_map(|path| path.parent())
+            .collect::<HashSet<_>>();
+        for dir in dirs {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            changes,
+            sheets,
+        })
+    }
+
+    /// Uploads the sheets whose file changed since the last call, returning their handles
+    pub(crate) fn reload_changed(
+        &self,
+        ctx: &GraphicsCtx,
+        atlas: &Atlas,
+    ) -> Vec<(SpriteSheetHandle, String)> {
+        // A single save usually triggers several events
+        let changed = self
+            .changes
+            .try_iter()
+            .filter_map(|event| match event {
+                Ok(event) => Some(event),
+                Err(e) => {
+                    warn!("Sprite sheets watcher error: {e}");
+                    None
+                }
+            })
+            .filter(|event| matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)))
+            .flat_map(|event| event.paths)
+            .map(|path| canonical(&path))
+            .filter(|path| self.sheets.contains_key(path))
+            .collect::<HashSet<_>>();
+
+        let mut reloaded = Vec::new();
+        for path in changed {
+            let path_str = path.to_string_lossy().into_owned();
+            // The file may still be being written, it is loaded again on the next event
+            let image = match try_load_sheet_image(&path_str) {
+                Ok(image) => image,
+                Err(e) => {
+                    warn!(
+                        "Unable to reload sprite(sheet) at {} ! error: {e}",
+                        path_str
+                    );
+                    continue;
+                }
+            };
+            for &handle in &self.sheets[&path] {
+                match atlas.reload_sheet(ctx, handle, &image) {
+                    Ok(()) => {
+                        info!("Reloaded sprite(sheet) at {}", path_str);
+                        reloaded.push((handle, path_str.clone()));
+                    }
+                    Err(e) => warn!("Unable to reload sprite(sheet) at {} ! {e}", path_str),
+                }
+            }
+        }
+        reloaded
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
@@ -1,22 +1,68 @@
-use cgmath::{Array, Matrix3, Vector2};
-use renderer::SpriteRenderer;
+use std::rc::Rc;
+
+use cgmath::{Matrix3, Vector2};
+use renderer::{SpriteRenderer, SpriteRendererConfig};
 use rgine_assets::AssetsModule;
 use rgine_graphics::{
-    GraphicsModule, PreSubmitRenderEvent, SubmitRenderEvent, SurfaceResizeEvent, WindowReadyEvent,
+    GraphicsDeviceLostEvent, GraphicsModule, PreSubmitRenderEvent, SubmitRenderEvent,
+    SurfaceResizeEvent, WindowReadyEvent,
 };
+use rgine_logger::error;
 use rgine_modules::{
-    events::{EventQueue, Listener},
+    events::{EventQueue, Listener, TryListener},
     AnyResult, Dependency, Engine, Module,
 };
 
-use texture::{DrawParams, Sprite, SpriteSheetsRegistry};
+use asset_server::{
+    AssetLoadFailedEvent, AssetLoadedEvent, AssetServerModule, Handle, TextureAsset,
+    TextureDecodedEvent,
+};
+use decoder::{decode_image, Decoding};
+use particles::ParticleEmitter;
+use post_process::PostEffect;
+use shapes::{GradientDirection, Rect};
+use text::{FontHandle, TextAlign};
+use texture::{
+    Color3, DrawParams, RgbaImage, Sprite, SpriteSheetData, SpriteSheetHandle, SpriteSheetsRegistry,
+};
+use tilemap::Tilemap;
 
-mod renderer;
+pub mod animation;
+pub mod asset_server;
+pub mod atlas_json;
+mod decoder;
+#[cfg(feature = "hot_reload")]
+mod hot_reload;
+mod mipmaps;
+pub mod particles;
+pub mod post_process;
+pub mod renderer;
+pub mod shapes;
+pub mod text;
 pub mod texture;
+pub mod tilemap;
+mod timer;
 
 pub mod prelude {
     pub use crate::{
-        texture::{DrawParams, Sprite, SpriteSheetData, SpriteSheetHandle, SpriteSheetsRegistry},
+        animation::{Animation, AnimationMode},
+        asset_server::{
+            AssetLoadFailedEvent, AssetLoadedEvent, AssetServerEventQueueExt, Handle, TextureAsset,
+        },
+        atlas_json::SpriteAtlas,
+        particles::{ParticleEmitter, ParticleEmitterConfig},
+        post_process::{BloomEffect, BlurEffect, ColorGradeEffect, PassResolution, PostEffect},
+        renderer::{
+            screen_to_normalized, CullMode, PixelOrigin, Projection, RenderStats,
+            RenderTargetError, SpriteRendererConfig, ViewportMode,
+        },
+        shapes::{GradientDirection, Rect},
+        text::{FontData, FontHandle, TextAlign},
+        texture::{
+            BlendMode, DrawParams, Outline, SheetLoadError, SheetTooLargeError, Sprite,
+            SpriteSheetData, SpriteSheetHandle, SpriteSheetsRegistry, SpriteUnit, TintMode,
+        },
+        tilemap::{Tilemap, TilemapSizeError},
         Draw2d, Render2DEvent, Renderer2DModule,
     };
 }
@@ -26,33 +72,178 @@ pub struct DrawSpriteEvent {
     sprite: Sprite,
     params: DrawParams,
 }
-pub struct RegisterSpriteEvent;
+pub struct DrawSpriteBatchEvent {
+    sprites: Vec<(Sprite, DrawParams)>,
+}
+pub struct DrawNineSliceEvent {
+    sprite: Sprite,
+    rect: Rect,
+    border: (f32, f32, f32, f32),
+    params: DrawParams,
+}
+pub struct DrawTextEvent {
+    font: FontHandle,
+    text: String,
+    params: DrawParams,
+}
+pub struct DrawWrappedTextEvent {
+    font: FontHandle,
+    text: String,
+    rect: Rect,
+    align: TextAlign,
+    line_height: f32,
+    params: DrawParams,
+}
+/// Untextured shape, batched with the sprites through a white texel of the atlas
+pub enum DrawShape2DEvent {
+    Rect {
+        rect: Rect,
+        color: Color3,
+        depth: f32,
+    },
+    RoundedRect {
+        rect: Rect,
+        radius: f32,
+        color: Color3,
+        depth: f32,
+    },
+    Circle {
+        center: Vector2<f32>,
+        radius: f32,
+        color: Color3,
+        depth: f32,
+    },
+    GradientRect {
+        rect: Rect,
+        color_a: Color3,
+        color_b: Color3,
+        direction: GradientDirection,
+        depth: f32,
+    },
+    Line {
+        a: Vector2<f32>,
+        b: Vector2<f32>,
+        thickness: f32,
+        color: Color3,
+        depth: f32,
+    },
+}
+pub struct SetCamera2DEvent {
+    view: Matrix3<f32>,
+}
+pub struct SetClearColor2DEvent {
+    color: Option<Color3>,
+}
+pub struct SetDepthSorting2DEvent {
+    enabled: bool,
+}
+pub struct SetTextureSorting2DEvent {
+    enabled: bool,
+}
+pub struct SetWireframe2DEvent {
+    enabled: bool,
+}
+pub struct SetMsaa2DEvent {
+    sample_count: u32,
+}
+pub struct SetRenderer2DConfigEvent {
+    config: SpriteRendererConfig,
+}
+pub struct SubmitToRenderTarget2DEvent {
+    target: SpriteSheetHandle,
+}
+pub struct Flush2DEvent;
+/// Appends a post effect, see [`SpriteRenderer::add_post_effect`]. Effects are kept when the
+/// renderer is recreated.
+pub struct AddPostEffect2DEvent {
+    effect: Rc<dyn PostEffect>,
+}
+pub struct ClearPostEffects2DEvent;
+/// Registers a sprite sheet while the renderer is running, see
+/// [`SpriteRenderer::register_sheet`](renderer::SpriteRenderer::register_sheet).
+///
+/// The image is decoded on a background thread, a [`SpriteSheetRegisteredEvent`] is emitted
+/// with the handle of the new sheet once it is uploaded. Decoding errors are logged.
+///
+/// The sheet is uploaded again when the renderer is recreated, its handle stays valid.
+pub struct RegisterSpriteSheetEvent {
+    pub data: SpriteSheetData,
+}
+pub struct SpriteSheetRegisteredEvent {
+    pub path: String,
+    pub handle: SpriteSheetHandle,
+}
 pub struct RefreshRenderer2DEvent;
+/// Emitted when the file of a sprite sheet changed and the sheet was uploaded again,
+/// see [`SpriteRenderer::reload_changed_sheets`]
+#[cfg(feature = "hot_reload")]
+pub struct TextureReloadedEvent {
+    pub path: String,
+    pub handle: SpriteSheetHandle,
+}
 
+/// Draws the sprites of the main window.
+///
+/// Secondary windows opened with an `OpenWindowEvent` are not drawn to, they have their own
+/// graphics context that the renderer does not share.
 pub struct Renderer2DModule {
     graphics: Dependency<GraphicsModule>,
     asset_loader: Dependency<AssetsModule>,
 
     renderer: Option<SpriteRenderer>,
+    config: SpriteRendererConfig,
+    /// Sheets registered at runtime that are still being decoded
+    decoding_sheets: Vec<(SpriteSheetData, Decoding<RgbaImage>)>,
+    /// Textures decoded by the asset server, uploaded with the sheets registered at runtime
+    decoded_textures: Vec<(Handle<TextureAsset>, SpriteSheetData, RgbaImage)>,
+    /// Images uploaded at runtime with the asset they were loaded for, in upload order. They
+    /// are uploaded again in that order when the renderer is recreated so that handles stay valid
+    uploaded: Vec<(Option<Handle<TextureAsset>>, RgbaImage, Vector2<u32>)>,
+    post_effects: Vec<Rc<dyn PostEffect>>,
 }
 
 impl Module for Renderer2DModule {
     type ListeningTo = (
         WindowReadyEvent,
         RefreshRenderer2DEvent,
+        GraphicsDeviceLostEvent,
         PreSubmitRenderEvent,
         SubmitRenderEvent,
         SurfaceResizeEvent,
         DrawSpriteEvent,
+        DrawSpriteBatchEvent,
+        DrawNineSliceEvent,
+        DrawTextEvent,
+        DrawWrappedTextEvent,
+        DrawShape2DEvent,
+        SetCamera2DEvent,
+        SetClearColor2DEvent,
+        SetDepthSorting2DEvent,
+        SetTextureSorting2DEvent,
+        SetWireframe2DEvent,
+        SetMsaa2DEvent,
+        SetRenderer2DConfigEvent,
+        SubmitToRenderTarget2DEvent,
+        Flush2DEvent,
+        AddPostEffect2DEvent,
+        ClearPostEffects2DEvent,
+        RegisterSpriteSheetEvent,
+        TextureDecodedEvent,
     );
     fn new(ctx: &mut Engine) -> AnyResult<Self> {
         let graphics = ctx.dependency::<GraphicsModule>()?;
         let asset_loader = ctx.dependency::<AssetsModule>()?;
+        ctx.dependency::<AssetServerModule>()?;
 
         Ok(Self {
             graphics,
             asset_loader,
             renderer: None,
+            config: SpriteRendererConfig::default(),
+            decoding_sheets: Vec::new(),
+            decoded_textures: Vec::new(),
+            uploaded: Vec::new(),
+            post_effects: Vec::new(),
         })
     }
 }
@@ -67,37 +258,135 @@ impl Listener<RefreshRenderer2DEvent> for Renderer2DModule {
     fn on_event(&mut self, _: &mut RefreshRenderer2DEvent, _: &mut EventQueue) {
         let g = self.graphics.read_state();
         let assets = self.asset_loader.read_state();
-        self.renderer.replace(SpriteRenderer::new(
-            g.ctx.as_ref().unwrap(),
+        let ctx = g.ctx.as_ref().unwrap();
+        let mut renderer = SpriteRenderer::new(
+            ctx,
             g.window_size().unwrap(),
             assets.get::<SpriteSheetsRegistry>().clone(),
-        ));
+            self.config,
+        );
+        for effect in &self.post_effects {
+            renderer.add_post_effect(ctx, effect.clone());
+        }
+        for (asset, image, sprite_px_size) in &self.uploaded {
+            let uploaded = match asset {
+                Some(handle) => {
+                    renderer.register_asset(ctx, *handle, image.clone(), *sprite_px_size)
+                }
+                None => renderer
+                    .register_sheet(ctx, image.clone(), *sprite_px_size)
+                    .map(|_| ()),
+            };
+            // Only possible if the new device has lower limits than the previous one
+            if let Err(e) = uploaded {
+                error!("Unable to upload a sprite(sheet) again! error: {e}");
+            }
+        }
+        self.renderer.replace(renderer);
+    }
+}
+
+impl Listener<GraphicsDeviceLostEvent> for Renderer2DModule {
+    fn on_event(&mut self, _: &mut GraphicsDeviceLostEvent, queue: &mut EventQueue) {
+        // Resources of the renderer belong to the lost device
+        if self.renderer.take().is_some() {
+            queue.push(RefreshRenderer2DEvent);
+        }
     }
 }
 
 impl Listener<PreSubmitRenderEvent> for Renderer2DModule {
     fn on_event(&mut self, _: &mut PreSubmitRenderEvent, queue: &mut EventQueue) {
+        self.upload_sheets(queue);
         queue.push(Render2DEvent);
     }
 }
 
-impl Listener<SubmitRenderEvent> for Renderer2DModule {
-    fn on_event(&mut self, _: &mut SubmitRenderEvent, _: &mut EventQueue) {
+impl Renderer2DModule {
+    /// Renderer of the window, `None` until the window is ready
+    pub fn renderer(&self) -> Option<&SpriteRenderer> {
+        self.renderer.as_ref()
+    }
+
+    /// Uploads the sheets registered at runtime or changed on the disk
+    fn upload_sheets(&mut self, queue: &mut EventQueue) {
+        let Some(renderer) = &mut self.renderer else {
+            return;
+        };
+        let g = self.graphics.read_state();
+
+        #[cfg(feature = "hot_reload")]
+        for (handle, path) in renderer.reload_changed_sheets(g.ctx.as_ref().unwrap()) {
+            queue.push(TextureReloadedEvent { path, handle });
+        }
+
+        for (data, decoding) in std::mem::take(&mut self.decoding_sheets) {
+            match decoding.poll() {
+                None => self.decoding_sheets.push((data, decoding)),
+                Some(Ok(image)) => {
+                    let ctx = g.ctx.as_ref().unwrap();
+                    match renderer.register_sheet(ctx, image.clone(), data.sprite_px_size) {
+                        Ok(handle) => {
+                            self.uploaded.push((None, image, data.sprite_px_size));
+                            queue.push(SpriteSheetRegisteredEvent {
+                                path: data.path,
+                                handle,
+                            });
+                        }
+                        Err(e) => error!(
+                            "Unable to upload sprite(sheet) at {} ! error: {e}",
+                            data.path
+                        ),
+                    }
+                }
+                Some(Err(e)) => {
+                    error!("Unable to load sprite(sheet) at {} ! error: {e}", data.path)
+                }
+            }
+        }
+
+        for (handle, data, image) in self.decoded_textures.drain(..) {
+            let ctx = g.ctx.as_ref().unwrap();
+            match renderer.register_asset(ctx, handle, image.clone(), data.sprite_px_size) {
+                Ok(()) => {
+                    self.uploaded
+                        .push((Some(handle), image, data.sprite_px_size));
+                    queue.push(AssetLoadedEvent { handle });
+                }
+                Err(e) => {
+                    error!("Unable to upload texture at {} ! error: {e}", data.path);
+                    queue.push(AssetLoadFailedEvent {
+                        handle,
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl TryListener<SubmitRenderEvent> for Renderer2DModule {
+    fn try_on_event(&mut self, _: &mut SubmitRenderEvent, _: &mut EventQueue) -> AnyResult<()> {
         if let Some(renderer) = &mut self.renderer {
             let g = self.graphics.read_state();
             let ctx = g.ctx.as_ref().unwrap();
             let frame = g.current_frame.as_ref().unwrap();
-            renderer.submit(ctx, frame);
+            renderer.submit(ctx, frame)?;
         }
+        Ok(())
     }
 }
 
 impl Listener<SurfaceResizeEvent> for Renderer2DModule {
-    fn on_event(&mut self, _: &mut SurfaceResizeEvent, _: &mut EventQueue) {
+    fn on_event(&mut self, event: &mut SurfaceResizeEvent, _: &mut EventQueue) {
         if let Some(renderer) = &mut self.renderer {
             let g = self.graphics.read_state();
+            // Secondary windows have their own renderers
+            if !g.is_main_window(event.window) {
+                return;
+            }
             let ctx = g.ctx.as_ref().unwrap();
-            renderer.resize(ctx, g.window_size().unwrap())
+            renderer.resize(ctx, (event.width, event.height))
         }
     }
 }
@@ -111,13 +400,691 @@ impl Listener<DrawSpriteEvent> for Renderer2DModule {
     }
 }
 
+impl Listener<DrawSpriteBatchEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut DrawSpriteBatchEvent, _: &mut EventQueue) {
+        self.renderer.as_mut().unwrap().draw_batch(&event.sprites);
+    }
+}
+
+impl Listener<DrawNineSliceEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut DrawNineSliceEvent, _: &mut EventQueue) {
+        self.renderer.as_mut().unwrap().draw_nine_slice(
+            event.sprite.clone(),
+            event.rect,
+            event.border,
+            event.params.clone(),
+        );
+    }
+}
+
+impl Listener<DrawTextEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut DrawTextEvent, _: &mut EventQueue) {
+        self.renderer
+            .as_mut()
+            .unwrap()
+            .draw_text(event.font, &event.text, event.params.clone());
+    }
+}
+
+impl Listener<DrawWrappedTextEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut DrawWrappedTextEvent, _: &mut EventQueue) {
+        self.renderer.as_mut().unwrap().draw_text_wrapped(
+            event.font,
+            &event.text,
+            event.rect,
+            event.align,
+            event.line_height,
+            event.params.clone(),
+        );
+    }
+}
+
+impl Listener<DrawShape2DEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut DrawShape2DEvent, _: &mut EventQueue) {
+        let renderer = self.renderer.as_mut().unwrap();
+        match *event {
+            DrawShape2DEvent::Rect { rect, color, depth } => renderer.draw_rect(rect, color, depth),
+            DrawShape2DEvent::RoundedRect {
+                rect,
+                radius,
+                color,
+                depth,
+            } => renderer.draw_rounded_rect(rect, radius, color, depth),
+            DrawShape2DEvent::Circle {
+                center,
+                radius,
+                color,
+                depth,
+            } => renderer.draw_circle(center, radius, color, depth),
+            DrawShape2DEvent::GradientRect {
+                rect,
+                color_a,
+                color_b,
+                direction,
+                depth,
+            } => renderer.draw_rect_gradient(rect, color_a, color_b, direction, depth),
+            DrawShape2DEvent::Line {
+                a,
+                b,
+                thickness,
+                color,
+                depth,
+            } => renderer.draw_line(a, b, thickness, color, depth),
+        }
+    }
+}
+
+impl Listener<SetCamera2DEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut SetCamera2DEvent, _: &mut EventQueue) {
+        if let Some(renderer) = &mut self.renderer {
+            renderer.set_camera(event.view);
+        }
+    }
+}
+
+impl Listener<SetClearColor2DEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut SetClearColor2DEvent, _: &mut EventQueue) {
+        if let Some(renderer) = &mut self.renderer {
+            renderer.set_clear_color(event.color);
+        }
+    }
+}
+
+impl Listener<RegisterSpriteSheetEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut RegisterSpriteSheetEvent, _: &mut EventQueue) {
+        let decoding = decode_image(event.data.path.clone());
+        self.decoding_sheets.push((event.data.clone(), decoding));
+    }
+}
+
+impl Listener<TextureDecodedEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut TextureDecodedEvent, _: &mut EventQueue) {
+        let image = event.image.take();
+        self.decoded_textures
+            .push((event.handle, event.data.clone(), image));
+    }
+}
+
+impl Listener<SetTextureSorting2DEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut SetTextureSorting2DEvent, _: &mut EventQueue) {
+        if let Some(renderer) = &mut self.renderer {
+            renderer.set_texture_sorting(event.enabled);
+        }
+    }
+}
+
+impl Listener<SetDepthSorting2DEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut SetDepthSorting2DEvent, _: &mut EventQueue) {
+        if let Some(renderer) = &mut self.renderer {
+            renderer.set_depth_sorting(event.enabled);
+        }
+    }
+}
+
+impl Listener<SetWireframe2DEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut SetWireframe2DEvent, _: &mut EventQueue) {
+        if let Some(renderer) = &mut self.renderer {
+            renderer.set_wireframe(event.enabled);
+        }
+    }
+}
+
+impl Listener<SetMsaa2DEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut SetMsaa2DEvent, queue: &mut EventQueue) {
+        if self.config.sample_count != event.sample_count {
+            self.config.sample_count = event.sample_count;
+            if self.renderer.is_some() {
+                queue.push(RefreshRenderer2DEvent);
+            }
+        }
+    }
+}
+
+impl Listener<SetRenderer2DConfigEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut SetRenderer2DConfigEvent, queue: &mut EventQueue) {
+        self.config = event.config;
+        if self.renderer.is_some() {
+            queue.push(RefreshRenderer2DEvent);
+        }
+    }
+}
+
+impl TryListener<SubmitToRenderTarget2DEvent> for Renderer2DModule {
+    fn try_on_event(
+        &mut self,
+        event: &mut SubmitToRenderTarget2DEvent,
+        _: &mut EventQueue,
+    ) -> AnyResult<()> {
+        if let Some(renderer) = &mut self.renderer {
+            let g = self.graphics.read_state();
+            renderer.submit_to_render_target(g.ctx.as_ref().unwrap(), event.target)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryListener<Flush2DEvent> for Renderer2DModule {
+    fn try_on_event(&mut self, _: &mut Flush2DEvent, _: &mut EventQueue) -> AnyResult<()> {
+        if let Some(renderer) = &mut self.renderer {
+            let g = self.graphics.read_state();
+            if let Some(frame) = &g.current_frame {
+                renderer.flush(g.ctx.as_ref().unwrap(), frame)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Listener<AddPostEffect2DEvent> for Renderer2DModule {
+    fn on_event(&mut self, event: &mut AddPostEffect2DEvent, _: &mut EventQueue) {
+        self.post_effects.push(event.effect.clone());
+        if let Some(renderer) = &mut self.renderer {
+            let g = self.graphics.read_state();
+            renderer.add_post_effect(g.ctx.as_ref().unwrap(), event.effect.clone());
+        }
+    }
+}
+
+impl Listener<ClearPostEffects2DEvent> for Renderer2DModule {
+    fn on_event(&mut self, _: &mut ClearPostEffects2DEvent, _: &mut EventQueue) {
+        self.post_effects.clear();
+        if let Some(renderer) = &mut self.renderer {
+            renderer.clear_post_effects();
+        }
+    }
+}
+
 pub struct Draw2d<'a>(pub &'a mut EventQueue);
 impl<'a> Draw2d<'a> {
     pub fn sprite(&mut self, sprite: Sprite, params: DrawParams) {
         self.0.push(DrawSpriteEvent { sprite, params })
     }
-    pub fn sprite_centered(&mut self, sprite: Sprite, mut params: DrawParams) {
-        params.transform = params.transform * Matrix3::from_translation(Vector2::from_value(-0.5));
+    /// Draws many sprites at once, cheaper than calling [`Draw2d::sprite`] for each one
+    pub fn sprite_batch(&mut self, sprites: Vec<(Sprite, DrawParams)>) {
+        self.0.push(DrawSpriteBatchEvent { sprites })
+    }
+    /// Draws `sprite` centered on the origin of `params.transform`, ignoring `params.origin`
+    pub fn sprite_centered(&mut self, sprite: Sprite, params: DrawParams) {
+        let params = DrawParams {
+            origin: (0.5, 0.5),
+            ..params
+        };
         self.0.push(DrawSpriteEvent { sprite, params })
     }
+    /// Draws `sprite` over `rect` without stretching its borders, see
+    /// [`SpriteRenderer::draw_nine_slice`](renderer::SpriteRenderer::draw_nine_slice).
+    pub fn nine_slice(
+        &mut self,
+        sprite: Sprite,
+        rect: Rect,
+        border: (f32, f32, f32, f32),
+        params: DrawParams,
+    ) {
+        self.0.push(DrawNineSliceEvent {
+            sprite,
+            rect,
+            border,
+            params,
+        })
+    }
+    /// Draws `text` with its baseline starting at the origin of `params.transform`,
+    /// one unit being the font size.
+    pub fn text(&mut self, font: FontHandle, text: impl Into<String>, params: DrawParams) {
+        self.0.push(DrawTextEvent {
+            font,
+            text: text.into(),
+            params,
+        })
+    }
+    /// Draws `text` wrapped and aligned within `rect`, see
+    /// [`SpriteRenderer::draw_text_wrapped`](renderer::SpriteRenderer::draw_text_wrapped)
+    /// which also returns the box of the drawn lines.
+    pub fn text_wrapped(
+        &mut self,
+        font: FontHandle,
+        text: impl Into<String>,
+        rect: Rect,
+        align: TextAlign,
+        line_height: f32,
+        params: DrawParams,
+    ) {
+        self.0.push(DrawWrappedTextEvent {
+            font,
+            text: text.into(),
+            rect,
+            align,
+            line_height,
+            params,
+        })
+    }
+    /// Draws the tiles of `tilemap` overlapping `camera_bounds`, see
+    /// [`Tilemap::draw_visible`](tilemap::Tilemap::draw_visible).
+    pub fn tilemap(&mut self, tilemap: &Tilemap, camera_bounds: Rect, params: &DrawParams) {
+        for (tile, transform) in tilemap.visible_tiles(camera_bounds) {
+            self.sprite(tile.clone(), tilemap::with_transform(params, transform));
+        }
+    }
+    /// Draws the live particles of `emitter`, see [`ParticleEmitter::draw`].
+    pub fn particles(&mut self, emitter: &ParticleEmitter) {
+        self.sprite_batch(emitter.sprites().collect());
+    }
+    /// Draws a rectangle filled with `color`.
+    pub fn rect(&mut self, rect: Rect, color: Color3, depth: f32) {
+        self.0.push(DrawShape2DEvent::Rect { rect, color, depth })
+    }
+    /// Draws a rectangle whose corners are rounded by `radius`, crisp at any scale.
+    pub fn rounded_rect(&mut self, rect: Rect, radius: f32, color: Color3, depth: f32) {
+        self.0.push(DrawShape2DEvent::RoundedRect {
+            rect,
+            radius,
+            color,
+            depth,
+        })
+    }
+    /// Draws a disk of `radius` around `center`, crisp at any scale.
+    pub fn circle(&mut self, center: Vector2<f32>, radius: f32, color: Color3, depth: f32) {
+        self.0.push(DrawShape2DEvent::Circle {
+            center,
+            radius,
+            color,
+            depth,
+        })
+    }
+    /// Draws a rectangle filled with a gradient from `color_a` to `color_b` in `direction`.
+    pub fn rect_gradient(
+        &mut self,
+        rect: Rect,
+        color_a: Color3,
+        color_b: Color3,
+        direction: GradientDirection,
+        depth: f32,
+    ) {
+        self.0.push(DrawShape2DEvent::GradientRect {
+            rect,
+            color_a,
+            color_b,
+            direction,
+            depth,
+        })
+    }
+    /// Draws a line of `thickness` from `a` to `b`, useful for debug visualization.
+    pub fn line(
+        &mut self,
+        a: Vector2<f32>,
+        b: Vector2<f32>,
+        thickness: f32,
+        color: Color3,
+        depth: f32,
+    ) {
+        self.0.push(DrawShape2DEvent::Line {
+            a,
+            b,
+            thickness,
+            color,
+            depth,
+        })
+    }
+    /// Sets the camera (view) matrix, applied to all sprites drawn after it.
+    pub fn camera(&mut self, view: Matrix3<f32>) {
+        self.0.push(SetCamera2DEvent { view })
+    }
+    /// Sets the frame clear color, `None` draws over the previous frame content instead.
+    pub fn clear_color(&mut self, color: Option<Color3>) {
+        self.0.push(SetClearColor2DEvent { color })
+    }
+    /// Enables or disables back to front depth sorting of the sprites before submitting them.
+    pub fn depth_sorting(&mut self, enabled: bool) {
+        self.0.push(SetDepthSorting2DEvent { enabled })
+    }
+    /// Enables or disables grouping sprites of the same depth by texture, see
+    /// [`SpriteRenderer::set_texture_sorting`](renderer::SpriteRenderer::set_texture_sorting).
+    pub fn texture_sorting(&mut self, enabled: bool) {
+        self.0.push(SetTextureSorting2DEvent { enabled })
+    }
+    /// Enables or disables drawing only the edges of the sprites, see
+    /// [`SpriteRenderer::set_wireframe`](renderer::SpriteRenderer::set_wireframe).
+    pub fn wireframe(&mut self, enabled: bool) {
+        self.0.push(SetWireframe2DEvent { enabled })
+    }
+    /// Sets the MSAA sample count (1, 2, 4 or 8), smoothing the edges of rotated sprites and lines.
+    ///
+    /// Changing it recreates the renderer, the sheets and textures loaded at runtime are kept.
+    pub fn msaa(&mut self, sample_count: u32) {
+        self.0.push(SetMsaa2DEvent { sample_count })
+    }
+    /// Sets the renderer sizes and quality settings, see [`SpriteRendererConfig`].
+    ///
+    /// Changing it recreates the renderer, the sheets and textures loaded at runtime are kept.
+    pub fn renderer_config(&mut self, config: SpriteRendererConfig) {
+        self.0.push(SetRenderer2DConfigEvent { config })
+    }
+    /// Renders every sprite drawn so far into the `target` render target instead of the frame.
+    pub fn submit_to_render_target(&mut self, target: SpriteSheetHandle) {
+        self.0.push(SubmitToRenderTarget2DEvent { target })
+    }
+    /// Renders every sprite drawn so far into the frame right away, see
+    /// [`SpriteRenderer::flush`](renderer::SpriteRenderer::flush).
+    pub fn flush(&mut self) {
+        self.0.push(Flush2DEvent)
+    }
+    /// Appends a post effect applied to every frame, see [`PostEffect`].
+    ///
+    /// Keep a clone of `effect` to change its uniforms through interior mutability.
+    pub fn add_post_effect(&mut self, effect: Rc<dyn PostEffect>) {
+        self.0.push(AddPostEffect2DEvent { effect })
+    }
+    pub fn clear_post_effects(&mut self) {
+        self.0.push(ClearPostEffects2DEvent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rgine_assets::AssetsEventQueueExt;
+    use rgine_modules::standards::StartEvent;
+    use rgine_platform::window::{headless::HeadlessEngineExt, WindowPlatformConfig};
+
+    use super::*;
+    use crate::renderer::{CullMode, ViewportMode};
+
+    struct Empty;
+    impl Module for Empty {
+        type ListeningTo = ();
+        fn new(_: &mut Engine) -> AnyResult<Self> {
+            Ok(Self)
+        }
+    }
+
+    /// Renderer config, sheets and sprites of the frames rendered by [`Capture`]
+    struct Scene {
+        config: SpriteRendererConfig,
+        /// Registers the sheets drawn by `draw` on startup, before the registry is loaded
+        sheets: fn(&mut SpriteSheetsRegistry, &mut EventQueue) -> Vec<SpriteSheetHandle>,
+        draw: fn(&mut Draw2d, &[SpriteSheetHandle]),
+    }
+
+    impl Scene {
+        fn new(config: SpriteRendererConfig, draw: fn(&mut Draw2d, &[SpriteSheetHandle])) -> Self {
+            Self {
+                config,
+                sheets: |_, _| Vec::new(),
+                draw,
+            }
+        }
+
+        fn with_sheets(
+            self,
+            sheets: fn(&mut SpriteSheetsRegistry, &mut EventQueue) -> Vec<SpriteSheetHandle>,
+        ) -> Self {
+            Self { sheets, ..self }
+        }
+    }
+
+    /// Reads back every frame once the renderer submitted it
+    struct Capture {
+        graphics: Dependency<GraphicsModule>,
+        scene: Scene,
+        sheets: Vec<SpriteSheetHandle>,
+        frames: Vec<Vec<u8>>,
+    }
+    impl Module for Capture {
+        type ListeningTo = (
+            StartEvent,
+            SpriteSheetRegisteredEvent,
+            Render2DEvent,
+            SubmitRenderEvent,
+        );
+        const PRIORITY: i32 = -1;
+        fn new(ctx: &mut Engine) -> AnyResult<Self> {
+            ctx.dependency::<Renderer2DModule>()?;
+            let resource = ctx.resource::<Scene>().unwrap();
+            let scene = Scene { ..*resource };
+            drop(resource);
+            Ok(Self {
+                graphics: ctx.dependency()?,
+                scene,
+                sheets: Vec::new(),
+                frames: Vec::new(),
+            })
+        }
+    }
+    impl Listener<StartEvent> for Capture {
+        fn on_event(&mut self, _: &mut StartEvent, queue: &mut EventQueue) {
+            let mut registry = SpriteSheetsRegistry::new();
+            self.sheets = (self.scene.sheets)(&mut registry, queue);
+            queue.load_asset(registry);
+            Draw2d(queue).renderer_config(self.scene.config);
+        }
+    }
+    impl Listener<Render2DEvent> for Capture {
+        fn on_event(&mut self, _: &mut Render2DEvent, queue: &mut EventQueue) {
+            (self.scene.draw)(&mut Draw2d(queue), &self.sheets);
+        }
+    }
+    impl Listener<SpriteSheetRegisteredEvent> for Capture {
+        fn on_event(&mut self, event: &mut SpriteSheetRegisteredEvent, _: &mut EventQueue) {
+            self.sheets.push(event.handle);
+        }
+    }
+    impl Listener<SubmitRenderEvent> for Capture {
+        fn on_event(&mut self, _: &mut SubmitRenderEvent, _: &mut EventQueue) {
+            let g = self.graphics.read_state();
+            let frame = g.current_frame.as_ref().unwrap();
+            self.frames
+                .push(g.ctx.as_ref().unwrap().read_frame(frame).unwrap());
+        }
+    }
+
+    /// Starts rendering `scene` in frames of `size` pixels, `None` if no graphics adapter is
+    /// available
+    fn capture(size: (u32, u32), scene: Scene) -> Option<Engine> {
+        let instance = wgpu::Instance::default();
+        pollster::block_on(instance.request_adapter(&Default::default()))?;
+
+        let mut engine = Engine::new_without_logger::<Empty>();
+        engine.insert_resource(scene);
+        engine.dependency::<Capture>().unwrap();
+        engine.start_headless(WindowPlatformConfig {
+            size: Some(size),
+            ..Default::default()
+        });
+        Some(engine)
+    }
+
+    /// Renders `frames` frames of `scene`, see [`capture`]
+    fn render(size: (u32, u32), scene: Scene, frames: u32) -> Option<Vec<Vec<u8>>> {
+        let mut engine = capture(size, scene)?;
+        for _ in 0..frames {
+            engine.step_headless(Duration::ZERO);
+        }
+        let frames = std::mem::take(&mut engine.module_mut::<Capture>().unwrap().frames);
+        Some(frames)
+    }
+
+    fn pixel(frame: &[u8], width: u32, (x, y): (u32, u32)) -> [u8; 4] {
+        let i = ((y * width + x) * 4) as usize;
+        frame[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn letterbox_bars_are_never_culled() {
+        for (y_down, cull_mode) in [
+            (false, CullMode::Back),
+            (true, CullMode::Back),
+            (false, CullMode::Front),
+        ] {
+            let config = SpriteRendererConfig {
+                viewport: ViewportMode::Letterbox {
+                    aspect_ratio: 1.,
+                    color: Color3::rgb(0., 1., 0.),
+                },
+                y_down,
+                cull_mode,
+                ..Default::default()
+            };
+            let draw = |draw: &mut Draw2d, _: &[SpriteSheetHandle]| {
+                let rect = Rect::new(Vector2::new(-1., -1.), Vector2::new(2., 2.));
+                draw.rect(rect, Color3::rgb(1., 0., 0.), 0.5);
+            };
+            let Some(frames) = render((4, 2), Scene::new(config, draw), 1) else {
+                return;
+            };
+
+            let frame = &frames[0];
+            for bar in [(0, 0), (3, 1)] {
+                assert_eq!(pixel(frame, 4, bar), [0, 255, 0, 255], "y_down: {y_down}");
+            }
+            // Sprites are culled with the front faces
+            if cull_mode == CullMode::Back {
+                assert_eq!(
+                    pixel(frame, 4, (1, 0)),
+                    [255, 0, 0, 255],
+                    "y_down: {y_down}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn empty_frames_are_cleared_before_the_post_effects() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        use crate::post_process::BlurEffect;
+
+        static DRAWN: AtomicBool = AtomicBool::new(false);
+        // Only the first frame has sprites, the following ones are empty
+        let draw = |draw: &mut Draw2d, _: &[SpriteSheetHandle]| {
+            draw.clear_color(Some(Color3::rgb(0., 0., 1.)));
+            if !DRAWN.swap(true, Ordering::Relaxed) {
+                draw.add_post_effect(BlurEffect::new(0.));
+                let rect = Rect::new(Vector2::new(-1., -1.), Vector2::new(2., 2.));
+                draw.rect(rect, Color3::rgb(1., 0., 0.), 0.5);
+            }
+        };
+        let Some(frames) = render((2, 2), Scene::new(SpriteRendererConfig::default(), draw), 2)
+        else {
+            return;
+        };
+
+        assert_eq!(pixel(&frames[0], 2, (0, 0)), [255, 0, 0, 255]);
+        assert_eq!(pixel(&frames[1], 2, (0, 0)), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn bloom_glows_past_the_bright_pixels() {
+        use crate::post_process::BloomEffect;
+
+        // The left half is white, the right half black
+        let draw = |draw: &mut Draw2d, _: &[SpriteSheetHandle]| {
+            draw.clear_color(Some(Color3::rgb(0., 0., 0.)));
+            draw.add_post_effect(BloomEffect::new(0.5, 1.));
+            let rect = Rect::new(Vector2::new(-1., -1.), Vector2::new(1., 2.));
+            draw.rect(rect, Color3::rgb(1., 1., 1.), 0.5);
+        };
+        let Some(frames) = render(
+            (16, 16),
+            Scene::new(SpriteRendererConfig::default(), draw),
+            1,
+        ) else {
+            return;
+        };
+
+        let frame = &frames[0];
+        assert_eq!(pixel(frame, 16, (0, 8)), [255, 255, 255, 255]);
+        let glow = pixel(frame, 16, (9, 8));
+        assert!(glow[0] > 0 && glow[0] < 255, "{glow:?}");
+    }
+
+    /// Draws the first sheet of the scene over the whole frame
+    fn draw_first_sheet(draw: &mut Draw2d, sheets: &[SpriteSheetHandle]) {
+        let Some(&sheet) = sheets.first() else {
+            return;
+        };
+        let params = DrawParams {
+            transform: Matrix3::from_translation(Vector2::new(-1., -1.)) * Matrix3::from_scale(2.),
+            ..Default::default()
+        };
+        draw.sprite(
+            Sprite::new(sheet, Vector2::new(0, 0), Vector2::new(1, 1)),
+            params,
+        );
+    }
+
+    /// Path in the temp dir unique to this test process and `test`
+    fn temp_path(test: &str) -> String {
+        let name = format!("rgine_{}_{test}.png", std::process::id());
+        std::env::temp_dir()
+            .join(name)
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn runtime_sheets_survive_renderer_refresh() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        fn path() -> String {
+            temp_path("runtime_sheets_survive_renderer_refresh")
+        }
+        static REFRESHED: AtomicBool = AtomicBool::new(false);
+
+        RgbaImage::from_pixel(1, 1, image::Rgba([0, 0, 255, 255]))
+            .save(path())
+            .unwrap();
+        let draw = |draw: &mut Draw2d, sheets: &[SpriteSheetHandle]| {
+            if !sheets.is_empty() && !REFRESHED.swap(true, Ordering::Relaxed) {
+                draw.renderer_config(SpriteRendererConfig::default());
+            }
+            draw_first_sheet(draw, sheets);
+        };
+        let scene = Scene::new(SpriteRendererConfig::default(), draw).with_sheets(|_, queue| {
+            queue.push(RegisterSpriteSheetEvent {
+                data: SpriteSheetData {
+                    path: path(),
+                    sprite_px_size: Vector2::new(1, 1),
+                },
+            });
+            Vec::new()
+        });
+        let Some(mut engine) = capture((2, 2), scene) else {
+            return;
+        };
+        let start = std::time::Instant::now();
+        while engine.module::<Capture>().unwrap().sheets.is_empty() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "sheet never registered"
+            );
+            engine.step_headless(Duration::ZERO);
+        }
+        // The frame of the refresh may be drawn before the renderer is recreated
+        for _ in 0..3 {
+            engine.step_headless(Duration::ZERO);
+        }
+
+        let frames = &engine.module::<Capture>().unwrap().frames;
+        for texel in [(0, 0), (1, 1)] {
+            assert_eq!(pixel(frames.last().unwrap(), 2, texel), [0, 0, 255, 255]);
+        }
+    }
+
+    #[test]
+    fn oversized_sheets_are_drawn_with_the_placeholder() {
+        // Wider than any device supports
+        let scene = Scene::new(SpriteRendererConfig::default(), draw_first_sheet).with_sheets(
+            |registry, _| {
+                let image = RgbaImage::from_pixel(1 << 16, 1, image::Rgba([0, 0, 255, 255]));
+                vec![registry.register_image(image, Vector2::new(1, 1))]
+            },
+        );
+        let Some(frames) = render((2, 2), scene, 1) else {
+            return;
+        };
+
+        // Top left cell of the magenta and black checkerboard
+        assert_eq!(pixel(&frames[0], 2, (0, 0)), [255, 0, 255, 255]);
+    }
 }
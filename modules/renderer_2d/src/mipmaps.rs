@@ -0,0 +1,111 @@
+use cgmath::Vector2;
+use rgine_graphics::ctx::GraphicsCtx;
+use wgpu::{Texture, TextureFormat};
+
+/// Number of mip levels down to a single pixel
+pub(crate) fn mip_level_count(size: Vector2<u32>) -> u32 {
+    32 - size.x.max(size.y).max(1).leading_zeros()
+}
+
+/// Fills every mip level of `texture` past the first one by successively downsampling
+/// the previous level with a linear filter.
+///
+/// The texture must allow `RENDER_ATTACHMENT` and `TEXTURE_BINDING` usages.
+pub(crate) fn generate_mipmaps(ctx: &GraphicsCtx, texture: &Texture, format: TextureFormat) {
+    let mip_level_count = texture.mip_level_count();
+    if mip_level_count <= 1 {
+        return;
+    }
+
+    let shader = ctx
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("2d_blit_shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("blit.wgsl").into()),
+        });
+
+    let pipeline = ctx
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("2d_blit_pipeline"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+    let bind_group_layout = pipeline.get_bind_group_layout(0);
+
+    let sampler = ctx.device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("2d_blit_sampler"),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+
+    let views = (0..mip_level_count)
+        .map(|mip| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("2d_mip_view"),
+                base_mip_level: mip,
+                mip_level_count: Some(1),
+                ..Default::default()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("2d_mipmaps_encoder"),
+        });
+
+    for mip in 1..mip_level_count as usize {
+        let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&views[mip - 1]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: None,
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("2d_mipmaps_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &views[mip],
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    ctx.queue.submit(std::iter::once(encoder.finish()));
+}
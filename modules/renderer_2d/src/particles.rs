@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use cgmath::{Matrix3, Vector2};
+
+use crate::{
+    renderer::SpriteRenderer,
+    texture::{BlendMode, Color3, DrawParams, Sprite},
+};
+
+/// Appearance and motion of the particles of a [`ParticleEmitter`]
+#[derive(Clone)]
+pub struct ParticleEmitterConfig {
+    pub sprite: Sprite,
+    /// Particles spawned per second while emitting
+    pub spawn_rate: f32,
+    /// Particles alive at once, no particle is spawned past it
+    pub max_particles: usize,
+    /// Particles of a zero lifetime are drawn once with their end appearance
+    pub lifetime: Duration,
+    /// Initial velocity in units per second
+    pub velocity: Vector2<f32>,
+    /// Maximum random deviation added to `velocity` on each axis
+    pub velocity_spread: Vector2<f32>,
+    /// Constant acceleration, such as gravity, in units per second squared
+    pub acceleration: Vector2<f32>,
+    /// Color and opacity at spawn, interpolated to `end_color` and `end_alpha` over the lifetime
+    pub start_color: Color3,
+    pub end_color: Color3,
+    pub start_alpha: f32,
+    pub end_alpha: f32,
+    /// Side length at spawn, interpolated to `end_size` over the lifetime
+    pub start_size: f32,
+    pub end_size: f32,
+    pub blend_mode: BlendMode,
+    pub depth: f32,
+}
+
+impl ParticleEmitterConfig {
+    /// Particles of `sprite` with white opaque colors and no motion
+    pub fn new(sprite: Sprite) -> Self {
+        Self {
+            sprite,
+            spawn_rate: 10.,
+            max_particles: 1000,
+            lifetime: Duration::from_secs(1),
+            velocity: Vector2::new(0., 0.),
+            velocity_spread: Vector2::new(0., 0.),
+            acceleration: Vector2::new(0., 0.),
+            start_color: Color3::WHITE,
+            end_color: Color3::WHITE,
+            start_alpha: 1.,
+            end_alpha: 1.,
+            start_size: 1.,
+            end_size: 1.,
+            blend_mode: BlendMode::Alpha,
+            depth: 0.,
+        }
+    }
+}
+
+struct Particle {
+    position: Vector2<f32>,
+    velocity: Vector2<f32>,
+    age: Duration,
+}
+
+/// Particles simulated on the CPU and drawn as sprites.
+///
+/// [`ParticleEmitter::update`] is meant to be called on every `OnWindowPlatformUpdate`
+/// with its `dt`, then the particles are drawn with [`ParticleEmitter::draw`] or
+/// [`Draw2d::particles`](crate::Draw2d::particles).
+pub struct ParticleEmitter {
+    pub config: ParticleEmitterConfig,
+    /// Where particles are spawned
+    pub position: Vector2<f32>,
+    /// Whether new particles are spawned, live particles keep being updated either way
+    pub emitting: bool,
+    particles: Vec<Particle>,
+    /// Fraction of particle not spawned yet
+    spawn_lag: f32,
+    rng_state: u32,
+}
+
+impl ParticleEmitter {
+    pub fn new(config: ParticleEmitterConfig, position: Vector2<f32>) -> Self {
+        Self {
+            config,
+            position,
+            emitting: true,
+            particles: Vec::new(),
+            spawn_lag: 0.,
+            rng_state: 0x9e37_79b9,
+        }
+    }
+
+    /// Number of live particles
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    /// Ages, moves and spawns particles, removing the ones older than their lifetime
+    pub fn update(&mut self, dt: Duration) {
+        let lifetime = self.config.lifetime;
+        self.particles.retain_mut(|particle| {
+            particle.age += dt;
+            particle.velocity += self.config.acceleration * dt.as_secs_f32();
+            particle.position += particle.velocity * dt.as_secs_f32();
+            particle.age < lifetime
+        });
+
+        if !self.emitting {
+            self.spawn_lag = 0.;
+            return;
+        }
+        self.spawn_lag += self.config.spawn_rate * dt.as_secs_f32();
+        while self.spawn_lag >= 1. {
+            self.spawn_lag -= 1.;
+            if self.particles.len() >= self.config.max_particles {
+                continue;
+            }
+            let spread = self.config.velocity_spread;
+            let velocity = self.config.velocity
+                + Vector2::new(self.random() * spread.x, self.random() * spread.y);
+            self.particles.push(Particle {
+                position: self.position,
+                velocity,
+                age: Duration::ZERO,
+            });
+        }
+    }
+
+    /// Queues the live particles into `renderer`
+    pub fn draw(&self, renderer: &mut SpriteRenderer) {
+        renderer.draw_batch(&self.sprites().collect::<Vec<_>>());
+    }
+
+    /// Sprite and draw parameters of each live particle, centered on its position
+    pub(crate) fn sprites(&self) -> impl Iterator<Item = (Sprite, DrawParams)> + '_ {
+        let config = &self.config;
+        self.particles.iter().map(move |particle| {
+            let t = lifetime_progress(particle.age, config.lifetime);
+            let size = config.start_size + (config.end_size - config.start_size) * t;
+            let params = DrawParams {
+                transform: Matrix3::from_translation(particle.position) * Matrix3::from_scale(size),
+                origin: (0.5, 0.5),
+                tint: config.start_color.lerp(config.end_color, t),
+                alpha: config.start_alpha + (config.end_alpha - config.start_alpha) * t,
+                depth: config.depth,
+                blend_mode: config.blend_mode,
+                ..Default::default()
+            };
+            (config.sprite.clone(), params)
+        })
+    }
+
+    /// Pseudo random number between -1 and 1 (xorshift)
+    fn random(&mut self) -> f32 {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 17;
+        self.rng_state ^= self.rng_state << 5;
+        self.rng_state as f32 / u32::MAX as f32 * 2. - 1.
+    }
+}
+
+/// Fraction of `lifetime` elapsed at `age`, from 0 to 1
+fn lifetime_progress(age: Duration, lifetime: Duration) -> f32 {
+    if lifetime.is_zero() {
+        return 1.;
+    }
+    (age.as_secs_f32() / lifetime.as_secs_f32()).min(1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::texture::{SheetId, SpriteSheetHandle};
+
+    use super::*;
+
+    fn emitter(lifetime: Duration) -> ParticleEmitter {
+        let sprite = Sprite::new(
+            SpriteSheetHandle(SheetId::Index(0)),
+            Vector2::new(0, 0),
+            Vector2::new(1, 1),
+        );
+        let config = ParticleEmitterConfig {
+            spawn_rate: 1.,
+            lifetime,
+            start_size: 1.,
+            end_size: 3.,
+            start_alpha: 1.,
+            end_alpha: 0.,
+            ..ParticleEmitterConfig::new(sprite)
+        };
+        ParticleEmitter::new(config, Vector2::new(0., 0.))
+    }
+
+    #[test]
+    fn appearance_is_interpolated_over_the_lifetime() {
+        let mut emitter = emitter(Duration::from_secs(2));
+        emitter.update(Duration::from_secs(1));
+        emitter.emitting = false;
+        emitter.update(Duration::from_secs(1));
+
+        let (_, params) = emitter.sprites().next().unwrap();
+        assert_eq!(params.alpha, 0.5);
+        assert_eq!(params.transform, Matrix3::from_scale(2.));
+    }
+
+    #[test]
+    fn zero_lifetime_particles_have_their_end_appearance() {
+        let mut emitter = emitter(Duration::ZERO);
+        emitter.update(Duration::from_secs(1));
+
+        let (_, params) = emitter.sprites().next().unwrap();
+        assert_eq!(params.alpha, 0.);
+        assert_eq!(params.transform, Matrix3::from_scale(3.));
+    }
+
+    #[test]
+    fn progress_is_clamped() {
+        let lifetime = Duration::from_secs(1);
+        assert_eq!(lifetime_progress(Duration::ZERO, lifetime), 0.);
+        assert_eq!(lifetime_progress(Duration::from_secs(2), lifetime), 1.);
+        assert_eq!(
+            lifetime_progress(Duration::from_secs(1), Duration::ZERO),
+            1.
+        );
+    }
+}
@@ -0,0 +1,635 @@
+use std::{cell::Cell, rc::Rc};
+
+use bytemuck::{bytes_of, Pod, Zeroable};
+use image::{
+    error::{ParameterError, ParameterErrorKind},
+    ImageError, ImageResult, RgbaImage,
+};
+use rgine_graphics::ctx::GraphicsCtx;
+use wgpu::*;
+
+use crate::texture::try_load_sheet_image;
+
+/// Fullscreen effect applied to the frame after the sprites are drawn, such as a CRT filter.
+///
+/// The fragment shader gets the following declarations prepended:
+/// - `PostVertexOutput`, with `tex_coords` going from `(0, 0)` at the top left corner to `(1, 1)`
+/// - `input_texture: texture_2d<f32>` and `input_sampler: sampler`, the output of the previous
+///   pass, effect or the sprites
+/// - `effect_input_texture: texture_2d<f32>`, the input of the first pass of the effect
+///
+/// It must define `fn fs_main(in: PostVertexOutput) -> @location(0) vec4<f32>`. Effects with
+/// uniforms must declare them as `@group(0) @binding(2) var<uniform>`, and effects with a
+/// texture as `@group(0) @binding(4) var name: texture_2d<f32>`.
+pub trait PostEffect: 'static {
+    /// WGSL source of the fragment shader
+    fn shader(&self) -> String;
+
+    /// Content of the uniform buffer, written before every frame. Its size must not change,
+    /// an empty buffer (default) binds no uniform.
+    fn uniforms(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Number of times the shader runs, each pass taking the output of the previous one as input
+    fn passes(&self) -> usize {
+        1
+    }
+
+    /// Content of the uniform buffer of the pass `pass`, [`PostEffect::uniforms`] by default.
+    /// Every pass must use the same size.
+    fn pass_uniforms(&self, _pass: usize) -> Vec<u8> {
+        self.uniforms()
+    }
+
+    /// Resolution the pass `pass` renders at, read once when the effect is added. The last
+    /// pass always renders at full resolution
+    fn pass_resolution(&self, _pass: usize) -> PassResolution {
+        PassResolution::Full
+    }
+
+    /// Image bound as an sRGB texture, such as a lookup table. It is read once when the
+    /// effect is added
+    fn texture(&self) -> Option<RgbaImage> {
+        None
+    }
+}
+
+/// Resolution of the output of a [`PostEffect`] pass, relative to the frame.
+///
+/// Lower resolutions are cheaper for passes whose output is blurred anyway, the textures
+/// given to the next pass are sampled with linear filtering.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PassResolution {
+    #[default]
+    Full,
+    Half,
+    Quarter,
+}
+
+impl PassResolution {
+    const ALL: [Self; 3] = [Self::Full, Self::Half, Self::Quarter];
+
+    fn size(self, (width, height): (u32, u32)) -> (u32, u32) {
+        let divisor = match self {
+            Self::Full => 1,
+            Self::Half => 2,
+            Self::Quarter => 4,
+        };
+        ((width / divisor).max(1), (height / divisor).max(1))
+    }
+}
+
+/// Separable gaussian blur, a horizontal pass followed by a vertical one.
+///
+/// The radius can be changed while the effect is in use, for example to animate a focus pull.
+pub struct BlurEffect {
+    radius: Cell<f32>,
+}
+
+impl BlurEffect {
+    /// Blur spreading every pixel over `radius` pixels
+    pub fn new(radius: f32) -> Rc<Self> {
+        Rc::new(Self {
+            radius: Cell::new(radius),
+        })
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius.get()
+    }
+
+    pub fn set_radius(&self, radius: f32) {
+        self.radius.set(radius.max(0.));
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct BlurUniforms {
+    direction: [f32; 2],
+    radius: f32,
+    _padding: f32,
+}
+
+impl PostEffect for BlurEffect {
+    fn shader(&self) -> String {
+        format!(
+            "{}\n{}",
+            include_str!("blur.wgsl"),
+            include_str!("blur_effect.wgsl")
+        )
+    }
+
+    fn passes(&self) -> usize {
+        2
+    }
+
+    fn pass_uniforms(&self, pass: usize) -> Vec<u8> {
+        let direction = match pass {
+            0 => [1., 0.],
+            _ => [0., 1.],
+        };
+        bytes_of(&BlurUniforms {
+            direction,
+            radius: self.radius.get(),
+            _padding: 0.,
+        })
+        .to_vec()
+    }
+}
+
+/// Makes the pixels brighter than a threshold glow, best used with [`BlendMode::Additive`].
+///
+/// Bright pixels are extracted at half resolution, blurred like with [`BlurEffect`] at
+/// quarter resolution and added back to the frame. The settings can be changed while the
+/// effect is in use.
+///
+/// [`BlendMode::Additive`]: crate::texture::BlendMode::Additive
+pub struct BloomEffect {
+    threshold: Cell<f32>,
+    intensity: Cell<f32>,
+    radius: Cell<f32>,
+}
+
+impl BloomEffect {
+    /// Bloom of the pixels whose brightest channel is above `threshold`, between 0 and 1,
+    /// added back `intensity` times
+    pub fn new(threshold: f32, intensity: f32) -> Rc<Self> {
+        Rc::new(Self {
+            threshold: Cell::new(threshold),
+            intensity: Cell::new(intensity),
+            radius: Cell::new(16.),
+        })
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold.get()
+    }
+
+    pub fn set_threshold(&self, threshold: f32) {
+        self.threshold.set(threshold);
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity.get()
+    }
+
+    pub fn set_intensity(&self, intensity: f32) {
+        self.intensity.set(intensity.max(0.));
+    }
+
+    /// Distance in pixels the glow spreads over, 16 by default
+    pub fn radius(&self) -> f32 {
+        self.radius.get()
+    }
+
+    pub fn set_radius(&self, radius: f32) {
+        self.radius.set(radius.max(0.));
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct BloomUniforms {
+    direction: [f32; 2],
+    radius: f32,
+    threshold: f32,
+    intensity: f32,
+    pass_index: u32,
+    _padding: [f32; 2],
+}
+
+impl PostEffect for BloomEffect {
+    fn shader(&self) -> String {
+        format!(
+            "{}\n{}",
+            include_str!("blur.wgsl"),
+            include_str!("bloom.wgsl")
+        )
+    }
+
+    /// Extraction, horizontal blur, vertical blur then composition
+    fn passes(&self) -> usize {
+        4
+    }
+
+    fn pass_uniforms(&self, pass: usize) -> Vec<u8> {
+        // The radius is in texels of the input of the blur pass
+        let (direction, radius) = match pass {
+            1 => ([1., 0.], self.radius.get() / 2.),
+            _ => ([0., 1.], self.radius.get() / 4.),
+        };
+        bytes_of(&BloomUniforms {
+            direction,
+            radius,
+            threshold: self.threshold.get(),
+            intensity: self.intensity.get(),
+            pass_index: pass as u32,
+            _padding: [0.; 2],
+        })
+        .to_vec()
+    }
+
+    fn pass_resolution(&self, pass: usize) -> PassResolution {
+        match pass {
+            0 => PassResolution::Half,
+            _ => PassResolution::Quarter,
+        }
+    }
+}
+
+/// Color grading with a lookup table, to tweak the mood of the frame without writing shaders.
+///
+/// The table is a strip of N tiles of N x N pixels side by side (such as 256 x 16 or
+/// 1024 x 32), blue growing from tile to tile, red along x and green along y within a tile.
+/// Grading the neutral table in an image editor gives the table of the grade. The frame is
+/// expected to be sRGB, which is the default.
+pub struct ColorGradeEffect {
+    lut: RgbaImage,
+    amount: Cell<f32>,
+}
+
+impl ColorGradeEffect {
+    /// Fails if `lut` is not a strip of square tiles, see [`ColorGradeEffect`]
+    pub fn new(lut: RgbaImage) -> ImageResult<Rc<Self>> {
+        let (width, height) = lut.dimensions();
+        if height < 2 || width != height * height {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+        Ok(Rc::new(Self {
+            lut,
+            amount: Cell::new(1.),
+        }))
+    }
+
+    /// Loads the lookup table image at `path` like a sprite sheet
+    pub fn from_file(path: &str) -> ImageResult<Rc<Self>> {
+        Self::new(try_load_sheet_image(path)?)
+    }
+
+    pub fn amount(&self) -> f32 {
+        self.amount.get()
+    }
+
+    /// Blends between the original colors (0) and the graded ones (1, default)
+    pub fn set_amount(&self, amount: f32) {
+        self.amount.set(amount.clamp(0., 1.));
+    }
+}
+
+impl PostEffect for ColorGradeEffect {
+    fn shader(&self) -> String {
+        include_str!("color_grade.wgsl").to_string()
+    }
+
+    fn uniforms(&self) -> Vec<u8> {
+        bytes_of(&[self.amount.get(), 0., 0., 0.]).to_vec()
+    }
+
+    fn texture(&self) -> Option<RgbaImage> {
+        Some(self.lut.clone())
+    }
+}
+
+struct PostEffectPass {
+    effect: Rc<dyn PostEffect>,
+    /// Index of the pass within the passes of the effect
+    pass: usize,
+    resolution: PassResolution,
+    /// Shared by the passes of the same effect
+    pipeline: Rc<RenderPipeline>,
+    uniform_buffer: Option<Buffer>,
+    /// Shared by the passes of the same effect
+    texture: Option<Rc<TextureView>>,
+}
+
+/// Chain of post effects, the sprites are drawn to an offscreen texture used as the first input
+pub(crate) struct PostProcessor {
+    passes: Vec<PostEffectPass>,
+    /// Texture the sprites are drawn to
+    pub(crate) scene: (Texture, TextureView),
+    /// Outputs of the passes, only used if there are several passes: two at every resolution.
+    /// The input of the first pass of an effect is kept until its last pass
+    swap: Vec<(PassResolution, Texture, TextureView)>,
+    sampler: Sampler,
+}
+
+impl PostProcessor {
+    pub(crate) fn new(ctx: &GraphicsCtx, size: (u32, u32)) -> Self {
+        Self {
+            passes: Vec::new(),
+            scene: create_target(ctx, size),
+            swap: create_swap(ctx, size),
+            sampler: ctx.device.create_sampler(&SamplerDescriptor {
+                label: Some("2d_post_sampler"),
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.passes.clear();
+    }
+
+    pub(crate) fn resize(&mut self, ctx: &GraphicsCtx, size: (u32, u32)) {
+        self.scene = create_target(ctx, size);
+        self.swap = create_swap(ctx, size);
+    }
+
+    pub(crate) fn push(&mut self, ctx: &GraphicsCtx, effect: Rc<dyn PostEffect>) {
+        let uniforms = effect.pass_uniforms(0);
+        let shader = ctx.device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("2d_post_effect_shader"),
+            source: ShaderSource::Wgsl(
+                format!("{}\n{}", include_str!("post_process.wgsl"), effect.shader()).into(),
+            ),
+        });
+
+        let mut entries = vec![
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: TextureViewDimension::D2,
+                    sample_type: TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 3,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: TextureViewDimension::D2,
+                    sample_type: TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+        ];
+        let texture = effect
+            .texture()
+            .map(|image| Rc::new(create_effect_texture(ctx, &image)));
+        if texture.is_some() {
+            entries.push(BindGroupLayoutEntry {
+                binding: 4,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: TextureViewDimension::D2,
+                    sample_type: TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            });
+        }
+        if !uniforms.is_empty() {
+            entries.push(BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            });
+        }
+        let bind_group_layout = ctx
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("2d_post_effect_bind_group_layout"),
+                entries: &entries,
+            });
+        let layout = ctx
+            .device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("2d_post_effect_pipeline_layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let pipeline = Rc::new(
+            ctx.device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some("2d_post_effect_pipeline"),
+                    layout: Some(&layout),
+                    vertex: VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                        compilation_options: PipelineCompilationOptions::default(),
+                    },
+                    fragment: Some(FragmentState {
+                        module: &shader,
+                        entry_point: "fs_main",
+                        targets: &[Some(ctx.surface_texture_format.into())],
+                        compilation_options: PipelineCompilationOptions::default(),
+                    }),
+                    primitive: PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: MultisampleState::default(),
+                    multiview: None,
+                }),
+        );
+
+        let passes = effect.passes();
+        for pass in 0..passes {
+            // Passes are recorded in one encoder, a buffer written between two of them would
+            // only hold the last value
+            let uniform_buffer = (!uniforms.is_empty()).then(|| {
+                ctx.device.create_buffer(&BufferDescriptor {
+                    label: Some("2d_post_effect_uniforms"),
+                    size: uniforms.len() as u64,
+                    usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            });
+            self.passes.push(PostEffectPass {
+                effect: effect.clone(),
+                pass,
+                // The input of the next effect is at full resolution
+                resolution: match pass + 1 == passes {
+                    true => PassResolution::Full,
+                    false => effect.pass_resolution(pass),
+                },
+                pipeline: pipeline.clone(),
+                uniform_buffer,
+                texture: texture.clone(),
+            });
+        }
+    }
+
+    fn target(&self, index: usize) -> &TextureView {
+        match index {
+            0 => &self.scene.1,
+            i => &self.swap[i - 1].2,
+        }
+    }
+
+    fn target_resolution(&self, index: usize) -> PassResolution {
+        match index {
+            0 => PassResolution::Full,
+            i => self.swap[i - 1].0,
+        }
+    }
+
+    /// Runs the effects one after the other from the scene texture, the last one into `output`
+    pub(crate) fn apply(&self, ctx: &GraphicsCtx, output: &TextureView) {
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("2d_post_process_encoder"),
+            });
+
+        // Indices of the scene then swap textures holding the input of the pass and of the effect
+        let mut input = 0;
+        let mut effect_input = 0;
+        for (i, pass) in self.passes.iter().enumerate() {
+            if pass.pass == 0 {
+                effect_input = input;
+            }
+            // The input of an effect is at full resolution, which leaves a target free at
+            // every resolution
+            let next = (0..=self.swap.len())
+                .find(|t| {
+                    *t != input
+                        && *t != effect_input
+                        && self.target_resolution(*t) == pass.resolution
+                })
+                .unwrap();
+            let target = match i + 1 == self.passes.len() {
+                true => output,
+                false => self.target(next),
+            };
+
+            let mut entries = vec![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(self.target(input)),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(self.target(effect_input)),
+                },
+            ];
+            if let Some(uniform_buffer) = &pass.uniform_buffer {
+                ctx.queue
+                    .write_buffer(uniform_buffer, 0, &pass.effect.pass_uniforms(pass.pass));
+                entries.push(BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                });
+            }
+            if let Some(texture) = &pass.texture {
+                entries.push(BindGroupEntry {
+                    binding: 4,
+                    resource: BindingResource::TextureView(texture),
+                });
+            }
+            let bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
+                label: Some("2d_post_effect_bind_group"),
+                layout: &pass.pipeline.get_bind_group_layout(0),
+                entries: &entries,
+            });
+
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("2d_post_effect_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&pass.pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+            input = next;
+        }
+
+        ctx.queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+fn create_target(ctx: &GraphicsCtx, (width, height): (u32, u32)) -> (Texture, TextureView) {
+    let texture = ctx.device.create_texture(&TextureDescriptor {
+        label: Some("2d_post_process_target"),
+        size: Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: ctx.surface_texture_format,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&TextureViewDescriptor::default());
+    (texture, view)
+}
+
+fn create_swap(ctx: &GraphicsCtx, size: (u32, u32)) -> Vec<(PassResolution, Texture, TextureView)> {
+    PassResolution::ALL
+        .into_iter()
+        .flat_map(|resolution| [resolution; 2])
+        .map(|resolution| {
+            let (texture, view) = create_target(ctx, resolution.size(size));
+            (resolution, texture, view)
+        })
+        .collect()
+}
+
+fn create_effect_texture(ctx: &GraphicsCtx, image: &RgbaImage) -> TextureView {
+    let size = Extent3d {
+        width: image.width(),
+        height: image.height(),
+        depth_or_array_layers: 1,
+    };
+    let texture = ctx.device.create_texture(&TextureDescriptor {
+        label: Some("2d_post_effect_texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8UnormSrgb,
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    ctx.queue.write_texture(
+        texture.as_image_copy(),
+        image.as_raw(),
+        ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * image.width()),
+            rows_per_image: Some(image.height()),
+        },
+        size,
+    );
+    texture.create_view(&TextureViewDescriptor::default())
+}
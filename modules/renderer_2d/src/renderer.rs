@@ -1,15 +1,57 @@
-use std::{mem::size_of, num::NonZeroU64};
+use std::{
+    collections::HashSet,
+    mem::{size_of, size_of_val},
+    num::NonZeroU64,
+    rc::Rc,
+};
 
 use bytemuck::{cast_slice, Pod, Zeroable};
-use cgmath::Matrix3;
+use cgmath::{InnerSpace, Matrix3, Rad, SquareMatrix, Vector2, Vector3};
 
 use rgine_graphics::{
     color::Color3,
     ctx::{Frame, GraphicsCtx},
 };
+use rgine_logger::warn;
+use rgine_modules::AnyResult;
 use wgpu::{util::StagingBelt, *};
 
-use crate::texture::{Atlas, DrawParams, Sprite, SpriteSheetsRegistry};
+use crate::{
+    asset_server::{Handle, TextureAsset},
+    post_process::{PostEffect, PostProcessor},
+    shapes::{GradientDirection, Rect},
+    text::{FontHandle, TextAlign, TextLayout},
+    texture::{
+        Atlas, BlendMode, DrawParams, Outline, RgbaImage, SheetId, SheetTooLargeError, Sprite,
+        SpriteSheet, SpriteSheetHandle, SpriteSheetsRegistry, TextureSampling, TintMode,
+        PLACEHOLDER_SPRITE,
+    },
+    timer::GpuTimer,
+};
+
+/// Error of [`SpriteRenderer::submit_to_render_target`] with an invalid target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderTargetError {
+    /// The sprite sheet is not loaded, or failed to
+    NotLoaded,
+    /// The sprite sheet was not registered with
+    /// [`SpriteSheetsRegistry::register_render_target`]
+    NotARenderTarget,
+}
+
+impl std::fmt::Display for RenderTargetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotLoaded => write!(f, "Tried to render into a sprite sheet that is not loaded"),
+            Self::NotARenderTarget => write!(
+                f,
+                "Tried to render into a sprite sheet that is not a render target"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RenderTargetError {}
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -17,89 +59,1148 @@ pub struct SpriteInstance {
     transform: [[f32; 3]; 3],
     tex_pos: [f32; 2],
     tex_dims: [f32; 2],
-    tint: [f32; 3],
+    tint: [f32; 4],
     z_index: f32,
+    /// Times the texture rect is repeated across the quad
+    repeat: [f32; 2],
+    /// One of the `TINT_*` modes of the shader
+    tint_mode: u32,
+    /// One of the `SHAPE_*` shapes of the shader, read along with `tint_mode`
+    shape: u32,
+    outline_color: [f32; 4],
+    /// Outline thickness in texels, no outline is drawn if zero
+    outline_width: f32,
+    /// Tint at the end of the gradient
+    gradient_tint: [f32; 4],
+    /// Direction of the gradient across the quad, no gradient is applied if zero
+    gradient_direction: [f32; 2],
+    /// Half size of the shape in world units, then its corner radius
+    shape_params: [f32; 3],
+    /// Free for custom shaders, see [`DrawParams::custom`]
+    custom: [f32; 4],
+}
+
+const TINT_MULTIPLY: u32 = 0;
+const TINT_REPLACE: u32 = 1;
+/// Replaces the color by the tint multiplied by the texel alpha
+const TINT_REPLACE_PREMULTIPLIED: u32 = 2;
+
+const SHAPE_QUAD: u32 = 0;
+/// Rounded rectangle cut out of the quad by its signed distance field, anti-aliased on its edge
+const SHAPE_ROUNDED_RECT: u32 = 1;
+
+/// Instances sharing the same key are drawn within the same draw call
+#[derive(Clone, Copy, PartialEq)]
+struct BatchKey {
+    texture: usize,
+    blend_mode: BlendMode,
+    clip: Option<(u32, u32, u32, u32)>,
+}
+
+/// Work done by the renderer during a frame, including flushes and render target submissions,
+/// see [`SpriteRenderer::last_stats`]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    /// Sprites, glyphs and shapes drawn
+    pub sprites: u32,
+    /// Instance batches uploaded, see [`SpriteRendererConfig::max_sprites_per_batch`]
+    pub batches: u32,
+    pub draw_calls: u32,
+    /// Instance data uploaded to the GPU
+    pub bytes_uploaded: u64,
+    /// GPU duration of the render passes of a recent frame, measured asynchronously.
+    /// `None` if timestamp queries are not supported by the device
+    pub gpu_time_ms: Option<f32>,
+    /// Texture bind group changes between draw calls
+    pub bind_group_switches: u32,
+}
+
+/// Color and depth attachments of a render pass
+enum PassTarget<'a> {
+    /// Given view using the window sized depth and multisampled textures
+    Window(&'a TextureView),
+    /// Render target at the given atlas texture index
+    RenderTarget(usize),
+    /// Given color, multisampled color and depth views
+    Texture(&'a TextureView, Option<&'a TextureView>, &'a TextureView),
 }
 
 pub struct SpriteRenderer {
-    pipeline: RenderPipeline,
+    /// One pipeline per [`BlendMode`], in [`BlendMode::ALL`] order
+    pipelines: [RenderPipeline; BlendMode::ALL.len()],
+    /// Line mode variants of `pipelines`, created once the wireframe is first enabled
+    wireframe_pipelines: Option<[RenderPipeline; BlendMode::ALL.len()]>,
+    wireframe: bool,
+    /// Draws the letterbox bars, which are never culled whatever `front_face` and `cull_mode`
+    letterbox_pipeline: RenderPipeline,
+    /// Missing sheets already reported, so that they are only logged once
+    missing_sheets: HashSet<SpriteSheetHandle>,
+    texture_bind_group_layout: BindGroupLayout,
+    sampling: TextureSampling,
     depth_texture: Texture,
     depth_texture_view: TextureView,
     depth_texture_sampler: Sampler,
+    config: SpriteRendererConfig,
+    msaa_view: Option<TextureView>,
     quad_vertex_buf: Buffer,
     quad_index_buf: Buffer,
     sprite_instance_buf: Buffer,
     sprite_staging_belt: StagingBelt,
 
     proj_matrix: Matrix3<f32>,
+    view_matrix: Matrix3<f32>,
+    clear_color: Option<Color3>,
+    depth_sorting: bool,
+    texture_sorting: bool,
     atlas: Atlas,
     queue: Vec<SpriteInstance>,
+    queue_keys: Vec<BatchKey>,
+    /// Kept across frames to sort the queue without allocating
+    sort_buffer: Vec<(SpriteInstance, BatchKey)>,
+    window_size: (u32, u32),
+    viewport: (u32, u32, u32, u32),
+    /// Whether sprites were already flushed into the current frame, which must then not be cleared
+    frame_flushed: bool,
+    /// Stats of the current frame, then of the last one
+    stats: RenderStats,
+    last_stats: RenderStats,
+    gpu_timer: Option<GpuTimer>,
+    /// Created once the first post effect is added
+    post_processor: Option<PostProcessor>,
+    #[cfg(feature = "hot_reload")]
+    sheet_watcher: Option<crate::hot_reload::SheetWatcher>,
 }
 
-const MAX_BATCHES: u64 = 100;
-const MAX_SPRITES_PER_BATCH: u64 = 5_000;
+/// Sizes and quality settings fixed at the renderer creation
+#[derive(Clone, Copy, Debug)]
+pub struct SpriteRendererConfig {
+    /// Sprites uploaded and drawn together, their instances share one staging belt chunk
+    pub max_sprites_per_batch: u64,
+    /// Batches per frame, sprites queued past `max_sprites_per_batch * max_batches` are dropped
+    pub max_batches: u64,
+    /// MSAA samples per pixel (1, 2, 4 or 8), unsupported counts fall back to the highest
+    /// supported one below
+    pub sample_count: u32,
+    /// Space sprite transforms are expressed in
+    pub projection: Projection,
+    /// Whether y goes downward like screen coordinates, instead of upward (default).
+    ///
+    /// Sprites and text are kept upright either way, text lines then go toward positive y.
+    pub y_down: bool,
+    /// Part of the window sprites are drawn in
+    pub viewport: ViewportMode,
+    /// `(front, back)` bounds of [`DrawParams::depth`], mapped linearly to the depth buffer range.
+    ///
+    /// Depths outside of it are clamped. Defaults to `(0, 100)`, `(-100, 100)` allows
+    /// layer numbers such as `-100..=100`.
+    pub depth_range: (f32, f32),
+    /// Sprite faces discarded, [`CullMode::None`] keeps the sprites mirrored by a negative
+    /// scale visible since it reverses their winding
+    pub cull_mode: CullMode,
+    /// Whether drawing a sprite of a sheet that does not exist panics in debug builds, instead
+    /// of drawing a magenta checkerboard and logging a warning (default)
+    pub panic_on_missing_sheet: bool,
+}
+
+impl Default for SpriteRendererConfig {
+    fn default() -> Self {
+        Self {
+            max_sprites_per_batch: 5_000,
+            max_batches: 100,
+            sample_count: 1,
+            projection: Projection::Normalized,
+            y_down: false,
+            viewport: ViewportMode::Fill,
+            depth_range: (0., 100.),
+            cull_mode: CullMode::Back,
+            panic_on_missing_sheet: false,
+        }
+    }
+}
+
+/// Faces of the sprites that are discarded, see [`SpriteRendererConfig::cull_mode`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CullMode {
+    /// Both faces are drawn
+    None,
+    /// Sprites seen from behind, once mirrored an odd number of times, are discarded
+    #[default]
+    Back,
+    /// Only sprites seen from behind are drawn
+    Front,
+}
+
+impl CullMode {
+    fn face(self) -> Option<Face> {
+        match self {
+            CullMode::None => None,
+            CullMode::Back => Some(Face::Back),
+            CullMode::Front => Some(Face::Front),
+        }
+    }
+}
+
+/// Part of the window sprites are drawn in, see [`SpriteRendererConfig::viewport`]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ViewportMode {
+    /// The whole window, more or less of the world is visible depending on its aspect ratio
+    #[default]
+    Fill,
+    /// The largest centered rectangle of `aspect_ratio` (width / height) fitting in the window,
+    /// the remaining bars are filled with `color`
+    Letterbox { aspect_ratio: f32, color: Color3 },
+}
+
+/// Rectangle `(x, y, width, height)` in pixels of the window sprites are drawn in
+fn compute_viewport(mode: ViewportMode, (w, h): (u32, u32)) -> (u32, u32, u32, u32) {
+    match mode {
+        ViewportMode::Fill => (0, 0, w, h),
+        ViewportMode::Letterbox { aspect_ratio, .. } => {
+            let width = ((h as f32 * aspect_ratio).round() as u32).clamp(1, w);
+            let height = ((w as f32 / aspect_ratio).round() as u32).clamp(1, h);
+            if width < w {
+                ((w - width) / 2, 0, width, h)
+            } else {
+                (0, (h - height) / 2, w, height)
+            }
+        }
+    }
+}
+
+/// Rectangles of the window outside of `viewport`, to be filled by the letterbox color
+fn letterbox_bars(
+    (x, y, width, height): (u32, u32, u32, u32),
+    (w, h): (u32, u32),
+) -> [(u32, u32, u32, u32); 2] {
+    if width < w {
+        [(0, 0, x, h), (x + width, 0, w - x - width, h)]
+    } else {
+        [(0, 0, w, y), (0, y + height, w, h - y - height)]
+    }
+}
+
+/// Maps the space sprites are drawn in to the window, before the camera is applied.
+///
+/// The y axis goes upward unless [`SpriteRendererConfig::y_down`] is set.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Projection {
+    /// The shortest side of the window spans from -1 to 1, the origin is at its center
+    #[default]
+    Normalized,
+    /// One unit is one physical pixel of the target, so sprites keep their size in pixels on
+    /// HiDPI displays. Scale the camera by the platform scale factor to draw in logical pixels
+    Pixels { origin: PixelOrigin },
+}
+
+/// Point of the target at the origin of [`Projection::Pixels`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PixelOrigin {
+    #[default]
+    TopLeft,
+    Center,
+}
+
+impl SpriteRendererConfig {
+    pub(crate) fn proj_matrix(&self, (w, h): (u32, u32)) -> Matrix3<f32> {
+        let (w, h) = (w as f32, h as f32);
+        let y_sign = if self.y_down { -1. } else { 1. };
+        match self.projection {
+            Projection::Normalized => {
+                let (x, y) = if w < h { (1.0, w / h) } else { (h / w, 1.0) };
+                Matrix3::from_nonuniform_scale(x, y_sign * y)
+            }
+            Projection::Pixels { origin } => {
+                let offset = match origin {
+                    PixelOrigin::TopLeft => Vector2::new(-1., 1.),
+                    PixelOrigin::Center => Vector2::new(0., 0.),
+                };
+                Matrix3::from_translation(offset)
+                    * Matrix3::from_nonuniform_scale(2. / w, y_sign * 2. / h)
+            }
+        }
+    }
+
+    /// Winding of the quads once projected, mirroring y reverses it so that they are not culled
+    fn front_face(&self) -> FrontFace {
+        if self.y_down {
+            FrontFace::Cw
+        } else {
+            FrontFace::Ccw
+        }
+    }
+}
+
+const DEFAULT_CLEAR_COLOR: Color3 = Color3::gray(0.01);
 
 impl SpriteRenderer {
     pub fn new(
         ctx: &GraphicsCtx,
         window_size: (u32, u32),
         sprite_registry: SpriteSheetsRegistry,
+        mut config: SpriteRendererConfig,
     ) -> Self {
-        let (sprite_pipeline, texture_bind_group_layout) =
-            create_sprite_pipeline(&ctx.device, ctx.surface_texture_format);
+        assert!(
+            config.max_sprites_per_batch > 0 && config.max_batches > 0,
+            "Renderer 2D batch sizes must not be zero!"
+        );
+        assert!(
+            config.depth_range.0 != config.depth_range.1,
+            "Renderer 2D depth range must not be empty!"
+        );
+        config.sample_count = supported_sample_count(ctx, config.sample_count);
+        let sample_count = config.sample_count;
+        let sampling = sprite_registry.sampling;
+        let texture_bind_group_layout = create_texture_bind_group_layout(&ctx.device, sampling);
+        let pipelines = create_sprite_pipelines(
+            &ctx.device,
+            ctx.surface_texture_format,
+            &texture_bind_group_layout,
+            sample_count,
+            config.front_face(),
+            config.cull_mode,
+            PolygonMode::Fill,
+            BlendMode::ALL,
+        );
+        let [letterbox_pipeline] = create_sprite_pipelines(
+            &ctx.device,
+            ctx.surface_texture_format,
+            &texture_bind_group_layout,
+            sample_count,
+            config.front_face(),
+            CullMode::None,
+            PolygonMode::Fill,
+            [BlendMode::Alpha],
+        );
         let (depth_texture, depth_texture_view, depth_texture_sampler) =
-            create_depth_texture(&ctx.device, window_size);
+            create_depth_texture(&ctx.device, window_size, sample_count);
+        let msaa_view = create_msaa_texture(ctx, window_size, sample_count);
         let (quad_vertex_buf, quad_index_buf) = create_quad_vertex_buf(&ctx.device);
-        let sprite_instance_buf = create_sprite_instance_buf(&ctx.device);
-        let sprite_staging_belt =
-            StagingBelt::new(std::mem::size_of::<SpriteInstance>() as u64 * MAX_SPRITES_PER_BATCH);
+        let sprite_instance_buf = create_sprite_instance_buf(&ctx.device, config);
+        let sprite_staging_belt = StagingBelt::new(
+            std::mem::size_of::<SpriteInstance>() as u64 * config.max_sprites_per_batch,
+        );
 
-        let queue = Vec::with_capacity(MAX_SPRITES_PER_BATCH as usize);
+        let queue = Vec::with_capacity(config.max_sprites_per_batch as usize);
+        let queue_keys = Vec::with_capacity(config.max_sprites_per_batch as usize);
 
-        let atlas = sprite_registry.build_atlas(ctx, &texture_bind_group_layout);
+        #[cfg(feature = "hot_reload")]
+        let sheet_watcher = crate::hot_reload::SheetWatcher::new(&sprite_registry)
+            .inspect_err(|e| warn!("Unable to watch sprite sheets for changes: {e}"))
+            .ok();
+        let atlas = sprite_registry.build_atlas(ctx, &texture_bind_group_layout, sample_count);
 
-        let proj_matrix = compute_proj_matrix(window_size);
+        let viewport = compute_viewport(config.viewport, window_size);
+        let proj_matrix = config.proj_matrix((viewport.2, viewport.3));
 
         Self {
-            pipeline: sprite_pipeline,
+            pipelines,
+            wireframe_pipelines: None,
+            wireframe: false,
+            letterbox_pipeline,
+            missing_sheets: HashSet::new(),
+            texture_bind_group_layout,
+            sampling,
             depth_texture,
             depth_texture_view,
             depth_texture_sampler,
+            config,
+            msaa_view,
             quad_vertex_buf,
             quad_index_buf,
             sprite_staging_belt,
             sprite_instance_buf,
             proj_matrix,
+            view_matrix: Matrix3::identity(),
+            clear_color: Some(DEFAULT_CLEAR_COLOR),
+            depth_sorting: true,
+            texture_sorting: false,
             queue,
+            queue_keys,
+            sort_buffer: Vec::new(),
             atlas,
+            window_size,
+            viewport,
+            frame_flushed: false,
+            stats: RenderStats::default(),
+            last_stats: RenderStats::default(),
+            gpu_timer: GpuTimer::new(ctx),
+            post_processor: None,
+            #[cfg(feature = "hot_reload")]
+            sheet_watcher,
         }
     }
 
+    /// Uploads again the sheets registered from a file that changed on the disk since the last
+    /// call, returning their handles and paths. Handles stay valid, but a sheet whose size
+    /// changed is not reloaded.
+    #[cfg(feature = "hot_reload")]
+    pub fn reload_changed_sheets(&mut self, ctx: &GraphicsCtx) -> Vec<(SpriteSheetHandle, String)> {
+        match &self.sheet_watcher {
+            Some(watcher) => watcher.reload_changed(ctx, &self.atlas),
+            None => Vec::new(),
+        }
+    }
+
+    /// Registers a new sprite sheet after the renderer creation, existing handles stay valid.
+    ///
+    /// The sheet is not packed into the atlas but gets its own texture instead: registering
+    /// never repacks anything, but drawing sprites from different textures breaks batching.
+    /// Sheets registered this way are lost if the renderer is recreated, unlike the ones
+    /// registered with a [`RegisterSpriteSheetEvent`](crate::RegisterSpriteSheetEvent).
+    ///
+    /// Fails if the image is larger than the maximum texture size of the device.
+    pub fn register_sheet(
+        &mut self,
+        ctx: &GraphicsCtx,
+        image: RgbaImage,
+        sprite_px_size: Vector2<u32>,
+    ) -> Result<SpriteSheetHandle, SheetTooLargeError> {
+        self.atlas.add_sheet(
+            ctx,
+            image,
+            sprite_px_size,
+            self.sampling,
+            &self.texture_bind_group_layout,
+        )
+    }
+
+    /// Uploads the texture decoded by the asset server for `handle`, like
+    /// [`SpriteRenderer::register_sheet`]. Sprites of the handle are drawn from then on
+    pub(crate) fn register_asset(
+        &mut self,
+        ctx: &GraphicsCtx,
+        handle: Handle<TextureAsset>,
+        image: RgbaImage,
+        sprite_px_size: Vector2<u32>,
+    ) -> Result<(), SheetTooLargeError> {
+        let SpriteSheetHandle(SheetId::Index(index)) =
+            self.register_sheet(ctx, image, sprite_px_size)?
+        else {
+            unreachable!("Sheets added to the atlas have an index")
+        };
+        let SpriteSheetHandle(SheetId::Asset(id)) = handle.into() else {
+            unreachable!()
+        };
+        self.atlas.assets.insert(id, index);
+        Ok(())
+    }
+
+    /// Sheet `sprite` is drawn from, the placeholder replaces the whole sprite if the sheet does
+    /// not exist or is still loading. Sheets that do not exist are reported once
+    fn resolve_sprite<'a>(&mut self, sprite: &'a Sprite) -> (SpriteSheet, &'a Sprite) {
+        if let Some(sheet) = self.atlas.sheet(sprite.sheet) {
+            return (*sheet, sprite);
+        }
+        // Sheets that failed to load were reported when the atlas was built
+        match sprite.sheet.0 {
+            SheetId::Index(index) if index >= self.atlas.sheets.len() => {
+                if cfg!(debug_assertions) && self.config.panic_on_missing_sheet {
+                    panic!(
+                        "Tried to draw a sprite of the sprite sheet {index}, which does not exist!"
+                    );
+                }
+                if self.missing_sheets.insert(sprite.sheet) {
+                    warn!("Tried to draw a sprite of the sprite sheet {index}, which does not exist! A placeholder is drawn instead");
+                }
+            }
+            _ => {}
+        }
+        (self.atlas.placeholder, &PLACEHOLDER_SPRITE)
+    }
+
     pub fn draw(&mut self, sprite: Sprite, params: DrawParams) {
-        let spritesheet = self.atlas.sheets[sprite.sheet.0];
-
-        self.queue.push(SpriteInstance {
-            transform: (self.proj_matrix * params.transform).into(),
-            tex_pos: spritesheet.tex_coords(sprite.position).into(),
-            tex_dims: spritesheet.tex_dims(sprite.size).into(),
-            tint: params.tint.into(),
-            z_index: params.depth,
-        })
+        self.draw_batch(&[(sprite, params)]);
+    }
+
+    /// Draws every sprite of `sprites` like [`SpriteRenderer::draw`], with the camera and
+    /// projection applied once for the whole batch.
+    pub fn draw_batch(&mut self, sprites: &[(Sprite, DrawParams)]) {
+        let proj_view = self.proj_matrix * self.view_matrix;
+        self.queue.reserve(sprites.len());
+        self.queue_keys.reserve(sprites.len());
+        for (sprite, params) in sprites {
+            let (spritesheet, sprite) = self.resolve_sprite(sprite);
+            let transform = params.transform
+                * Matrix3::from_translation(-Vector2::new(params.origin.0, params.origin.1));
+            let (instance, key) = self.instance(
+                proj_view,
+                transform,
+                spritesheet.tex_coords(sprite),
+                spritesheet.tex_dims(sprite),
+                spritesheet.texture,
+                params.repeat,
+                params,
+            );
+            self.queue.push(instance);
+            self.queue_keys.push(key);
+        }
+    }
+
+    /// Draws `sprite` stretched over `rect` while keeping its borders from stretching.
+    ///
+    /// `border` is `(left, right, bottom, top)` in sprite pixels, `rect` is in the same unit and
+    /// `params.transform` is applied on top of it. Corners keep their size, edges are stretched
+    /// along a single axis and the center fills the remaining space.
+    pub fn draw_nine_slice(
+        &mut self,
+        sprite: Sprite,
+        rect: Rect,
+        (left, right, bottom, top): (f32, f32, f32, f32),
+        params: DrawParams,
+    ) {
+        let (spritesheet, sprite) = self.resolve_sprite(&sprite);
+        let tex_pos = spritesheet.tex_coords(sprite);
+        let tex_dims = spritesheet.tex_dims(sprite);
+        let size_px = spritesheet
+            .px_size(sprite.size, sprite.unit)
+            .map(|x| x as f32);
+
+        // Bounds of the columns (left to right) and rows (along y) in the destination
+        let (low, high) = match self.config.y_down {
+            false => (bottom, top),
+            true => (top, bottom),
+        };
+        let origin = Vector2::new(params.origin.0 * rect.size.x, params.origin.1 * rect.size.y);
+        let xs = [0., left, rect.size.x - right, rect.size.x];
+        let ys = [0., low, rect.size.y - high, rect.size.y];
+        // Same bounds normalized in the sprite uv space where v goes downward
+        let us = [0., left / size_px.x, 1. - right / size_px.x, 1.];
+        let vs = match self.config.y_down {
+            false => [1., 1. - low / size_px.y, high / size_px.y, 0.],
+            true => [0., low / size_px.y, 1. - high / size_px.y, 1.],
+        };
+
+        for i in 0..3 {
+            for j in 0..3 {
+                let size = Vector2::new(xs[i + 1] - xs[i], ys[j + 1] - ys[j]);
+                if size.x <= 0. || size.y <= 0. {
+                    continue;
+                }
+                let transform = params.transform
+                    * Matrix3::from_translation(
+                        rect.position + Vector2::new(xs[i], ys[j]) - origin,
+                    )
+                    * Matrix3::from_nonuniform_scale(size.x, size.y);
+                let slice_pos =
+                    tex_pos + Vector2::new(us[i] * tex_dims.x, vs[j].min(vs[j + 1]) * tex_dims.y);
+                let slice_dims = Vector2::new(
+                    (us[i + 1] - us[i]) * tex_dims.x,
+                    (vs[j] - vs[j + 1]).abs() * tex_dims.y,
+                );
+                self.push_instance(
+                    transform,
+                    slice_pos,
+                    slice_dims,
+                    spritesheet.texture,
+                    (1., 1.),
+                    &params,
+                );
+            }
+        }
+    }
+
+    /// Draws `text` using a font registered with [`SpriteSheetsRegistry::register_font`].
+    ///
+    /// The text starts on the baseline at the origin of `params.transform`, where one unit is
+    /// the font size. Lines are separated by `\n`.
+    pub fn draw_text(&mut self, font: FontHandle, text: &str, params: DrawParams) {
+        let Some(glyph_atlas) = &mut self.atlas.fonts[font.0] else {
+            return;
+        };
+        let lines = glyph_atlas.wrap(text, None);
+        let layout = glyph_atlas.layout(&lines, TextAlign::Left, None);
+        let texture = glyph_atlas.texture_index;
+        self.draw_text_layout(texture, layout, params);
+    }
+
+    /// Draws `text` within `rect`, breaking its lines between words so that they fit in its
+    /// width and aligning them according to `align`. Returns the box of the drawn lines.
+    ///
+    /// The font is scaled so that its baselines are `line_height` apart, the first line being at
+    /// the top of `rect`. Lines going past its bottom are still drawn. Like
+    /// [`SpriteRenderer::draw_nine_slice`], `rect` is in the unit of `params.transform`.
+    /// Nothing is drawn with a font that failed to load, an empty box is returned then.
+    pub fn draw_text_wrapped(
+        &mut self,
+        font: FontHandle,
+        text: &str,
+        rect: Rect,
+        align: TextAlign,
+        line_height: f32,
+        params: DrawParams,
+    ) -> Rect {
+        let Some(glyph_atlas) = &mut self.atlas.fonts[font.0] else {
+            return Rect::new(rect.position, Vector2::new(0., 0.));
+        };
+        let scale = line_height / glyph_atlas.line_height();
+        let lines = glyph_atlas.wrap(text, Some(rect.size.x / scale));
+        let layout = glyph_atlas.layout(&lines, align, Some(rect.size.x / scale));
+        let ascent = glyph_atlas.ascent() * scale;
+        let texture = glyph_atlas.texture_index;
+        let (left, size) = (layout.left * scale, layout.size * scale);
+
+        // The first baseline is below the top of the rect, which is at its lowest y when y goes downward
+        let (baseline, bounds_y) = match self.config.y_down {
+            false => (
+                rect.position.y + rect.size.y - ascent,
+                rect.position.y + rect.size.y - size.y,
+            ),
+            true => (rect.position.y + ascent, rect.position.y),
+        };
+        let transform = params.transform
+            * Matrix3::from_translation(Vector2::new(rect.position.x, baseline))
+            * Matrix3::from_scale(scale);
+        self.draw_text_layout(
+            texture,
+            layout,
+            DrawParams {
+                transform,
+                ..params
+            },
+        );
+
+        Rect::new(Vector2::new(rect.position.x + left, bounds_y), size)
+    }
+
+    /// Size `(width, height)` of `text` once drawn with [`SpriteRenderer::draw_text`] by a
+    /// transform of `scale`, without drawing it. Each line is one line height tall, the text of
+    /// a font that failed to load is empty.
+    pub fn measure_text(&self, font: FontHandle, text: &str, scale: f32) -> (f32, f32) {
+        let Some(glyph_atlas) = &self.atlas.fonts[font.0] else {
+            return (0., 0.);
+        };
+        let lines = glyph_atlas.wrap(text, None);
+        let width = lines
+            .iter()
+            .map(|line| glyph_atlas.line_width(line))
+            .fold(0., f32::max);
+        let height = lines.len() as f32 * glyph_atlas.line_height();
+        (width * scale, height * scale)
+    }
+
+    /// Draws the glyphs of `layout` from the glyph atlas `texture`
+    fn draw_text_layout(&mut self, texture: usize, layout: TextLayout, params: DrawParams) {
+        let y_sign = if self.config.y_down { -1. } else { 1. };
+        for (pen, glyph) in layout.glyphs {
+            let pen = Vector2::new(pen.x, y_sign * pen.y);
+            // Glyph offsets go upward from the baseline, to the bottom of the glyph
+            let offset = match self.config.y_down {
+                false => glyph.offset,
+                true => Vector2::new(glyph.offset.x, -glyph.offset.y - glyph.size.y),
+            };
+            let transform = params.transform
+                * Matrix3::from_translation(pen + offset)
+                * Matrix3::from_nonuniform_scale(glyph.size.x, glyph.size.y);
+            self.push_instance(
+                transform,
+                glyph.tex_pos,
+                glyph.tex_dims,
+                texture,
+                (1., 1.),
+                &params,
+            );
+        }
+    }
+
+    /// Draws a rectangle filled with `color`.
+    pub fn draw_rect(&mut self, rect: Rect, color: Color3, depth: f32) {
+        let transform = Matrix3::from_translation(rect.position)
+            * Matrix3::from_nonuniform_scale(rect.size.x, rect.size.y);
+        self.draw_untextured(transform, color, depth);
+    }
+
+    /// Draws a rectangle filled with a linear gradient, going from `color_a` to `color_b` in
+    /// `direction` across the rectangle.
+    pub fn draw_rect_gradient(
+        &mut self,
+        rect: Rect,
+        color_a: Color3,
+        color_b: Color3,
+        direction: GradientDirection,
+        depth: f32,
+    ) {
+        let transform = Matrix3::from_translation(rect.position)
+            * Matrix3::from_nonuniform_scale(rect.size.x, rect.size.y);
+        let (mut instance, key) = self.instance(
+            self.proj_matrix * self.view_matrix,
+            transform,
+            self.atlas.white_texel,
+            Vector2::new(0., 0.),
+            0,
+            (1., 1.),
+            &DrawParams {
+                tint: color_a,
+                depth,
+                ..Default::default()
+            },
+        );
+        instance.gradient_tint = [color_b.r, color_b.g, color_b.b, 1.];
+        instance.gradient_direction = direction.vector().into();
+        self.queue.push(instance);
+        self.queue_keys.push(key);
+    }
+
+    /// Draws a rectangle filled with `color` whose corners are rounded by `radius`, in world units.
+    pub fn draw_rounded_rect(&mut self, rect: Rect, radius: f32, color: Color3, depth: f32) {
+        let half_size = rect.size.map(f32::abs) / 2.;
+        let radius = radius.clamp(0., half_size.x.min(half_size.y));
+        let transform = Matrix3::from_translation(rect.position)
+            * Matrix3::from_nonuniform_scale(rect.size.x, rect.size.y);
+        let (mut instance, key) = self.instance(
+            self.proj_matrix * self.view_matrix,
+            transform,
+            self.atlas.white_texel,
+            Vector2::new(0., 0.),
+            0,
+            (1., 1.),
+            &DrawParams {
+                tint: color,
+                depth,
+                ..Default::default()
+            },
+        );
+        instance.shape = SHAPE_ROUNDED_RECT;
+        instance.shape_params = [half_size.x, half_size.y, radius];
+        self.queue.push(instance);
+        self.queue_keys.push(key);
+    }
+
+    /// Draws a disk of `radius` filled with `color`, in world units.
+    pub fn draw_circle(&mut self, center: Vector2<f32>, radius: f32, color: Color3, depth: f32) {
+        let rect = Rect::new(
+            center - Vector2::new(radius, radius),
+            Vector2::new(2. * radius, 2. * radius),
+        );
+        self.draw_rounded_rect(rect, radius, color, depth);
+    }
+
+    /// Draws a line of `thickness` from `a` to `b`.
+    pub fn draw_line(
+        &mut self,
+        a: Vector2<f32>,
+        b: Vector2<f32>,
+        thickness: f32,
+        color: Color3,
+        depth: f32,
+    ) {
+        let ab = b - a;
+        let transform = Matrix3::from_translation(a)
+            * Matrix3::from_angle_z(Rad(ab.y.atan2(ab.x)))
+            * Matrix3::from_nonuniform_scale(ab.magnitude(), thickness)
+            * Matrix3::from_translation(Vector2::new(0., -0.5));
+        self.draw_untextured(transform, color, depth);
+    }
+
+    fn draw_untextured(&mut self, transform: Matrix3<f32>, color: Color3, depth: f32) {
+        self.push_instance(
+            transform,
+            self.atlas.white_texel,
+            Vector2::new(0., 0.),
+            0,
+            (1., 1.),
+            &DrawParams {
+                tint: color,
+                depth,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Maps `depth` from [`SpriteRendererConfig::depth_range`] to the depth buffer range,
+    /// the back bound being excluded since the depth buffer is cleared to it
+    fn normalized_depth(&self, depth: f32) -> f32 {
+        let (front, back) = self.config.depth_range;
+        ((depth - front) / (back - front)).clamp(0., 1. - f32::EPSILON)
+    }
+
+    fn push_instance(
+        &mut self,
+        transform: Matrix3<f32>,
+        tex_pos: Vector2<f32>,
+        tex_dims: Vector2<f32>,
+        texture: usize,
+        repeat: (f32, f32),
+        params: &DrawParams,
+    ) {
+        let (instance, key) = self.instance(
+            self.proj_matrix * self.view_matrix,
+            transform,
+            tex_pos,
+            tex_dims,
+            texture,
+            repeat,
+            params,
+        );
+        self.queue.push(instance);
+        self.queue_keys.push(key);
+    }
+
+    /// Instance drawn at `transform` in world space, `proj_view` applying the camera then the projection
+    #[allow(clippy::too_many_arguments)]
+    fn instance(
+        &self,
+        proj_view: Matrix3<f32>,
+        transform: Matrix3<f32>,
+        mut tex_pos: Vector2<f32>,
+        mut tex_dims: Vector2<f32>,
+        texture: usize,
+        repeat: (f32, f32),
+        params: &DrawParams,
+    ) -> (SpriteInstance, BatchKey) {
+        // Flipping swaps the uv corners so that the quad winding is left untouched
+        if params.flip_x {
+            tex_pos.x += tex_dims.x;
+            tex_dims.x = -tex_dims.x;
+        }
+        // Textures are stored top row first, they are mirrored so that they stay upright when y goes downward
+        if params.flip_y != self.config.y_down {
+            tex_pos.y += tex_dims.y;
+            tex_dims.y = -tex_dims.y;
+        }
+
+        let mut tint = [params.tint.r, params.tint.g, params.tint.b, params.alpha];
+        // The tint is applied to premultiplied texels as is, so it must be premultiplied too
+        if params.blend_mode == BlendMode::PremultipliedAlpha {
+            for c in &mut tint[..3] {
+                *c *= params.alpha;
+            }
+        }
+
+        let tint_mode = match (params.tint_mode, params.blend_mode) {
+            (TintMode::Multiply, _) => TINT_MULTIPLY,
+            (TintMode::Replace, BlendMode::PremultipliedAlpha) => TINT_REPLACE_PREMULTIPLIED,
+            (TintMode::Replace, _) => TINT_REPLACE,
+        };
+        let (outline_color, outline_width) = match params.outline {
+            Some(outline) => {
+                let mut color = [
+                    outline.color.r,
+                    outline.color.g,
+                    outline.color.b,
+                    params.alpha,
+                ];
+                if params.blend_mode == BlendMode::PremultipliedAlpha {
+                    for c in &mut color[..3] {
+                        *c *= params.alpha;
+                    }
+                }
+                (color, outline.width.clamp(0., Outline::MAX_WIDTH))
+            }
+            None => ([0.; 4], 0.),
+        };
+        let instance = SpriteInstance {
+            transform: (proj_view * transform).into(),
+            tex_pos: tex_pos.into(),
+            tex_dims: tex_dims.into(),
+            tint,
+            z_index: self.normalized_depth(params.depth),
+            repeat: [repeat.0, repeat.1],
+            tint_mode,
+            shape: SHAPE_QUAD,
+            outline_color,
+            outline_width,
+            gradient_tint: tint,
+            gradient_direction: [0., 0.],
+            shape_params: [0.; 3],
+            custom: params.custom,
+        };
+        let key = BatchKey {
+            texture,
+            blend_mode: params.blend_mode,
+            clip: params.clip,
+        };
+        (instance, key)
+    }
+
+    /// Sets the camera (view) matrix applied to every sprite drawn afterward.
+    pub fn set_camera(&mut self, view: Matrix3<f32>) {
+        self.view_matrix = view;
+    }
+
+    /// Converts a `position` in pixels from the top left corner of the window, such as a cursor
+    /// position, to the world point drawn there through the current camera.
+    ///
+    /// `None` if the camera matrix is not invertible, such as a camera scaled by zero.
+    pub fn screen_to_world(&self, position: (f32, f32)) -> Option<Vector2<f32>> {
+        screen_to_world(
+            self.proj_matrix * self.view_matrix,
+            self.viewport_f32(),
+            position,
+        )
+    }
+
+    /// Converts a world `position` to the pixel of the window it is drawn at through the
+    /// current camera, from the top left corner of the window.
+    pub fn world_to_screen(&self, position: (f32, f32)) -> (f32, f32) {
+        world_to_screen(
+            self.proj_matrix * self.view_matrix,
+            self.viewport_f32(),
+            position,
+        )
+    }
+
+    /// Smallest world rectangle containing everything visible through the current camera,
+    /// `None` if the camera matrix is not invertible
+    pub fn camera_bounds(&self) -> Option<Rect> {
+        let (x, y, w, h) = self.viewport_f32();
+        let mut corners = [(x, y), (x + w, y), (x, y + h), (x + w, y + h)].into_iter();
+        let first = self.screen_to_world(corners.next().unwrap())?;
+        let (mut min, mut max) = (first, first);
+        for corner in corners {
+            let corner = self.screen_to_world(corner)?;
+            min = min.zip(corner, f32::min);
+            max = max.zip(corner, f32::max);
+        }
+        Some(Rect::new(min, max - min))
+    }
+
+    /// Rectangle `(x, y, width, height)` in pixels of the window sprites are drawn in,
+    /// see [`SpriteRendererConfig::viewport`]
+    pub fn viewport(&self) -> (u32, u32, u32, u32) {
+        self.viewport
+    }
+
+    fn viewport_f32(&self) -> (f32, f32, f32, f32) {
+        let (x, y, w, h) = self.viewport;
+        (x as f32, y as f32, w as f32, h as f32)
+    }
+
+    /// Sets the color the frame is cleared with before drawing sprites.
+    ///
+    /// `None` keeps the previous content of the frame, allowing to draw over it.
+    pub fn set_clear_color(&mut self, color: Option<Color3>) {
+        self.clear_color = color;
+    }
+
+    /// Enables (default) or disables sorting sprites back to front by depth before submitting.
+    ///
+    /// Without sorting, sprites are drawn in order and only the depth buffer decides which
+    /// one is visible, which breaks blending of overlapping translucent sprites.
+    pub fn set_depth_sorting(&mut self, enabled: bool) {
+        self.depth_sorting = enabled;
+    }
+
+    /// Enables or disables (default) grouping sprites of the same depth by texture before
+    /// submitting, so that textures are switched less often.
+    ///
+    /// Sprites of the same depth from different textures are then no longer drawn in order,
+    /// which only matters if they overlap.
+    pub fn set_texture_sorting(&mut self, enabled: bool) {
+        self.texture_sorting = enabled;
+    }
+
+    /// Enables or disables (default) drawing only the edges of the sprite triangles, to debug
+    /// overdraw and batching.
+    ///
+    /// It requires the `POLYGON_MODE_LINE` feature, sprites are drawn filled without it.
+    pub fn set_wireframe(&mut self, enabled: bool) {
+        self.wireframe = enabled;
+    }
+
+    /// Creates the wireframe pipelines if they are needed for the first time
+    fn prepare_wireframe(&mut self, ctx: &GraphicsCtx) {
+        if !self.wireframe || self.wireframe_pipelines.is_some() {
+            return;
+        }
+        if !ctx.features().contains(Features::POLYGON_MODE_LINE) {
+            warn!("Wireframe rendering is not supported by the graphics device, it is disabled!");
+            self.wireframe = false;
+            return;
+        }
+        self.wireframe_pipelines = Some(create_sprite_pipelines(
+            &ctx.device,
+            ctx.surface_texture_format,
+            &self.texture_bind_group_layout,
+            self.config.sample_count,
+            self.config.front_face(),
+            self.config.cull_mode,
+            PolygonMode::Line,
+            BlendMode::ALL,
+        ));
     }
 
+    /// Stats of the last submitted frame
+    pub fn last_stats(&self) -> RenderStats {
+        self.last_stats
+    }
+
+    /// Resizes the render targets to `window_size`, in physical pixels like
+    /// [`SurfaceResizeEvent`](rgine_graphics::SurfaceResizeEvent) so that
+    /// frames are not upscaled on HiDPI displays.
+    ///
+    /// Null sizes of minimized windows are ignored, the previous targets are kept until restored.
     pub fn resize(&mut self, ctx: &GraphicsCtx, window_size: (u32, u32)) {
-        self.proj_matrix = compute_proj_matrix(window_size);
+        // Textures can't be created with a null size
+        if window_size.0 == 0 || window_size.1 == 0 {
+            return;
+        }
+        self.window_size = window_size;
+        self.viewport = compute_viewport(self.config.viewport, window_size);
+        self.proj_matrix = self.config.proj_matrix((self.viewport.2, self.viewport.3));
         let (depth_texture, depth_texture_view, depth_texture_sampler) =
-            create_depth_texture(&ctx.device, window_size);
+            create_depth_texture(&ctx.device, window_size, self.config.sample_count);
         self.depth_texture = depth_texture;
         self.depth_texture_view = depth_texture_view;
         self.depth_texture_sampler = depth_texture_sampler;
+        self.msaa_view = create_msaa_texture(ctx, window_size, self.config.sample_count);
+        if let Some(post_processor) = &mut self.post_processor {
+            post_processor.resize(ctx, window_size);
+        }
     }
 
-    pub fn submit(&mut self, ctx: &GraphicsCtx, frame: &Frame) {
-        if self.queue.is_empty() {
-            return;
+    /// Appends `effect` to the post effects applied to the frame once every sprite is drawn,
+    /// see [`PostEffect`].
+    pub fn add_post_effect(&mut self, ctx: &GraphicsCtx, effect: Rc<dyn PostEffect>) {
+        self.post_processor
+            .get_or_insert_with(|| PostProcessor::new(ctx, self.window_size))
+            .push(ctx, effect);
+    }
+
+    pub fn clear_post_effects(&mut self) {
+        if let Some(post_processor) = &mut self.post_processor {
+            post_processor.clear();
         }
+    }
+
+    /// Renders the sprites queued so far into `frame` right away, later sprites being drawn over them.
+    ///
+    /// Only the first flush of a frame clears it, even with no sprites queued, and
+    /// [`SpriteRenderer::submit`] ends the frame.
+    pub fn flush(&mut self, ctx: &GraphicsCtx, frame: &Frame) -> AnyResult<()> {
+        if self.queue.is_empty() && self.frame_flushed {
+            return Ok(());
+        }
+        let load = match self.frame_flushed {
+            true => LoadOp::Load,
+            false => self.load_op(),
+        };
+        // Sprites are drawn to the input of the post effects if there are any
+        let post_processor = self.post_processor.take();
+        let view = match &post_processor {
+            Some(post_processor) if !post_processor.is_empty() => &post_processor.scene.1,
+            _ => &frame.view,
+        };
+        let result = self.submit_to(ctx, PassTarget::Window(view), self.window_size, load);
+        self.post_processor = post_processor;
+        self.frame_flushed = true;
+        result
+    }
+
+    /// Flushes the remaining sprites into `frame`, ending it.
+    ///
+    /// Errors raised by the device while rendering are returned, the frame is still ended.
+    pub fn submit(&mut self, ctx: &GraphicsCtx, frame: &Frame) -> AnyResult<()> {
+        let flushed = self.flush(ctx, frame);
+        let post_processed = match &self.post_processor {
+            Some(post_processor) if !post_processor.is_empty() => {
+                ctx.catch_errors(|| post_processor.apply(ctx, &frame.view))
+            }
+            _ => Ok(()),
+        };
+        self.frame_flushed = false;
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            self.stats.gpu_time_ms = gpu_timer.read_ms().or(self.last_stats.gpu_time_ms);
+            gpu_timer.end_frame(ctx);
+        }
+        self.last_stats = std::mem::take(&mut self.stats);
+        flushed?;
+        Ok(post_processed?)
+    }
+
+    /// Renders the sprites queued so far into the render target registered as `target`
+    /// using [`SpriteSheetsRegistry::register_render_target`], it can then be drawn as a sprite.
+    ///
+    /// The render target must not be drawn from while rendering into it.
+    pub fn submit_to_render_target(
+        &mut self,
+        ctx: &GraphicsCtx,
+        target: SpriteSheetHandle,
+    ) -> AnyResult<()> {
+        let texture = self
+            .atlas
+            .sheet(target)
+            .ok_or(RenderTargetError::NotLoaded)?
+            .texture;
+        let render_target = self.atlas.textures[texture]
+            .render_target
+            .as_ref()
+            .ok_or(RenderTargetError::NotARenderTarget)?;
+        let size = render_target.size.into();
+        self.reproject_queue(size);
+
+        self.submit_to(
+            ctx,
+            PassTarget::RenderTarget(texture),
+            size,
+            LoadOp::Clear(Color::TRANSPARENT),
+        )
+    }
+
+    /// Renders the sprites queued so far into an arbitrary `target` texture of `size` pixels.
+    ///
+    /// The target must use the surface texture format and allow `RENDER_ATTACHMENT` usage.
+    pub fn submit_to_texture(
+        &mut self,
+        ctx: &GraphicsCtx,
+        target: &TextureView,
+        size: (u32, u32),
+    ) -> AnyResult<()> {
+        if size == self.window_size {
+            self.submit_to(ctx, PassTarget::Window(target), size, self.load_op())
+        } else {
+            self.reproject_queue(size);
+            let (_depth_texture, depth_view, _) =
+                create_depth_texture(&ctx.device, size, self.config.sample_count);
+            let msaa_view = create_msaa_texture(ctx, size, self.config.sample_count);
+            self.submit_to(
+                ctx,
+                PassTarget::Texture(target, msaa_view.as_ref(), &depth_view),
+                size,
+                self.load_op(),
+            )
+        }
+    }
+
+    /// Queued sprites are projected for the window, this reprojects them for a target of `size` pixels.
+    fn reproject_queue(&mut self, size: (u32, u32)) {
+        let reprojection = self.config.proj_matrix(size) * self.proj_matrix.invert().unwrap();
+        for instance in &mut self.queue {
+            instance.transform = (reprojection * Matrix3::from(instance.transform)).into();
+        }
+    }
+
+    fn load_op(&self) -> LoadOp<Color> {
+        match self.clear_color {
+            Some(color) => LoadOp::Clear(color.into()),
+            None => LoadOp::Load,
+        }
+    }
+
+    fn submit_to(
+        &mut self,
+        ctx: &GraphicsCtx,
+        target: PassTarget,
+        size: (u32, u32),
+        load: LoadOp<Color>,
+    ) -> AnyResult<()> {
+        Ok(ctx.catch_errors(|| self.render_queue(ctx, target, size, load))?)
+    }
+
+    fn render_queue(
+        &mut self,
+        ctx: &GraphicsCtx,
+        target: PassTarget,
+        (width, height): (u32, u32),
+        load: LoadOp<Color>,
+    ) {
+        for glyph_atlas in self.atlas.fonts.iter_mut().flatten() {
+            glyph_atlas.upload(ctx);
+        }
+        self.prepare_wireframe(ctx);
+        let pipelines = match &self.wireframe_pipelines {
+            Some(wireframe_pipelines) if self.wireframe => wireframe_pipelines,
+            _ => &self.pipelines,
+        };
 
         let mut encoder = ctx
             .device
@@ -107,204 +1208,410 @@ impl SpriteRenderer {
                 label: Some("Renderer 2D Command encoder"),
             });
 
-        let queue = std::mem::replace(
-            &mut self.queue,
-            Vec::with_capacity(MAX_SPRITES_PER_BATCH as usize),
-        );
+        let sprites_per_batch = self.config.max_sprites_per_batch as usize;
+        // Given back cleared once submitted, so that their capacity is reused every frame
+        let mut queue = std::mem::take(&mut self.queue);
+        let mut queue_keys = std::mem::take(&mut self.queue_keys);
 
-        let rawqueue = cast_slice(&queue);
+        // Letterbox bars are only drawn around the window viewport
+        let letterbox = match (&target, self.config.viewport) {
+            (PassTarget::Window(_), ViewportMode::Letterbox { color, .. }) => Some(color),
+            _ => None,
+        };
+        let viewport = match target {
+            PassTarget::Window(_) => self.viewport,
+            _ => (0, 0, width, height),
+        };
 
-        self.sprite_staging_belt.recall();
-        {
-            let byte_size = (queue.len() * size_of::<SpriteInstance>()) as u64;
+        // The letterbox bars instance is drawn last
+        let max_sprites =
+            sprites_per_batch * self.config.max_batches as usize - letterbox.is_some() as usize;
+        if queue.len() > max_sprites {
+            warn!(
+                "Too many sprites queued ({}), only the first {} will be drawn!",
+                queue.len(),
+                max_sprites
+            );
+            queue.truncate(max_sprites);
+            queue_keys.truncate(max_sprites);
+        }
+        self.stats.sprites += queue.len() as u32;
+
+        if self.depth_sorting || self.texture_sorting {
+            // Back to front so that translucent sprites blend over what is behind them,
+            // the sort is stable so sprites of the same depth keep their draw order
+            let (depth_sorting, texture_sorting) = (self.depth_sorting, self.texture_sorting);
+            let mut sorted = std::mem::take(&mut self.sort_buffer);
+            sorted.extend(queue.drain(..).zip(queue_keys.drain(..)));
+            sorted.sort_by(|(a, a_key), (b, b_key)| {
+                let depth = match depth_sorting {
+                    true => b.z_index.total_cmp(&a.z_index),
+                    false => std::cmp::Ordering::Equal,
+                };
+                let texture = match texture_sorting {
+                    true => a_key.texture.cmp(&b_key.texture),
+                    false => std::cmp::Ordering::Equal,
+                };
+                depth.then(texture)
+            });
+            for (instance, key) in sorted.drain(..) {
+                queue.push(instance);
+                queue_keys.push(key);
+            }
+            self.sort_buffer = sorted;
+        }
+        if let Some(color) = letterbox {
+            // Quad covering the whole window in front of everything
+            queue.push(SpriteInstance {
+                transform: (Matrix3::from_translation(Vector2::new(-1., -1.))
+                    * Matrix3::from_scale(2.))
+                .into(),
+                tex_pos: self.atlas.white_texel.into(),
+                tex_dims: [0., 0.],
+                tint: [color.r, color.g, color.b, 1.],
+                z_index: 0.,
+                repeat: [1., 1.],
+                tint_mode: TINT_MULTIPLY,
+                shape: SHAPE_QUAD,
+                outline_color: [0.; 4],
+                outline_width: 0.,
+                gradient_tint: [0.; 4],
+                gradient_direction: [0., 0.],
+                shape_params: [0.; 3],
+                custom: [0.; 4],
+            });
+        }
+
+        // Each batch is uploaded separately so that it fits in a single staging belt chunk
+        for (i, batch) in queue.chunks(sprites_per_batch).enumerate() {
+            let offset = (i * sprites_per_batch * size_of::<SpriteInstance>()) as u64;
+            let byte_size = size_of_val(batch) as u64;
+            let Some(size) = NonZeroU64::new(byte_size) else {
+                continue;
+            };
             let mut bufmut = self.sprite_staging_belt.write_buffer(
                 &mut encoder,
                 &self.sprite_instance_buf,
-                0,
-                NonZeroU64::new(byte_size).unwrap(),
+                offset,
+                size,
                 &ctx.device,
             );
-            bufmut.clone_from_slice(rawqueue);
+            bufmut.clone_from_slice(cast_slice(batch));
+            self.stats.batches += 1;
+            self.stats.bytes_uploaded += byte_size;
         }
         self.sprite_staging_belt.finish();
 
+        let (color_view, msaa_view, depth_view) = match target {
+            PassTarget::Window(view) => (view, self.msaa_view.as_ref(), &self.depth_texture_view),
+            PassTarget::RenderTarget(texture) => {
+                let render_target = self.atlas.textures[texture].render_target.as_ref().unwrap();
+                (
+                    &render_target.view,
+                    render_target.msaa_view.as_ref(),
+                    &render_target.depth_view,
+                )
+            }
+            PassTarget::Texture(view, msaa_view, depth_view) => (view, msaa_view, depth_view),
+        };
+        // When multisampling, sprites are drawn to the multisampled texture then resolved
+        let (view, resolve_target) = match msaa_view {
+            Some(msaa_view) => (msaa_view, Some(color_view)),
+            None => (color_view, None),
+        };
+
         {
             let mut render_pass: RenderPass<'_> =
                 encoder.begin_render_pass(&RenderPassDescriptor {
                     label: Some("Sprite Render Pass"),
                     color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &frame.view,
-                        resolve_target: None,
+                        view,
+                        resolve_target,
                         ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(Color3::gray(0.01).into()),
+                            load,
                             store: wgpu::StoreOp::Store,
                         },
                     })],
                     depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                        view: &self.depth_texture_view,
+                        view: depth_view,
                         depth_ops: Some(wgpu::Operations {
                             load: wgpu::LoadOp::Clear(1.0),
                             store: wgpu::StoreOp::Store,
                         }),
                         stencil_ops: None,
                     }),
-                    timestamp_writes: None,
+                    timestamp_writes: self
+                        .gpu_timer
+                        .as_mut()
+                        .and_then(|timer| timer.pass_timestamps()),
                     occlusion_query_set: None,
                 });
 
-            render_pass.set_pipeline(&self.pipeline);
-
             render_pass.set_vertex_buffer(0, self.quad_vertex_buf.slice(..));
             render_pass.set_vertex_buffer(1, self.sprite_instance_buf.slice(..));
-            render_pass.set_bind_group(0, &self.atlas.bind_group, &[]);
             render_pass.set_index_buffer(self.quad_index_buf.slice(..), IndexFormat::Uint16);
-            render_pass.draw_indexed(0..6, 0, 0..queue.len() as u32);
+
+            let (vx, vy, vw, vh) = viewport;
+            render_pass.set_viewport(vx as f32, vy as f32, vw as f32, vh as f32, 0., 1.);
+
+            // Consecutive instances sharing the same key are drawn together
+            let mut first_instance = 0;
+            let mut bound_texture = None;
+            for keys in queue_keys.chunk_by(|a, b| a == b) {
+                let key = keys[0];
+                let instances = first_instance..first_instance + keys.len() as u32;
+                first_instance = instances.end;
+
+                // The scissor rect must be contained within the viewport
+                let (x, y, w, h) = match key.clip {
+                    Some((x, y, w, h)) => {
+                        let (x, y) = (x.min(vw), y.min(vh));
+                        (vx + x, vy + y, w.min(vw - x), h.min(vh - y))
+                    }
+                    None => viewport,
+                };
+                if w == 0 || h == 0 {
+                    continue;
+                }
+
+                render_pass.set_pipeline(&pipelines[key.blend_mode as usize]);
+                render_pass.set_scissor_rect(x, y, w, h);
+                if bound_texture != Some(key.texture) {
+                    render_pass.set_bind_group(
+                        0,
+                        &self.atlas.textures[key.texture].bind_group,
+                        &[],
+                    );
+                    bound_texture = Some(key.texture);
+                    self.stats.bind_group_switches += 1;
+                }
+                render_pass.draw_indexed(0..6, 0, instances);
+                self.stats.draw_calls += 1;
+            }
+
+            if letterbox.is_some() {
+                let instance = first_instance..first_instance + 1;
+                render_pass.set_viewport(0., 0., width as f32, height as f32, 0., 1.);
+                render_pass.set_pipeline(&self.letterbox_pipeline);
+                if bound_texture != Some(0) {
+                    render_pass.set_bind_group(0, &self.atlas.textures[0].bind_group, &[]);
+                    self.stats.bind_group_switches += 1;
+                }
+                for (x, y, w, h) in letterbox_bars(viewport, (width, height)) {
+                    if w > 0 && h > 0 {
+                        render_pass.set_scissor_rect(x, y, w, h);
+                        render_pass.draw_indexed(0..6, 0, instance.clone());
+                        self.stats.draw_calls += 1;
+                    }
+                }
+            }
         }
 
         ctx.queue.submit(std::iter::once(encoder.finish()));
+        // Chunks can only be reused once the commands copying from them are submitted,
+        // they are mapped again asynchronously as soon as the GPU is done with them
+        self.sprite_staging_belt.recall();
+
+        queue.clear();
+        queue_keys.clear();
+        self.queue = queue;
+        self.queue_keys = queue_keys;
     }
 }
 
-fn create_sprite_pipeline(
+fn create_texture_bind_group_layout(device: &Device, sampling: TextureSampling) -> BindGroupLayout {
+    let sampler_binding_type = if sampling.is_filtering() {
+        wgpu::SamplerBindingType::Filtering
+    } else {
+        wgpu::SamplerBindingType::NonFiltering
+    };
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                // Outlines grow the quads according to the texture size
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(sampler_binding_type),
+                count: None,
+            },
+        ],
+        label: Some("bind_group_layout"),
+    })
+}
+
+/// Creates one sprite pipeline per blend mode of `blend_modes`
+#[allow(clippy::too_many_arguments)]
+fn create_sprite_pipelines<const N: usize>(
     device: &Device,
     surface_texture_format: TextureFormat,
-) -> (RenderPipeline, BindGroupLayout) {
+    texture_bind_group_layout: &BindGroupLayout,
+    sample_count: u32,
+    front_face: FrontFace,
+    cull_mode: CullMode,
+    polygon_mode: PolygonMode,
+    blend_modes: [BlendMode; N],
+) -> [RenderPipeline; N] {
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: Some("Shader"),
         source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
     });
 
-    let texture_bind_group_layout =
-        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        multisampled: false,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
-                    count: None,
-                },
-            ],
-            label: Some("bind_group_layout"),
-        });
-
     let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Render Pipeline Layout"),
-        bind_group_layouts: &[&texture_bind_group_layout],
+        bind_group_layouts: &[texture_bind_group_layout],
         push_constant_ranges: &[],
     });
 
-    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("2d_render_pipeline"),
-        layout: Some(&render_pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: &shader,
-            entry_point: "vs_main",
-            buffers: &[
-                wgpu::VertexBufferLayout {
-                    array_stride: 4 * std::mem::size_of::<f32>() as u64,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &[
-                        wgpu::VertexAttribute {
-                            format: wgpu::VertexFormat::Float32x2,
-                            offset: 0,
-                            shader_location: 0,
-                        },
-                        wgpu::VertexAttribute {
-                            format: wgpu::VertexFormat::Float32x2,
-                            offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
-                            shader_location: 1,
-                        },
-                    ],
-                },
-                wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<SpriteInstance>() as wgpu::BufferAddress,
-                    step_mode: wgpu::VertexStepMode::Instance,
-                    attributes: &[
-                        wgpu::VertexAttribute {
-                            offset: 0,
-                            shader_location: 2,
-                            format: wgpu::VertexFormat::Float32x3,
-                        },
-                        wgpu::VertexAttribute {
-                            offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                            shader_location: 3,
-                            format: wgpu::VertexFormat::Float32x3,
-                        },
-                        wgpu::VertexAttribute {
-                            offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
-                            shader_location: 4,
-                            format: wgpu::VertexFormat::Float32x3,
-                        },
-                        wgpu::VertexAttribute {
-                            offset: std::mem::size_of::<[f32; 9]>() as wgpu::BufferAddress,
-                            shader_location: 5,
-                            format: wgpu::VertexFormat::Float32x2,
-                        },
-                        wgpu::VertexAttribute {
-                            offset: std::mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
-                            shader_location: 6,
-                            format: wgpu::VertexFormat::Float32x2,
-                        },
-                        wgpu::VertexAttribute {
-                            offset: std::mem::size_of::<[f32; 13]>() as wgpu::BufferAddress,
-                            shader_location: 7,
-                            format: wgpu::VertexFormat::Float32x3,
-                        },
-                        wgpu::VertexAttribute {
-                            offset: std::mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
-                            shader_location: 8,
-                            format: wgpu::VertexFormat::Float32,
-                        },
-                    ],
-                },
-            ],
-            compilation_options: PipelineCompilationOptions::default(),
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &shader,
-            entry_point: "fs_main",
-            targets: &[Some(wgpu::ColorTargetState {
-                format: surface_texture_format,
-                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-            compilation_options: PipelineCompilationOptions::default(),
-        }),
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            polygon_mode: wgpu::PolygonMode::Fill,
-            unclipped_depth: false,
-            conservative: false,
-        },
-        depth_stencil: Some(wgpu::DepthStencilState {
-            format: wgpu::TextureFormat::Depth32Float,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        }),
-        multisample: wgpu::MultisampleState {
-            count: 1,
-            mask: !0,
-            alpha_to_coverage_enabled: false,
-        },
-        multiview: None,
-    });
-
-    (render_pipeline, texture_bind_group_layout)
+    // Pipelines only differ by their blend state
+    blend_modes.map(|blend_mode| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("2d_render_pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    wgpu::VertexBufferLayout {
+                        array_stride: 4 * std::mem::size_of::<f32>() as u64,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &[
+                            wgpu::VertexAttribute {
+                                format: wgpu::VertexFormat::Float32x2,
+                                offset: 0,
+                                shader_location: 0,
+                            },
+                            wgpu::VertexAttribute {
+                                format: wgpu::VertexFormat::Float32x2,
+                                offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                                shader_location: 1,
+                            },
+                        ],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<SpriteInstance>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &[
+                            wgpu::VertexAttribute {
+                                offset: 0,
+                                shader_location: 2,
+                                format: wgpu::VertexFormat::Float32x3,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                                shader_location: 3,
+                                format: wgpu::VertexFormat::Float32x3,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                                shader_location: 4,
+                                format: wgpu::VertexFormat::Float32x3,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 9]>() as wgpu::BufferAddress,
+                                shader_location: 5,
+                                format: wgpu::VertexFormat::Float32x2,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
+                                shader_location: 6,
+                                format: wgpu::VertexFormat::Float32x2,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 13]>() as wgpu::BufferAddress,
+                                shader_location: 7,
+                                format: wgpu::VertexFormat::Float32x4,
+                            },
+                            // Z index, then repeat, as there are not enough locations for both
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 17]>() as wgpu::BufferAddress,
+                                shader_location: 8,
+                                format: wgpu::VertexFormat::Float32x3,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                                shader_location: 9,
+                                format: wgpu::VertexFormat::Uint32x2,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 22]>() as wgpu::BufferAddress,
+                                shader_location: 10,
+                                format: wgpu::VertexFormat::Float32x4,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 26]>() as wgpu::BufferAddress,
+                                shader_location: 11,
+                                format: wgpu::VertexFormat::Float32,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 27]>() as wgpu::BufferAddress,
+                                shader_location: 12,
+                                format: wgpu::VertexFormat::Float32x4,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 31]>() as wgpu::BufferAddress,
+                                shader_location: 13,
+                                format: wgpu::VertexFormat::Float32x2,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 33]>() as wgpu::BufferAddress,
+                                shader_location: 14,
+                                format: wgpu::VertexFormat::Float32x3,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 36]>() as wgpu::BufferAddress,
+                                shader_location: 15,
+                                format: wgpu::VertexFormat::Float32x4,
+                            },
+                        ],
+                    },
+                ],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_texture_format,
+                    blend: Some(blend_mode.blend_state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face,
+                cull_mode: cull_mode.face(),
+                polygon_mode,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    })
 }
 
 fn create_quad_vertex_buf(device: &Device) -> (Buffer, Buffer) {
@@ -352,10 +1659,12 @@ fn create_quad_vertex_buf(device: &Device) -> (Buffer, Buffer) {
     (vertex_buffer, index_buffer)
 }
 
-fn create_sprite_instance_buf(device: &Device) -> Buffer {
+fn create_sprite_instance_buf(device: &Device, config: SpriteRendererConfig) -> Buffer {
     let bufdesc = BufferDescriptor {
         label: Some("Sprite instance buffer"),
-        size: MAX_SPRITES_PER_BATCH * MAX_BATCHES * std::mem::size_of::<SpriteInstance>() as u64,
+        size: config.max_sprites_per_batch
+            * config.max_batches
+            * std::mem::size_of::<SpriteInstance>() as u64,
         usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         mapped_at_creation: false,
     };
@@ -366,6 +1675,7 @@ fn create_sprite_instance_buf(device: &Device) -> Buffer {
 pub fn create_depth_texture(
     device: &wgpu::Device,
     (width, height): (u32, u32),
+    sample_count: u32,
 ) -> (Texture, TextureView, Sampler) {
     let size = wgpu::Extent3d {
         width,
@@ -376,7 +1686,7 @@ pub fn create_depth_texture(
         label: Some("Depth texture"),
         size,
         mip_level_count: 1,
-        sample_count: 1,
+        sample_count,
         dimension: wgpu::TextureDimension::D2,
         format: wgpu::TextureFormat::Depth32Float,
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
@@ -401,8 +1711,143 @@ pub fn create_depth_texture(
     (texture, view, sampler)
 }
 
-fn compute_proj_matrix((w, h): (u32, u32)) -> Matrix3<f32> {
-    let (w, h) = (w as f32, h as f32);
-    let (x, y) = if w < h { (1.0, w / h) } else { (h / w, 1.0) };
-    Matrix3::from_nonuniform_scale(x, y)
+/// Multisampled color texture sprites are drawn to before being resolved to the target,
+/// `None` without multisampling.
+pub(crate) fn create_msaa_texture(
+    ctx: &GraphicsCtx,
+    (width, height): (u32, u32),
+    sample_count: u32,
+) -> Option<TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+    let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Multisampled texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: ctx.surface_texture_format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+/// Highest sample count up to `requested` supported by both the color and depth formats
+fn supported_sample_count(ctx: &GraphicsCtx, requested: u32) -> u32 {
+    let color = ctx
+        .adapter
+        .get_texture_format_features(ctx.surface_texture_format);
+    let depth = ctx
+        .adapter
+        .get_texture_format_features(wgpu::TextureFormat::Depth32Float);
+    let supported = [8, 4, 2, 1]
+        .into_iter()
+        .filter(|&count| count <= requested)
+        .find(|&count| {
+            color.flags.sample_count_supported(count) && depth.flags.sample_count_supported(count)
+        })
+        .unwrap_or(1);
+    if supported != requested {
+        warn!(
+            "MSAA sample count {} is not supported, falling back to {}!",
+            requested, supported
+        );
+    }
+    supported
+}
+
+/// Converts a `position` in pixels from the top left corner of a window of `window_size` pixels,
+/// such as a cursor position, to the space sprites are drawn in with [`Projection::Normalized`]
+/// and y going upward, before the camera is applied.
+pub fn screen_to_normalized(position: (f64, f64), window_size: (u32, u32)) -> Vector2<f32> {
+    let (w, h) = (window_size.0 as f32, window_size.1 as f32);
+    let clip = Vector2::new(
+        2. * position.0 as f32 / w - 1.,
+        1. - 2. * position.1 as f32 / h,
+    );
+    let proj = SpriteRendererConfig::default().proj_matrix(window_size);
+    Vector2::new(clip.x / proj.x.x, clip.y / proj.y.y)
+}
+
+/// Inverse of [`world_to_screen`], `None` if `proj_view` is not invertible
+fn screen_to_world(
+    proj_view: Matrix3<f32>,
+    (x, y, w, h): (f32, f32, f32, f32),
+    position: (f32, f32),
+) -> Option<Vector2<f32>> {
+    let clip = Vector3::new(
+        2. * (position.0 - x) / w - 1.,
+        1. - 2. * (position.1 - y) / h,
+        1.,
+    );
+    let world = proj_view.invert()? * clip;
+    Some(Vector2::new(world.x / world.z, world.y / world.z))
+}
+
+/// Pixel from the top left corner of the window `position` is drawn at through `proj_view`,
+/// within the `(x, y, width, height)` viewport
+fn world_to_screen(
+    proj_view: Matrix3<f32>,
+    (x, y, w, h): (f32, f32, f32, f32),
+    position: (f32, f32),
+) -> (f32, f32) {
+    let clip = proj_view * Vector3::new(position.0, position.1, 1.);
+    let clip = clip.truncate() / clip.z;
+    (x + (clip.x + 1.) * w / 2., y + (1. - clip.y) * h / 2.)
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, Matrix2};
+
+    use super::*;
+
+    const VIEWPORT: (f32, f32, f32, f32) = (10., 20., 800., 600.);
+
+    fn camera() -> Matrix3<f32> {
+        let proj = SpriteRendererConfig::default().proj_matrix((820, 620));
+        let view = Matrix3::from_translation(Vector2::new(0.25, -0.5))
+            * Matrix3::from(Matrix2::from_angle(Deg(30.)))
+            * Matrix3::from_scale(2.);
+        proj * view
+    }
+
+    #[test]
+    fn world_to_screen_round_trips() {
+        for world in [(0., 0.), (0.3, -0.7), (-1.5, 2.)] {
+            let screen = world_to_screen(camera(), VIEWPORT, world);
+            let back = screen_to_world(camera(), VIEWPORT, screen).unwrap();
+            assert!((back.x - world.0).abs() < 1e-4, "{world:?} -> {back:?}");
+            assert!((back.y - world.1).abs() < 1e-4, "{world:?} -> {back:?}");
+        }
+    }
+
+    #[test]
+    fn screen_to_world_round_trips() {
+        for screen in [(10., 20.), (410., 320.), (700., 35.)] {
+            let world = screen_to_world(camera(), VIEWPORT, screen).unwrap();
+            let back = world_to_screen(camera(), VIEWPORT, world.into());
+            assert!((back.0 - screen.0).abs() < 1e-2, "{screen:?} -> {back:?}");
+            assert!((back.1 - screen.1).abs() < 1e-2, "{screen:?} -> {back:?}");
+        }
+    }
+
+    #[test]
+    fn viewport_center_is_the_camera_origin() {
+        let proj = SpriteRendererConfig::default().proj_matrix((820, 620));
+        let world = screen_to_world(proj, VIEWPORT, (410., 320.)).unwrap();
+        assert!(world.x.abs() < 1e-6 && world.y.abs() < 1e-6, "{world:?}");
+    }
+
+    #[test]
+    fn flat_cameras_have_no_world_position() {
+        let camera = camera() * Matrix3::from_scale(0.);
+        assert_eq!(screen_to_world(camera, VIEWPORT, (0., 0.)), None);
+    }
 }
@@ -11,16 +11,46 @@ struct InstanceInput {
     @location(4) model_mat_2: vec3<f32>,
     @location(5) tex_pos: vec2<f32>,
     @location(6) tex_dims: vec2<f32>,
-    @location(7) tint: vec3<f32>,
-    @location(8) z_index: f32,
+    @location(7) tint: vec4<f32>,
+    // Z index, then repeat
+    @location(8) depth_repeat: vec3<f32>,
+    // Tint mode, then shape
+    @location(9) modes: vec2<u32>,
+    @location(10) outline_color: vec4<f32>,
+    @location(11) outline_width: f32,
+    @location(12) gradient_tint: vec4<f32>,
+    @location(13) gradient_direction: vec2<f32>,
+    @location(14) shape_params: vec3<f32>,
+    // Free for custom effects, see `DrawParams::custom`
+    @location(15) custom: vec4<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
-    @location(1) tint: vec3<f32>,
+    @location(1) tint: vec4<f32>,
+    // Position in the repeated sprite, wrapped against the texture rect in the fragment shader
+    @location(2) repeat_coords: vec2<f32>,
+    @location(3) @interpolate(flat) tex_pos: vec2<f32>,
+    @location(4) @interpolate(flat) tex_dims: vec2<f32>,
+    @location(5) @interpolate(flat) repeated: u32,
+    @location(6) @interpolate(flat) tint_mode: u32,
+    @location(7) @interpolate(flat) outline_color: vec4<f32>,
+    @location(8) @interpolate(flat) outline_width: f32,
+    @location(9) @interpolate(flat) repeat: vec2<f32>,
+    @location(10) @interpolate(flat) shape: u32,
+    // Position from the center of the shape in world units
+    @location(11) shape_coords: vec2<f32>,
+    @location(12) @interpolate(flat) shape_params: vec3<f32>,
+    @location(13) @interpolate(flat) custom: vec4<f32>,
 };
 
+const TINT_REPLACE: u32 = 1u;
+const TINT_REPLACE_PREMULTIPLIED: u32 = 2u;
+const SHAPE_ROUNDED_RECT: u32 = 1u;
+// Matches `Outline::MAX_WIDTH`
+const MAX_OUTLINE_WIDTH: i32 = 8;
+
 
 @vertex
 fn vs_main(
@@ -28,13 +58,45 @@ fn vs_main(
     instance: InstanceInput,
 ) -> VertexOutput {
     var out: VertexOutput;
+    let z_index = instance.depth_repeat.x;
+    let repeat = instance.depth_repeat.yz;
     let model_matrix = mat3x3<f32>(
         instance.model_mat_0, instance.model_mat_1, instance.model_mat_2
     );
-    out.tex_coords = instance.tex_pos + model.tex_coords * instance.tex_dims;
-    let pos = model_matrix * vec3<f32>(model.position, 1.0);
-    out.clip_position = vec4<f32>(pos.xy, instance.z_index / 100.0, 1.0);
-    out.tint = instance.tint;
+    // Outlined quads are grown by the outline width on every side, the texture coordinates
+    // outside of the sprite are then transparent
+    var quad_coords = model.tex_coords;
+    var position = model.position;
+    if instance.outline_width > 0.0 {
+        let sprite_texels = abs(instance.tex_dims * repeat) * vec2<f32>(textureDimensions(tex));
+        let margin = instance.outline_width / max(sprite_texels, vec2<f32>(1e-6));
+        quad_coords = quad_coords * (1.0 + 2.0 * margin) - margin;
+        position = position * (1.0 + 2.0 * margin) - margin;
+    }
+    out.tex_coords = instance.tex_pos + quad_coords * instance.tex_dims;
+    out.repeat_coords = quad_coords * repeat;
+    out.tex_pos = instance.tex_pos;
+    out.tex_dims = instance.tex_dims;
+    out.repeated = u32(any(repeat != vec2<f32>(1.0)));
+    out.tint_mode = instance.modes.x;
+    out.shape = instance.modes.y;
+    out.shape_coords = (model.position - 0.5) * 2.0 * instance.shape_params.xy;
+    out.shape_params = instance.shape_params;
+    out.outline_color = instance.outline_color;
+    out.outline_width = instance.outline_width;
+    out.repeat = repeat;
+    out.custom = instance.custom;
+    let pos = model_matrix * vec3<f32>(position, 1.0);
+    out.clip_position = vec4<f32>(pos.xy, z_index, 1.0);
+    // The gradient goes from the tint on the quad corner farthest behind its direction to
+    // the gradient tint on the opposite one
+    let direction = instance.gradient_direction;
+    let extent = abs(direction.x) + abs(direction.y);
+    var gradient = 0.0;
+    if extent > 0.0 {
+        gradient = dot(model.position - 0.5, direction) / extent + 0.5;
+    }
+    out.tint = mix(instance.tint, instance.gradient_tint, gradient);
     
     return out;
 }
@@ -46,6 +108,41 @@ var tex: texture_2d<f32>;
 @group(0)@binding(1)
 var sam: sampler;
 
+// Alpha of the sprite texel at `repeat_coords`, zero outside of the sprite
+fn sprite_alpha(in: VertexOutput, repeat_coords: vec2<f32>) -> f32 {
+    if any(repeat_coords < vec2<f32>(0.0)) || any(repeat_coords > in.repeat) {
+        return 0.0;
+    }
+    let tex_coords = in.tex_pos + fract(repeat_coords) * in.tex_dims;
+    return textureSampleLevel(tex, sam, tex_coords, 0.0).a;
+}
+
+// Whether an opaque texel of the sprite is within the outline width
+fn near_sprite(in: VertexOutput) -> bool {
+    let sprite_texels = abs(in.tex_dims) * vec2<f32>(textureDimensions(tex));
+    let texel = 1.0 / max(sprite_texels, vec2<f32>(1e-6));
+    let width = min(i32(ceil(in.outline_width)), MAX_OUTLINE_WIDTH);
+    for (var x = -width; x <= width; x++) {
+        for (var y = -width; y <= width; y++) {
+            let offset = vec2<f32>(f32(x), f32(y));
+            if length(offset) > in.outline_width {
+                continue;
+            }
+            if sprite_alpha(in, in.repeat_coords + offset * texel) > 0.0 {
+                return true;
+            }
+        }
+    }
+    return false;
+}
+
+// Signed distance from `p` to the edge of the rectangle of `half_size` with rounded corners,
+// negative inside
+fn rounded_rect_distance(p: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+    let q = abs(p) - half_size + radius;
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+}
+
 struct FragmentOutput {
     @location(0) rgba: vec4<f32>,
     @builtin(frag_depth) depth: f32,
@@ -53,10 +150,40 @@ struct FragmentOutput {
 
 @fragment
 fn fs_main(in: VertexOutput, ) -> FragmentOutput {
-    var sample: vec4<f32> = textureSample(tex, sam, in.tex_coords);
-    var rgb: vec3<f32> = sample.xyz * in.tint;
+    // The atlas holds other sprites around this one, so repeating can't use the sampler address mode
+    var tex_coords = in.tex_coords;
+    if in.repeated != 0u {
+        tex_coords = in.tex_pos + fract(in.repeat_coords) * in.tex_dims;
+    }
+    // Gradients of the unwrapped coordinates avoid picking the smallest mip level on the seams
+    let unwrapped = in.tex_pos + in.repeat_coords * in.tex_dims;
+    var sample: vec4<f32> = textureSampleGrad(tex, sam, tex_coords, dpdx(unwrapped), dpdy(unwrapped));
+    let outside = any(in.repeat_coords < vec2<f32>(0.0)) || any(in.repeat_coords > in.repeat);
+    if in.outline_width > 0.0 && outside {
+        sample = vec4<f32>(0.0);
+    }
+    var rgba: vec4<f32> = sample * in.tint;
+    if in.tint_mode == TINT_REPLACE {
+        rgba = vec4<f32>(in.tint.rgb, rgba.a);
+    } else if in.tint_mode == TINT_REPLACE_PREMULTIPLIED {
+        rgba = vec4<f32>(in.tint.rgb * sample.a, rgba.a);
+    }
+    // Derivatives must be computed outside of the shape branch
+    let distance = rounded_rect_distance(in.shape_coords, in.shape_params.xy, in.shape_params.z);
+    let distance_width = max(fwidth(distance), 1e-6);
+    if in.shape == SHAPE_ROUNDED_RECT {
+        let coverage = clamp(0.5 - distance / distance_width, 0.0, 1.0);
+        sample.a *= coverage;
+        rgba.a *= coverage;
+    }
+    var alpha = sample.a;
+    // The outline covers the transparent texels next to the opaque ones
+    if in.outline_width > 0.0 && sample.a == 0.0 && near_sprite(in) {
+        rgba = in.outline_color;
+        alpha = in.outline_color.a;
+    }
     var out: FragmentOutput;
-    out.rgba = vec4<f32>(rgb, sample.a);
-    out.depth = in.clip_position.z + (1.0 - sample.a);
+    out.rgba = rgba;
+    out.depth = in.clip_position.z + (1.0 - alpha);
     return out;
 }
\ No newline at end of file
@@ -0,0 +1,36 @@
+use cgmath::Vector2;
+
+/// Axis aligned rectangle, `position` being its bottom left corner
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub position: Vector2<f32>,
+    pub size: Vector2<f32>,
+}
+
+impl Rect {
+    /// Rectangle of `size` from its bottom left corner at `position`
+    pub fn new(position: Vector2<f32>, size: Vector2<f32>) -> Self {
+        Self { position, size }
+    }
+}
+
+/// Direction in which a gradient goes from its first color to its second one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientDirection {
+    /// Along the x axis
+    Horizontal,
+    /// Along the y axis
+    Vertical,
+    /// Along the direction at the given angle in radians, counterclockwise from the x axis
+    Linear(f32),
+}
+
+impl GradientDirection {
+    pub(crate) fn vector(self) -> Vector2<f32> {
+        match self {
+            GradientDirection::Horizontal => Vector2::new(1., 0.),
+            GradientDirection::Vertical => Vector2::new(0., 1.),
+            GradientDirection::Linear(angle) => Vector2::new(angle.cos(), angle.sin()),
+        }
+    }
+}
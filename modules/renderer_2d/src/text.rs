@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+
+use cgmath::Vector2;
+use fontdue::{Font, FontSettings};
+use rgine_graphics::ctx::GraphicsCtx;
+use rgine_logger::warn;
+use wgpu::{BindGroup, BindGroupLayout, Texture};
+
+use crate::texture::{create_bind_group, TextureSampling};
+
+const GLYPH_ATLAS_SIZE: u32 = 1024;
+const GLYPH_PADDING: u32 = 1;
+
+#[derive(Clone)]
+pub struct FontData {
+    pub path: String,
+    /// Size in pixels at which glyphs are rasterized
+    pub px_size: f32,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct FontHandle(pub(crate) usize);
+
+/// Horizontal alignment of the lines of a text within its box
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl TextAlign {
+    /// Offset of a line of `width` in a box of `box_width`
+    fn offset(self, width: f32, box_width: f32) -> f32 {
+        match self {
+            TextAlign::Left => 0.,
+            TextAlign::Center => (box_width - width) / 2.,
+            TextAlign::Right => box_width - width,
+        }
+    }
+}
+
+/// Glyphs laid out in text space, see [`GlyphAtlas::layout`]
+pub(crate) struct TextLayout {
+    /// Glyphs with their pen position, the first baseline being at `y = 0` and the next ones below
+    pub(crate) glyphs: Vec<(Vector2<f32>, Glyph)>,
+    /// Left of the widest line
+    pub(crate) left: f32,
+    /// Width of the widest line and height of the lines, each one being one line height tall
+    pub(crate) size: Vector2<f32>,
+}
+
+/// Glyph metrics in text space, where `1.0` is the font size
+#[derive(Clone, Copy)]
+pub(crate) struct Glyph {
+    pub(crate) tex_pos: Vector2<f32>,
+    pub(crate) tex_dims: Vector2<f32>,
+    pub(crate) offset: Vector2<f32>,
+    pub(crate) size: Vector2<f32>,
+    pub(crate) advance: f32,
+}
+
+struct PendingUpload {
+    position: Vector2<u32>,
+    size: Vector2<u32>,
+    coverage: Vec<u8>,
+}
+
+/// Font rasterized on demand into its own texture, glyphs are cached across frames.
+pub(crate) struct GlyphAtlas {
+    font: Font,
+    px_size: f32,
+    texture: Texture,
+    pub(crate) texture_index: usize,
+    glyphs: HashMap<char, Glyph>,
+    cursor: Vector2<u32>,
+    row_height: u32,
+    pending: Vec<PendingUpload>,
+    /// Whether a glyph didn't fit in the atlas already, to only warn once
+    full: bool,
+}
+
+impl GlyphAtlas {
+    /// Fails if the font file can't be read or parsed
+    pub(crate) fn new(
+        ctx: &GraphicsCtx,
+        data: &FontData,
+        texture_index: usize,
+        sampling: TextureSampling,
+        texture_bind_group_layout: &BindGroupLayout,
+    ) -> Result<(Self, BindGroup), String> {
+        let bytes = std::fs::read(&data.path)
+            .map_err(|e| format!("Unable to load font at {} ! error: {e}", data.path))?;
+        let font = Font::from_bytes(bytes, FontSettings::default())
+            .map_err(|e| format!("Unable to parse font at {} ! error: {e}", data.path))?;
+
+        let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: GLYPH_ATLAS_SIZE,
+                height: GLYPH_ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("2d_glyph_atlas"),
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = create_bind_group(ctx, &view, sampling, texture_bind_group_layout);
+
+        Ok((
+            Self {
+                font,
+                px_size: data.px_size,
+                texture,
+                texture_index,
+                glyphs: HashMap::new(),
+                cursor: Vector2::new(GLYPH_PADDING, GLYPH_PADDING),
+                row_height: 0,
+                pending: Vec::new(),
+                full: false,
+            },
+            bind_group,
+        ))
+    }
+
+    /// Distance between two baselines in text space
+    pub(crate) fn line_height(&self) -> f32 {
+        self.font
+            .horizontal_line_metrics(self.px_size)
+            .map(|m| m.new_line_size / self.px_size)
+            .unwrap_or(1.)
+    }
+
+    /// Distance from the baseline to the top of the highest glyphs in text space
+    pub(crate) fn ascent(&self) -> f32 {
+        self.font
+            .horizontal_line_metrics(self.px_size)
+            .map(|m| m.ascent / self.px_size)
+            .unwrap_or(1.)
+    }
+
+    /// Width of `line` in text space, kerning included
+    pub(crate) fn line_width(&self, line: &str) -> f32 {
+        let mut width = 0.;
+        let mut previous = None;
+        for c in line.chars() {
+            width += self.kern(previous, c)
+                + self.font.metrics(c, self.px_size).advance_width / self.px_size;
+            previous = Some(c);
+        }
+        width
+    }
+
+    fn kern(&self, previous: Option<char>, c: char) -> f32 {
+        previous
+            .and_then(|previous| self.font.horizontal_kern(previous, c, self.px_size))
+            .map_or(0., |kern| kern / self.px_size)
+    }
+
+    /// Splits `text` into lines at `\n`, and between words so that they are not wider than
+    /// `max_width` if given. A word wider than `max_width` gets its own line.
+    pub(crate) fn wrap<'a>(&self, text: &'a str, max_width: Option<f32>) -> Vec<&'a str> {
+        let Some(max_width) = max_width else {
+            return text.split('\n').collect();
+        };
+        let space = self.line_width(" ");
+
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let (mut line_start, mut line_end, mut width, mut words) = (0, 0, 0., 0);
+            let mut word_start = 0;
+            for word in paragraph.split(' ') {
+                let word_width = self.line_width(word);
+                if words > 0 && width + space + word_width > max_width {
+                    lines.push(&paragraph[line_start..line_end]);
+                    (line_start, width, words) = (word_start, 0., 0);
+                }
+                if words > 0 {
+                    width += space;
+                }
+                width += word_width;
+                words += 1;
+                line_end = word_start + word.len();
+                word_start = line_end + 1;
+            }
+            lines.push(&paragraph[line_start..line_end]);
+        }
+        lines
+    }
+
+    /// Lays `lines` out one below the other, aligned within `box_width` or the widest line.
+    pub(crate) fn layout(
+        &mut self,
+        lines: &[&str],
+        align: TextAlign,
+        box_width: Option<f32>,
+    ) -> TextLayout {
+        let line_height = self.line_height();
+        let widths = lines
+            .iter()
+            .map(|line| self.line_width(line))
+            .collect::<Vec<_>>();
+        let widest = widths.iter().copied().fold(0., f32::max);
+        let box_width = box_width.unwrap_or(widest);
+
+        let mut glyphs = Vec::new();
+        for (row, (line, width)) in lines.iter().zip(widths).enumerate() {
+            let mut pen = Vector2::new(align.offset(width, box_width), -(row as f32) * line_height);
+            let mut previous = None;
+            for c in line.chars() {
+                pen.x += self.kern(previous, c);
+                previous = Some(c);
+                let Some(glyph) = self.glyph(c) else {
+                    continue;
+                };
+                if glyph.size.x > 0. && glyph.size.y > 0. {
+                    glyphs.push((pen, glyph));
+                }
+                pen.x += glyph.advance;
+            }
+        }
+
+        TextLayout {
+            glyphs,
+            left: align.offset(widest, box_width),
+            size: Vector2::new(widest, lines.len() as f32 * line_height),
+        }
+    }
+
+    /// Returns the glyph of `c`, rasterizing it if it was never used before.
+    ///
+    /// `None` is returned if the glyph does not fit in the atlas, it is tried again on the
+    /// next use.
+    pub(crate) fn glyph(&mut self, c: char) -> Option<Glyph> {
+        if let Some(glyph) = self.glyphs.get(&c) {
+            return Some(*glyph);
+        }
+        let glyph = self.rasterize(c)?;
+        self.glyphs.insert(c, glyph);
+        Some(glyph)
+    }
+
+    fn rasterize(&mut self, c: char) -> Option<Glyph> {
+        let metrics = self.font.metrics(c, self.px_size);
+        let size = Vector2::new(metrics.width as u32, metrics.height as u32);
+
+        let mut cursor = self.cursor;
+        let mut row_height = self.row_height;
+        if cursor.x + size.x + GLYPH_PADDING > GLYPH_ATLAS_SIZE {
+            cursor = Vector2::new(GLYPH_PADDING, cursor.y + row_height);
+            row_height = 0;
+        }
+        if cursor.y + size.y + GLYPH_PADDING > GLYPH_ATLAS_SIZE {
+            if !std::mem::replace(&mut self.full, true) {
+                warn!("Glyph atlas is full, the character {:?} can't be drawn!", c);
+            }
+            return None;
+        }
+        let (_, coverage) = self.font.rasterize(c, self.px_size);
+        (self.cursor, self.row_height) = (cursor, row_height);
+
+        let position = self.cursor;
+        self.cursor.x += size.x + GLYPH_PADDING;
+        self.row_height = self.row_height.max(size.y + GLYPH_PADDING);
+
+        if size.x > 0 && size.y > 0 {
+            self.pending.push(PendingUpload {
+                position,
+                size,
+                coverage,
+            });
+        }
+
+        let atlas_size = GLYPH_ATLAS_SIZE as f32;
+        Some(Glyph {
+            tex_pos: position.map(|x| x as f32 / atlas_size),
+            tex_dims: size.map(|x| x as f32 / atlas_size),
+            offset: Vector2::new(metrics.xmin as f32, metrics.ymin as f32) / self.px_size,
+            size: size.map(|x| x as f32 / self.px_size),
+            advance: metrics.advance_width / self.px_size,
+        })
+    }
+
+    /// Uploads the glyphs rasterized since the last upload to the atlas texture
+    pub(crate) fn upload(&mut self, ctx: &GraphicsCtx) {
+        for upload in self.pending.drain(..) {
+            // Glyphs are white, their coverage is stored as alpha so that they can be tinted
+            let rgba = upload
+                .coverage
+                .iter()
+                .flat_map(|&a| [255, 255, 255, a])
+                .collect::<Vec<u8>>();
+
+            ctx.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: upload.position.x,
+                        y: upload.position.y,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &rgba,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * upload.size.x),
+                    rows_per_image: Some(upload.size.y),
+                },
+                wgpu::Extent3d {
+                    width: upload.size.x,
+                    height: upload.size.y,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+}
@@ -1,18 +1,291 @@
-use std::path::Path;
+use std::{collections::HashMap, path::Path, sync::Arc};
 
 use cgmath::{Matrix3, SquareMatrix, Vector2};
+use image::{DynamicImage, ImageError, ImageResult, Rgba};
 use rgine_graphics::ctx::GraphicsCtx;
+use rgine_logger::{debug, error, warn};
 use texture_packer::{
-    exporter::ImageExporter, importer::ImageImporter, texture::Texture as _, TexturePacker,
-    TexturePackerConfig,
+    exporter::ImageExporter, texture::Texture as _, TexturePacker, TexturePackerConfig,
 };
-use wgpu::{BindGroup, BindGroupLayout, Texture};
+use wgpu::{BindGroup, BindGroupLayout, Texture, TextureView};
 
+use crate::{
+    decoder::decode_image,
+    mipmaps::{generate_mipmaps, mip_level_count},
+    renderer::{create_depth_texture, create_msaa_texture},
+    text::{FontData, FontHandle, GlyphAtlas},
+};
+
+pub use image::RgbaImage;
 pub use rgine_graphics::color::Color3;
+pub use wgpu::FilterMode;
 
 pub struct Atlas {
-    pub(super) sheets: Vec<SpriteSheet>,
+    /// `None` for the sheets that failed to load, drawn with the placeholder
+    pub(super) sheets: Vec<Option<SpriteSheet>>,
+    /// Index in `sheets` of the textures loaded by the asset server
+    pub(crate) assets: HashMap<u64, usize>,
+    /// The packed atlas pages come first, followed by render targets and glyph atlases
+    pub(crate) textures: Vec<AtlasTexture>,
+    /// `None` for the fonts that failed to load, their text is not drawn
+    pub(crate) fonts: Vec<Option<GlyphAtlas>>,
+    /// Center of a white texel of the first packed atlas page
+    pub(crate) white_texel: Vector2<f32>,
+    /// Checkerboard drawn instead of the sprites of missing sheets, made of a single sprite
+    pub(crate) placeholder: SpriteSheet,
+}
+
+const WHITE_TEXEL_KEY: usize = usize::MAX;
+const PLACEHOLDER_KEY: usize = usize::MAX - 1;
+const PLACEHOLDER_SIZE: u32 = 8;
+
+/// Sprite covering the whole [`Atlas::placeholder`] sheet
+pub(crate) const PLACEHOLDER_SPRITE: Sprite = Sprite {
+    sheet: SpriteSheetHandle(SheetId::Index(usize::MAX)),
+    position: Vector2::new(0, 0),
+    size: Vector2::new(1, 1),
+    unit: SpriteUnit::Cells,
+};
+
+/// Magenta and black checkerboard, hard to miss on purpose
+fn placeholder_image() -> RgbaImage {
+    let cell = PLACEHOLDER_SIZE / 2;
+    RgbaImage::from_fn(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, |x, y| {
+        match (x / cell + y / cell) % 2 {
+            0 => Rgba([255, 0, 255, 255]),
+            _ => Rgba([0, 0, 0, 255]),
+        }
+    })
+}
+
+impl Atlas {
+    /// Adds a sheet in its own texture, leaving the packed atlas and existing handles untouched.
+    pub(crate) fn add_sheet(
+        &mut self,
+        ctx: &GraphicsCtx,
+        image: RgbaImage,
+        sprite_px_size: Vector2<u32>,
+        sampling: TextureSampling,
+        texture_bind_group_layout: &BindGroupLayout,
+    ) -> Result<SpriteSheetHandle, SheetTooLargeError> {
+        let size: Vector2<u32> = image.dimensions().into();
+        check_sheet_size(
+            || format!("#{}", self.sheets.len()),
+            size,
+            ctx.max_texture_size(),
+        )?;
+        let (texture, bind_group) = create_texture(
+            ctx,
+            size,
+            image.into_vec(),
+            sampling,
+            texture_bind_group_layout,
+        );
+
+        self.sheets.push(Some(SpriteSheet {
+            size_px: size,
+            sprite_size_px: sprite_px_size,
+            tex_coords: Vector2 { x: 0., y: 0. },
+            tex_dims: Vector2 { x: 1., y: 1. },
+            texture: self.textures.len(),
+        }));
+        self.textures.push(AtlasTexture {
+            bind_group,
+            image_texture: Some(texture),
+            render_target: None,
+        });
+
+        Ok(SpriteSheetHandle(SheetId::Index(self.sheets.len() - 1)))
+    }
+
+    /// Sheet of `handle`, `None` if it is not loaded (yet)
+    pub(crate) fn sheet(&self, handle: SpriteSheetHandle) -> Option<&SpriteSheet> {
+        let index = match handle.0 {
+            SheetId::Index(index) => index,
+            SheetId::Asset(id) => *self.assets.get(&id)?,
+        };
+        self.sheets.get(index)?.as_ref()
+    }
+
+    /// Uploads `image` over the region of the atlas texture used by the sheet of `handle`.
+    ///
+    /// The image must have the size of the sheet it replaces.
+    #[cfg(feature = "hot_reload")]
+    pub(crate) fn reload_sheet(
+        &self,
+        ctx: &GraphicsCtx,
+        handle: SpriteSheetHandle,
+        image: &RgbaImage,
+    ) -> Result<(), String> {
+        let sheet = self.sheet(handle).ok_or("the sheet is not loaded")?;
+        if Vector2::from(image.dimensions()) != sheet.size_px {
+            return Err(format!(
+                "size changed from {:?} to {:?}, restart to repack the atlas",
+                sheet.size_px,
+                image.dimensions()
+            ));
+        }
+        let Some(texture) = &self.textures[sheet.texture].image_texture else {
+            return Err("the sheet is not backed by an image".to_string());
+        };
+
+        ctx.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: (sheet.tex_coords.x * texture.width() as f32).round() as u32,
+                    y: (sheet.tex_coords.y * texture.height() as f32).round() as u32,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            image.as_raw(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * sheet.size_px.x),
+                rows_per_image: Some(sheet.size_px.y),
+            },
+            wgpu::Extent3d {
+                width: sheet.size_px.x,
+                height: sheet.size_px.y,
+                depth_or_array_layers: 1,
+            },
+        );
+        generate_mipmaps(ctx, texture, texture.format());
+        Ok(())
+    }
+}
+
+/// Reads the image of a sprite sheet from the disk.
+pub fn load_sheet_image(path: &str) -> RgbaImage {
+    try_load_sheet_image(path)
+        .unwrap_or_else(|e| panic!("Unable to load sprite(sheet) at {} ! error: {e}", path))
+}
+
+/// Reads the image of a sprite sheet from the disk, failing if it can't be read or decoded.
+pub fn try_load_sheet_image(path: &str) -> ImageResult<RgbaImage> {
+    Ok(image::open(Path::new(path))?.to_rgba8())
+}
+
+/// Error of a sheet that can't be uploaded in a single texture of the device, its sprites are
+/// drawn with a placeholder
+#[derive(Debug)]
+pub struct SheetTooLargeError {
+    /// Path of the sheet, or `#index` if it was not loaded from a file
+    pub sheet: String,
+    pub size: Vector2<u32>,
+    /// Maximum texture width and height of the device
+    pub max_size: u32,
+}
+
+impl std::fmt::Display for SheetTooLargeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the sprite(sheet) {} is {}x{} pixels, which exceeds the maximum texture size of the device ({}x{})",
+            self.sheet, self.size.x, self.size.y, self.max_size, self.max_size
+        )
+    }
+}
+
+impl std::error::Error for SheetTooLargeError {}
+
+fn check_sheet_size(
+    sheet: impl FnOnce() -> String,
+    size: Vector2<u32>,
+    max_size: u32,
+) -> Result<(), SheetTooLargeError> {
+    if size.x <= max_size && size.y <= max_size {
+        return Ok(());
+    }
+    Err(SheetTooLargeError {
+        sheet: sheet(),
+        size,
+        max_size,
+    })
+}
+
+/// Error of [`SpriteSheetsRegistry::add_from_path`]
+#[derive(Debug)]
+pub struct SheetLoadError {
+    pub path: String,
+    pub error: ImageError,
+}
+
+impl std::fmt::Display for SheetLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unable to load sprite(sheet) at {}: {}",
+            self.path, self.error
+        )
+    }
+}
+
+impl std::error::Error for SheetLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+pub(crate) struct AtlasTexture {
     pub(crate) bind_group: BindGroup,
+    /// Texture sheet images are uploaded to, `None` for render targets and fonts
+    #[cfg_attr(not(feature = "hot_reload"), allow(dead_code))]
+    pub(crate) image_texture: Option<Texture>,
+    pub(crate) render_target: Option<RenderTarget>,
+}
+
+/// Offscreen texture that sprites can be rendered into and that can then be drawn as a sprite
+pub(crate) struct RenderTarget {
+    _texture: Texture,
+    pub(crate) view: TextureView,
+    pub(crate) msaa_view: Option<TextureView>,
+    _depth_texture: Texture,
+    pub(crate) depth_view: TextureView,
+    pub(crate) size: Vector2<u32>,
+}
+
+/// How the textures of the atlas are sampled
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct TextureSampling {
+    pub(crate) filter_mode: FilterMode,
+    /// Mipmaps are only generated if this is set
+    pub(crate) mipmap_filter: Option<FilterMode>,
+}
+
+impl Default for TextureSampling {
+    fn default() -> Self {
+        Self {
+            filter_mode: FilterMode::Nearest,
+            mipmap_filter: None,
+        }
+    }
+}
+
+impl TextureSampling {
+    pub(crate) fn is_filtering(&self) -> bool {
+        self.filter_mode == FilterMode::Linear || self.mipmap_filter == Some(FilterMode::Linear)
+    }
+}
+
+#[derive(Clone)]
+enum SpriteSheetSource {
+    File(SpriteSheetData),
+    /// Already decoded image, shared between the clones of the registry
+    Image(Arc<RgbaImage>, Vector2<u32>),
+    RenderTarget(Vector2<u32>),
+}
+
+impl SpriteSheetSource {
+    /// Name of the sheet `k` in error messages
+    fn name(&self, k: usize) -> String {
+        match self {
+            SpriteSheetSource::File(ssd) => ssd.path.clone(),
+            _ => format!("#{k}"),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -26,46 +299,213 @@ pub struct SpriteSheet {
     pub sprite_size_px: Vector2<u32>,
     tex_coords: Vector2<f32>,
     tex_dims: Vector2<f32>,
+    pub(crate) texture: usize,
 }
 
 impl SpriteSheet {
-    fn normalize_tex_vec(&self, vec: Vector2<u32>) -> Vector2<f32> {
-        let sprite_size_px = self.sprite_size_px.map(|x| x as f32);
-        let sprite_pos = vec.map(|x| x as f32);
-        let size_px = self.size_px.map(|x| x as f32);
+    /// Converts a position or size in `unit` to pixels of the sheet
+    pub(crate) fn px_size(&self, vec: Vector2<u32>, unit: SpriteUnit) -> Vector2<u32> {
+        match unit {
+            SpriteUnit::Cells => self.sprite_size_px.zip(vec, |a, b| a * b),
+            SpriteUnit::Pixels => vec,
+        }
+    }
 
-        let pos_px = sprite_size_px.zip(sprite_pos, |a, b| a * b);
+    fn normalize_tex_vec(&self, vec: Vector2<u32>, unit: SpriteUnit) -> Vector2<f32> {
+        let pos_px = self.px_size(vec, unit).map(|x| x as f32);
+        let size_px = self.size_px.map(|x| x as f32);
         pos_px.zip(size_px, |a, b| a / b)
     }
 
-    pub(crate) fn tex_coords(&self, sprite_coords: Vector2<u32>) -> Vector2<f32> {
+    pub(crate) fn tex_coords(&self, sprite: &Sprite) -> Vector2<f32> {
         self.tex_coords
             + self
-                .normalize_tex_vec(sprite_coords)
+                .normalize_tex_vec(sprite.position, sprite.unit)
                 .zip(self.tex_dims, |a, b| a * b)
     }
 
-    pub(crate) fn tex_dims(&self, sprite_dims: Vector2<u32>) -> Vector2<f32> {
-        self.normalize_tex_vec(sprite_dims)
+    pub(crate) fn tex_dims(&self, sprite: &Sprite) -> Vector2<f32> {
+        self.normalize_tex_vec(sprite.size, sprite.unit)
             .zip(self.tex_dims, |a, b| a * b)
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy)]
-pub struct SpriteSheetHandle(pub(crate) usize);
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub struct SpriteSheetHandle(pub(crate) SheetId);
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub(crate) enum SheetId {
+    /// Index of the sheet in the atlas, in registration order
+    Index(usize),
+    /// Texture loaded by the [`AssetServerModule`](crate::asset_server::AssetServerModule),
+    /// resolved once it is uploaded
+    Asset(u64),
+}
+
+/// Unit of the position and size of a [`Sprite`] within its sheet
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpriteUnit {
+    /// Cells of [`SpriteSheetData::sprite_px_size`] pixels
+    #[default]
+    Cells,
+    /// Pixels from the top left corner of the sheet, for irregularly packed sheets
+    Pixels,
+}
 
 #[derive(Clone)]
 pub struct Sprite {
     pub sheet: SpriteSheetHandle,
     pub position: Vector2<u32>,
     pub size: Vector2<u32>,
+    pub unit: SpriteUnit,
+}
+
+impl Sprite {
+    /// Sprite covering `size` cells of the sheet grid from the cell at `position`
+    pub fn new(sheet: SpriteSheetHandle, position: Vector2<u32>, size: Vector2<u32>) -> Self {
+        Self {
+            sheet,
+            position,
+            size,
+            unit: SpriteUnit::Cells,
+        }
+    }
+
+    /// Sprite covering an arbitrary rectangle of the sheet, in pixels from its top left corner.
+    ///
+    /// Useful for sheets packed by external tools such as TexturePacker.
+    pub fn from_pixel_rect(
+        sheet: SpriteSheetHandle,
+        position_px: Vector2<u32>,
+        size_px: Vector2<u32>,
+    ) -> Self {
+        Self {
+            sheet,
+            position: position_px,
+            size: size_px,
+            unit: SpriteUnit::Pixels,
+        }
+    }
+}
+
+/// How [`DrawParams::tint`] is applied to the sprite texels
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TintMode {
+    /// Multiplies the texel colors by the tint
+    #[default]
+    Multiply,
+    /// Replaces the texel colors by the tint, keeping their alpha. Useful for hit flashes and silhouettes
+    Replace,
+}
+
+/// How a sprite is combined with what is already drawn behind it.
+///
+/// Sprite sheets are uploaded as they are stored, the blend mode must match how their
+/// colors were exported. Glyphs and shapes use straight (not premultiplied) alpha.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Covers what is behind according to the sprite opacity, for textures with straight alpha
+    #[default]
+    Alpha,
+    /// Same as [`BlendMode::Alpha`] for textures whose colors are already multiplied by their
+    /// alpha, which avoids dark fringes on their edges when filtered
+    PremultipliedAlpha,
+    /// Adds the sprite color, weighted by its opacity, useful for glow and fire effects
+    Additive,
+    /// Multiplies what is behind by the sprite color, useful for shadows.
+    /// Opacity is ignored, except that fully transparent texels are not drawn.
+    Multiply,
+}
+
+impl BlendMode {
+    pub(crate) const ALL: [BlendMode; 4] = [
+        BlendMode::Alpha,
+        BlendMode::PremultipliedAlpha,
+        BlendMode::Additive,
+        BlendMode::Multiply,
+    ];
+
+    pub(crate) fn blend_state(self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::PremultipliedAlpha => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            },
+            BlendMode::Multiply => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::Src,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            },
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct DrawParams {
+    /// Transform in world units, which are physical pixels only with [`Projection::Pixels`](crate::renderer::Projection::Pixels)
     pub transform: Matrix3<f32>,
     pub tint: Color3,
+    pub tint_mode: TintMode,
+    /// Opacity multiplied with the sampled texel alpha (1.0 is fully opaque)
+    pub alpha: f32,
+    /// Sprites of lower depth are drawn in front, see [`SpriteRendererConfig::depth_range`](crate::renderer::SpriteRendererConfig::depth_range)
     pub depth: f32,
+    /// Mirrors the sprite texture horizontally
+    pub flip_x: bool,
+    /// Mirrors the sprite texture vertically
+    pub flip_y: bool,
+    /// Clipping rectangle `(x, y, width, height)` in physical pixels from the top left corner
+    /// of the target viewport, the sprite is not clipped if `None`
+    pub clip: Option<(u32, u32, u32, u32)>,
+    pub blend_mode: BlendMode,
+    /// Point of the sprite placed at the origin of `transform`, normalized from its bottom left
+    /// corner: `(0.5, 0.5)` rotates and scales the sprite around its center
+    pub origin: (f32, f32),
+    /// Times the sprite is repeated across the quad along each axis, to cover large areas with a
+    /// tileable texture in a single instance. Fractional repeats cut the last repetition
+    pub repeat: (f32, f32),
+    /// Outline drawn around the opaque texels of the sprite, see [`Outline`]
+    pub outline: Option<Outline>,
+    /// Values passed as is to the shader in `InstanceInput::custom`, left for custom effects
+    /// such as a dissolve progress. The default shader ignores them
+    pub custom: [f32; 4],
+}
+
+/// Stroke around the opaque texels of a sprite, useful for hover and selection feedback.
+///
+/// The sprite quad is grown by `width` so that the outline is not cut on its edges.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outline {
+    /// Outline opacity is [`DrawParams::alpha`]
+    pub color: Color3,
+    /// Thickness in texels of the sprite, up to [`Outline::MAX_WIDTH`]
+    pub width: f32,
+}
+
+impl Outline {
+    /// Thicker outlines are clamped, each outlined pixel samples up to `(2 * MAX_WIDTH + 1)²` texels
+    pub const MAX_WIDTH: f32 = 8.;
+
+    pub fn new(color: Color3, width: f32) -> Self {
+        Self { color, width }
+    }
 }
 
 impl Default for DrawParams {
@@ -73,95 +513,441 @@ impl Default for DrawParams {
         Self {
             depth: 0.,
             tint: Color3::WHITE,
+            tint_mode: TintMode::Multiply,
+            alpha: 1.,
+            flip_x: false,
+            flip_y: false,
+            clip: None,
+            blend_mode: BlendMode::Alpha,
+            origin: (0., 0.),
+            repeat: (1., 1.),
+            outline: None,
+            custom: [0.; 4],
             transform: Matrix3::identity(),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct SpriteSheetsRegistry {
-    to_load: Vec<SpriteSheetData>,
+    to_load: Vec<SpriteSheetSource>,
+    fonts_to_load: Vec<FontData>,
+    /// Sprites looked up by name, see [`SpriteSheetsRegistry::sprite`]
+    sprites: HashMap<String, Sprite>,
+    pub(crate) sampling: TextureSampling,
 }
 
 impl SpriteSheetsRegistry {
     pub fn new() -> Self {
         Self {
             to_load: Vec::new(),
+            fonts_to_load: Vec::new(),
+            sprites: HashMap::new(),
+            sampling: TextureSampling::default(),
+        }
+    }
+
+    /// Names `sprite` so that it can be retrieved with [`SpriteSheetsRegistry::sprite`],
+    /// replacing the sprite previously given the same name.
+    pub fn name_sprite(&mut self, name: impl Into<String>, sprite: Sprite) {
+        let name = name.into();
+        if self.sprites.contains_key(&name) {
+            warn!("The sprite name \"{name}\" is already in use, it now refers to another sprite");
+        }
+        self.sprites.insert(name, sprite);
+    }
+
+    /// Sprite given `name` with [`SpriteSheetsRegistry::name_sprite`], or when its sheet was
+    /// registered. Looking sprites up by name is slower than keeping the [`Sprite`] around.
+    ///
+    /// Panics if no sprite has this name, see [`SpriteSheetsRegistry::try_sprite`].
+    pub fn sprite(&self, name: &str) -> Sprite {
+        self.try_sprite(name)
+            .unwrap_or_else(|| panic!("Requested sprite \"{name}\" doesn't exist!"))
+    }
+
+    /// Sprite given `name`, like [`SpriteSheetsRegistry::sprite`] without panicking
+    pub fn try_sprite(&self, name: &str) -> Option<Sprite> {
+        self.sprites.get(name).cloned()
+    }
+
+    /// Registers the image file like [`SpriteSheetsRegistry::register`], naming its sprites
+    /// given as `(name, position, size)` in cells of `spritesheet_data.sprite_px_size`.
+    pub fn register_named<'a>(
+        &mut self,
+        spritesheet_data: SpriteSheetData,
+        sprites: impl IntoIterator<Item = (&'a str, Vector2<u32>, Vector2<u32>)>,
+    ) -> SpriteSheetHandle {
+        let sheet = self.register(spritesheet_data);
+        for (name, position, size) in sprites {
+            self.name_sprite(name, Sprite::new(sheet, position, size));
         }
+        sheet
+    }
+
+    /// Sets how the atlas texture is sampled, `Nearest` (default) keeps pixel art crisp.
+    pub fn set_filter_mode(&mut self, filter_mode: FilterMode) {
+        self.sampling.filter_mode = filter_mode;
     }
 
+    /// Enables mipmaps generation for the packed atlas, sampled using `mipmap_filter`.
+    ///
+    /// Mipmaps prevent aliasing when drawing sprites smaller than their source resolution,
+    /// they are disabled by default. Note that sheets are packed without padding so neighbouring
+    /// sheets slightly bleed into each other at the smallest mip levels.
+    pub fn set_mipmap_filter(&mut self, mipmap_filter: Option<FilterMode>) {
+        self.sampling.mipmap_filter = mipmap_filter;
+    }
+
+    /// Registers the image file at `spritesheet_data.path`, it is decoded when the atlas is built.
     pub fn register(&mut self, spritesheet_data: SpriteSheetData) -> SpriteSheetHandle {
-        self.to_load.push(spritesheet_data);
-        SpriteSheetHandle(self.to_load.len() - 1)
+        self.to_load.push(SpriteSheetSource::File(spritesheet_data));
+        SpriteSheetHandle(SheetId::Index(self.to_load.len() - 1))
+    }
+
+    /// Reads and decodes the image file at `spritesheet_data.path` right away, unlike
+    /// [`SpriteSheetsRegistry::register`] which only fails when the atlas is built.
+    pub fn add_from_path(
+        &mut self,
+        spritesheet_data: SpriteSheetData,
+    ) -> Result<SpriteSheetHandle, SheetLoadError> {
+        let image =
+            try_load_sheet_image(&spritesheet_data.path).map_err(|error| SheetLoadError {
+                path: spritesheet_data.path.clone(),
+                error,
+            })?;
+        Ok(self.register_image(image, spritesheet_data.sprite_px_size))
+    }
+
+    /// Registers an already decoded sheet image.
+    pub fn register_image(
+        &mut self,
+        image: RgbaImage,
+        sprite_px_size: Vector2<u32>,
+    ) -> SpriteSheetHandle {
+        self.to_load
+            .push(SpriteSheetSource::Image(Arc::new(image), sprite_px_size));
+        SpriteSheetHandle(SheetId::Index(self.to_load.len() - 1))
+    }
+
+    /// Decodes and registers an encoded sheet image, such as the content of a PNG file.
+    pub fn register_bytes(
+        &mut self,
+        bytes: &[u8],
+        sprite_px_size: Vector2<u32>,
+    ) -> ImageResult<SpriteSheetHandle> {
+        let image = image::load_from_memory(bytes)?.to_rgba8();
+        Ok(self.register_image(image, sprite_px_size))
+    }
+
+    /// Sheets registered from an image file, with their path
+    #[cfg(feature = "hot_reload")]
+    pub(crate) fn file_sheets(&self) -> impl Iterator<Item = (SpriteSheetHandle, &str)> {
+        self.to_load
+            .iter()
+            .enumerate()
+            .filter_map(|(k, source)| match source {
+                SpriteSheetSource::File(ssd) => {
+                    Some((SpriteSheetHandle(SheetId::Index(k)), ssd.path.as_str()))
+                }
+                _ => None,
+            })
+    }
+
+    /// Registers an offscreen texture of `size_px` pixels that sprites can be rendered into.
+    ///
+    /// The returned handle is a sheet made of a single sprite covering the whole texture.
+    pub fn register_render_target(&mut self, size_px: Vector2<u32>) -> SpriteSheetHandle {
+        self.to_load.push(SpriteSheetSource::RenderTarget(size_px));
+        SpriteSheetHandle(SheetId::Index(self.to_load.len() - 1))
+    }
+
+    /// Registers a font, its glyphs are rasterized into their own texture when first drawn.
+    ///
+    /// A font file that can't be read or parsed is logged once loaded, its text is not drawn.
+    pub fn register_font(&mut self, font_data: FontData) -> FontHandle {
+        self.fonts_to_load.push(font_data);
+        FontHandle(self.fonts_to_load.len() - 1)
     }
 
     pub(super) fn build_atlas(
         self,
         ctx: &GraphicsCtx,
         texture_bind_group_layout: &BindGroupLayout,
+        sample_count: u32,
     ) -> Atlas {
-        let mut packer = TexturePacker::new_skyline(TexturePackerConfig {
-            max_width: 4096,
-            max_height: 4096,
-            texture_padding: 0,
-            trim: false,
-            allow_rotation: false,
-            ..Default::default()
-        });
+        let max_size = ctx.max_texture_size();
+        let new_page = || {
+            TexturePacker::new_skyline(TexturePackerConfig {
+                max_width: max_size,
+                max_height: max_size,
+                texture_padding: 0,
+                trim: false,
+                allow_rotation: false,
+                ..Default::default()
+            })
+        };
+
+        // Files are decoded in parallel by the decoding threads
+        let decoding = self
+            .to_load
+            .iter()
+            .enumerate()
+            .filter_map(|(k, source)| match source {
+                SpriteSheetSource::File(ssd) => Some((k, decode_image(ssd.path.clone()))),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut images = self
+            .to_load
+            .iter()
+            .enumerate()
+            .filter_map(|(k, source)| match source {
+                SpriteSheetSource::Image(image, _) => {
+                    Some((k, DynamicImage::ImageRgba8(RgbaImage::clone(image))))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for (k, decoding) in decoding {
+            match decoding.wait() {
+                Ok(image) => images.push((k, DynamicImage::ImageRgba8(image))),
+                Err(e) => error!(
+                    "Unable to load sprite(sheet) at {} ! error: {e}, a placeholder is drawn instead",
+                    self.to_load[k].name(k)
+                ),
+            }
+        }
 
-        let images = self.to_load.iter().enumerate().map(|(k, ssd)| {
-            (
-                k,
-                ImageImporter::import_from_file(Path::new(&ssd.path)).unwrap_or_else(|e| {
-                    panic!("Unable to load sprite(sheet) at {} ! error: {e}", ssd.path)
-                }),
+        // Untextured shapes sample this texel so that they can be batched with sprites,
+        // it is packed first so that it always lands in the first page, with the placeholder.
+        let mut pages = vec![new_page()];
+        pages[0]
+            .pack_own(
+                WHITE_TEXEL_KEY,
+                DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([255; 4]))),
             )
-        });
+            .expect("Failed to pack white texel into global atlas!");
+        pages[0]
+            .pack_own(
+                PLACEHOLDER_KEY,
+                DynamicImage::ImageRgba8(placeholder_image()),
+            )
+            .expect("Failed to pack placeholder into global atlas!");
+
+        // The skyline packer wastes less space when the tallest sheets are placed first
+        images.sort_by_key(|(_, img)| std::cmp::Reverse((img.height(), img.width())));
 
-        images.for_each(|(k, img)| {
-            packer
+        // Sheets that don't fit in the current page spill into a new atlas texture
+        for (k, img) in images {
+            let size = Vector2::new(img.width(), img.height());
+            if let Err(e) = check_sheet_size(|| self.to_load[k].name(k), size, max_size) {
+                error!("{e}, a placeholder is drawn instead");
+                continue;
+            }
+            if !pages.last().unwrap().can_pack(&img) {
+                debug!(
+                    "Global atlas page {} is full, {} starts a new one",
+                    pages.len() - 1,
+                    self.to_load[k].name(k)
+                );
+                pages.push(new_page());
+            }
+            pages
+                .last_mut()
+                .unwrap()
                 .pack_own(k, img)
-                .expect("Failed to pack sprite(sheet) into global atlas!")
-        });
+                .unwrap_or_else(|_| {
+                    panic!(
+                        "Failed to pack sprite(sheet) {} into global atlas!",
+                        self.to_load[k].name(k)
+                    )
+                });
+        }
 
         let mut sheets = vec![None; self.to_load.len()];
+        let mut white_texel = Vector2::new(0., 0.);
+        let mut placeholder = None;
+        let mut textures = Vec::with_capacity(pages.len());
 
-        packer.get_frames().into_iter().for_each(|(k, sheet)| {
-            sheets[*k] = Some(SpriteSheet {
-                size_px: Vector2 {
-                    x: sheet.frame.w,
-                    y: sheet.frame.h,
-                },
-                sprite_size_px: self.to_load.get(*k).unwrap().sprite_px_size,
-                tex_coords: Vector2 {
-                    x: sheet.frame.x as f32 / packer.width() as f32,
-                    y: sheet.frame.y as f32 / packer.height() as f32,
-                },
-                tex_dims: Vector2 {
-                    x: sheet.frame.w as f32 / packer.width() as f32,
-                    y: sheet.frame.h as f32 / packer.height() as f32,
-                },
+        for (page, packer) in pages.iter().enumerate() {
+            let used_area: u64 = packer
+                .get_frames()
+                .values()
+                .map(|f| f.frame.w as u64 * f.frame.h as u64)
+                .sum();
+            let page_area = packer.width() as u64 * packer.height() as u64;
+            debug!(
+                "Global atlas page {page} is {}x{} with {} sheet(s), packing efficiency: {:.1}%",
+                packer.width(),
+                packer.height(),
+                packer
+                    .get_frames()
+                    .keys()
+                    .filter(|k| **k != WHITE_TEXEL_KEY && **k != PLACEHOLDER_KEY)
+                    .count(),
+                used_area as f64 / page_area.max(1) as f64 * 100.
+            );
+
+            packer.get_frames().iter().for_each(|(k, sheet)| {
+                if *k == WHITE_TEXEL_KEY {
+                    white_texel = Vector2 {
+                        x: (sheet.frame.x as f32 + 0.5) / packer.width() as f32,
+                        y: (sheet.frame.y as f32 + 0.5) / packer.height() as f32,
+                    };
+                    return;
+                }
+                let sprite_size_px = match self.to_load.get(*k) {
+                    Some(SpriteSheetSource::File(ssd)) => ssd.sprite_px_size,
+                    Some(SpriteSheetSource::Image(_, sprite_px_size)) => *sprite_px_size,
+                    Some(SpriteSheetSource::RenderTarget(_)) => unreachable!(),
+                    None => Vector2::new(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE),
+                };
+                let sheet = Some(SpriteSheet {
+                    size_px: Vector2 {
+                        x: sheet.frame.w,
+                        y: sheet.frame.h,
+                    },
+                    sprite_size_px,
+                    tex_coords: Vector2 {
+                        x: sheet.frame.x as f32 / packer.width() as f32,
+                        y: sheet.frame.y as f32 / packer.height() as f32,
+                    },
+                    tex_dims: Vector2 {
+                        x: sheet.frame.w as f32 / packer.width() as f32,
+                        y: sheet.frame.h as f32 / packer.height() as f32,
+                    },
+                    texture: page,
+                });
+                match *k {
+                    PLACEHOLDER_KEY => placeholder = sheet,
+                    k => sheets[k] = sheet,
+                }
             });
-        });
 
-        let sheets = sheets.into_iter().map(|s| s.unwrap()).collect();
+            let image = ImageExporter::export(packer, None)
+                .expect("An error occured while exporting global atlas!")
+                .to_rgba8();
+            let size: Vector2<u32> = image.dimensions().into();
 
-        let image = ImageExporter::export(&packer, None)
-            .expect("An error occured while exporting global atlas!")
-            .to_rgba8();
-        let size: Vector2<u32> = image.dimensions().into();
+            let (texture, bind_group) = create_texture(
+                ctx,
+                size,
+                image.into_vec(),
+                self.sampling,
+                texture_bind_group_layout,
+            );
 
-        let (_texture, bind_group) =
-            create_texture(ctx, size, image.into_vec(), texture_bind_group_layout);
+            textures.push(AtlasTexture {
+                bind_group,
+                image_texture: Some(texture),
+                render_target: None,
+            });
+        }
+
+        for (k, source) in self.to_load.iter().enumerate() {
+            let SpriteSheetSource::RenderTarget(size) = source else {
+                continue;
+            };
+            let (render_target, bind_group) = create_render_target(
+                ctx,
+                *size,
+                self.sampling,
+                texture_bind_group_layout,
+                sample_count,
+            );
+            sheets[k] = Some(SpriteSheet {
+                size_px: *size,
+                sprite_size_px: *size,
+                tex_coords: Vector2 { x: 0., y: 0. },
+                tex_dims: Vector2 { x: 1., y: 1. },
+                texture: textures.len(),
+            });
+            textures.push(AtlasTexture {
+                bind_group,
+                image_texture: None,
+                render_target: Some(render_target),
+            });
+        }
+
+        let fonts = self
+            .fonts_to_load
+            .iter()
+            .map(|font_data| {
+                let (glyph_atlas, bind_group) = GlyphAtlas::new(
+                    ctx,
+                    font_data,
+                    textures.len(),
+                    self.sampling,
+                    texture_bind_group_layout,
+                )
+                .map_err(|e| error!("{e}, its text is not drawn"))
+                .ok()?;
+                textures.push(AtlasTexture {
+                    bind_group,
+                    image_texture: None,
+                    render_target: None,
+                });
+                Some(glyph_atlas)
+            })
+            .collect();
 
-        Atlas { sheets, bind_group }
+        Atlas {
+            sheets,
+            assets: HashMap::new(),
+            textures,
+            fonts,
+            white_texel,
+            placeholder: placeholder.unwrap(),
+        }
     }
 }
 
+fn create_render_target(
+    ctx: &GraphicsCtx,
+    size: Vector2<u32>,
+    sampling: TextureSampling,
+    texture_bind_group_layout: &BindGroupLayout,
+    sample_count: u32,
+) -> (RenderTarget, BindGroup) {
+    let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ctx.surface_texture_format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        label: Some("2d_render_target"),
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(ctx, &view, sampling, texture_bind_group_layout);
+    let (depth_texture, depth_view, _) =
+        create_depth_texture(&ctx.device, size.into(), sample_count);
+    let msaa_view = create_msaa_texture(ctx, size.into(), sample_count);
+
+    (
+        RenderTarget {
+            _texture: texture,
+            view,
+            msaa_view,
+            _depth_texture: depth_texture,
+            depth_view,
+            size,
+        },
+        bind_group,
+    )
+}
+
 fn create_texture(
     ctx: &GraphicsCtx,
     size: Vector2<u32>,
     image: Vec<u8>,
+    sampling: TextureSampling,
     texture_bind_group_layout: &BindGroupLayout,
 ) -> (Texture, BindGroup) {
     let texture_size = wgpu::Extent3d {
@@ -170,39 +956,82 @@ fn create_texture(
         depth_or_array_layers: 1,
     };
 
+    let (mip_level_count, usage) = match sampling.mipmap_filter {
+        Some(_) => (
+            mip_level_count(size),
+            wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        ),
+        None => (
+            1,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        ),
+    };
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+
     let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
         size: texture_size,
-        mip_level_count: 1,
+        mip_level_count,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Rgba8UnormSrgb,
-        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        format,
+        usage,
         label: Some("2d_texture"),
         view_formats: &[],
     });
 
     let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(ctx, &view, sampling, texture_bind_group_layout);
+
+    ctx.queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        image.as_ref(),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * size.x),
+            rows_per_image: Some(size.y),
+        },
+        texture_size,
+    );
+
+    generate_mipmaps(ctx, &texture, format);
+
+    (texture, bind_group)
+}
+
+pub(crate) fn create_bind_group(
+    ctx: &GraphicsCtx,
+    view: &TextureView,
+    sampling: TextureSampling,
+    texture_bind_group_layout: &BindGroupLayout,
+) -> BindGroup {
     let sampler = ctx.device.create_sampler(&wgpu::SamplerDescriptor {
         label: Some("2d_sampler"),
         address_mode_u: wgpu::AddressMode::ClampToEdge,
         address_mode_v: wgpu::AddressMode::ClampToEdge,
         address_mode_w: wgpu::AddressMode::ClampToEdge,
-        mag_filter: wgpu::FilterMode::Nearest,
-        min_filter: wgpu::FilterMode::Nearest,
-        mipmap_filter: wgpu::FilterMode::Nearest,
+        mag_filter: sampling.filter_mode,
+        min_filter: sampling.filter_mode,
+        mipmap_filter: sampling.mipmap_filter.unwrap_or(FilterMode::Nearest),
         lod_min_clamp: 0.0,
-        lod_max_clamp: 1.0,
+        lod_max_clamp: 32.0,
         compare: None,
         anisotropy_clamp: 1,
         border_color: None,
     });
 
-    let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+    ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
         layout: texture_bind_group_layout,
         entries: &[
             wgpu::BindGroupEntry {
                 binding: 0,
-                resource: wgpu::BindingResource::TextureView(&view),
+                resource: wgpu::BindingResource::TextureView(view),
             },
             wgpu::BindGroupEntry {
                 binding: 1,
@@ -210,23 +1039,5 @@ fn create_texture(
             },
         ],
         label: Some("2d_texture_bind_group"),
-    });
-
-    ctx.queue.write_texture(
-        wgpu::ImageCopyTexture {
-            texture: &texture,
-            mip_level: 0,
-            origin: wgpu::Origin3d::ZERO,
-            aspect: wgpu::TextureAspect::All,
-        },
-        image.as_ref(),
-        wgpu::ImageDataLayout {
-            offset: 0,
-            bytes_per_row: Some(4 * size.x),
-            rows_per_image: Some(size.y),
-        },
-        texture_size,
-    );
-
-    (texture, bind_group)
+    })
 }
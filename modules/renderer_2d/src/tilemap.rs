@@ -0,0 +1,222 @@
+use std::{fmt, ops::Range};
+
+use cgmath::{Matrix3, Vector2};
+
+use crate::{
+    renderer::SpriteRenderer,
+    shapes::Rect,
+    texture::{DrawParams, Sprite},
+};
+
+/// Grid of tile sprites, tile `(0, 0)` having its bottom left corner at `position`
+/// and rows going along y.
+#[derive(Clone)]
+pub struct Tilemap {
+    width: u32,
+    height: u32,
+    tiles: Vec<Option<Sprite>>,
+    pub position: Vector2<f32>,
+    pub tile_size: Vector2<f32>,
+}
+
+/// Returned when the tiles of a [`Tilemap`] are too many to be allocated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TilemapSizeError {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl fmt::Display for TilemapSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a {}x{} tilemap has too many tiles",
+            self.width, self.height
+        )
+    }
+}
+
+impl std::error::Error for TilemapSizeError {}
+
+impl Tilemap {
+    /// Empty tilemap of `width * height` tiles
+    pub fn new(width: u32, height: u32, tile_size: Vector2<f32>) -> Result<Self, TilemapSizeError> {
+        // Allocations are limited to `isize::MAX` bytes
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .filter(|len| {
+                len.checked_mul(std::mem::size_of::<Option<Sprite>>())
+                    .is_some_and(|bytes| bytes <= isize::MAX as usize)
+            })
+            .ok_or(TilemapSizeError { width, height })?;
+        Ok(Self {
+            width,
+            height,
+            tiles: vec![None; len],
+            position: Vector2::new(0., 0.),
+            tile_size,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Panics if `(x, y)` is outside of the tilemap.
+    pub fn get(&self, x: u32, y: u32) -> Option<&Sprite> {
+        self.tiles[self.index(x, y)].as_ref()
+    }
+
+    /// Sets the sprite of the tile at `(x, y)`, `None` leaving it empty.
+    ///
+    /// Panics if `(x, y)` is outside of the tilemap.
+    pub fn set(&mut self, x: u32, y: u32, tile: Option<Sprite>) {
+        let index = self.index(x, y);
+        self.tiles[index] = tile;
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "Tile ({x}, {y}) is outside of the {}x{} tilemap!",
+            self.width,
+            self.height
+        );
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Columns and rows of the tiles overlapping `bounds`
+    pub fn visible_range(&self, bounds: Rect) -> (Range<u32>, Range<u32>) {
+        let min = (bounds.position - self.position).zip(self.tile_size, |a, b| a / b);
+        let max = (bounds.position + bounds.size - self.position).zip(self.tile_size, |a, b| a / b);
+        let range = |min: f32, max: f32, len: u32| {
+            (min.floor().clamp(0., len as f32) as u32)..(max.ceil().clamp(0., len as f32) as u32)
+        };
+        (
+            range(min.x, max.x, self.width),
+            range(min.y, max.y, self.height),
+        )
+    }
+
+    /// Tiles overlapping `bounds` with their transform
+    pub(crate) fn visible_tiles(
+        &self,
+        bounds: Rect,
+    ) -> impl Iterator<Item = (&Sprite, Matrix3<f32>)> {
+        let (columns, rows) = self.visible_range(bounds);
+        rows.flat_map(move |y| columns.clone().map(move |x| (x, y)))
+            .filter_map(|(x, y)| {
+                let tile = self.get(x, y)?;
+                let offset = Vector2::new(x as f32, y as f32).zip(self.tile_size, |a, b| a * b);
+                let transform = Matrix3::from_translation(self.position + offset)
+                    * Matrix3::from_nonuniform_scale(self.tile_size.x, self.tile_size.y);
+                Some((tile, transform))
+            })
+    }
+
+    /// Draws the tiles overlapping `camera_bounds`, such as [`SpriteRenderer::camera_bounds`].
+    ///
+    /// `params.transform` is applied on top of the tilemap layout, `camera_bounds` must be in
+    /// the space before it.
+    pub fn draw_visible(
+        &self,
+        renderer: &mut SpriteRenderer,
+        camera_bounds: Rect,
+        params: &DrawParams,
+    ) {
+        for (tile, transform) in self.visible_tiles(camera_bounds) {
+            renderer.draw(tile.clone(), with_transform(params, transform));
+        }
+    }
+}
+
+pub(crate) fn with_transform(params: &DrawParams, transform: Matrix3<f32>) -> DrawParams {
+    DrawParams {
+        transform: params.transform * transform,
+        ..params.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::{SheetId, SpriteSheetHandle};
+
+    fn tile(x: u32) -> Sprite {
+        Sprite::new(
+            SpriteSheetHandle(SheetId::Index(0)),
+            Vector2::new(x, 0),
+            Vector2::new(1, 1),
+        )
+    }
+
+    #[test]
+    fn tiles_are_stored_by_position() {
+        let mut tilemap = Tilemap::new(3, 2, Vector2::new(1., 1.)).unwrap();
+        tilemap.set(2, 1, Some(tile(7)));
+
+        assert_eq!(tilemap.get(2, 1).unwrap().position, Vector2::new(7, 0));
+        assert!(tilemap.get(1, 1).is_none());
+        assert!(tilemap.get(0, 0).is_none());
+        assert_eq!(tilemap.index(2, 1), 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn tile_outside_is_rejected() {
+        Tilemap::new(3, 2, Vector2::new(1., 1.)).unwrap().get(3, 0);
+    }
+
+    #[test]
+    fn too_many_tiles_are_rejected() {
+        let Err(e) = Tilemap::new(u32::MAX, u32::MAX, Vector2::new(1., 1.)) else {
+            panic!("An overflowing tilemap was created");
+        };
+        assert_eq!(
+            e,
+            TilemapSizeError {
+                width: u32::MAX,
+                height: u32::MAX
+            }
+        );
+    }
+
+    #[test]
+    fn visible_range_covers_the_overlapping_tiles() {
+        let mut tilemap = Tilemap::new(10, 10, Vector2::new(16., 8.)).unwrap();
+        tilemap.position = Vector2::new(-32., 0.);
+
+        let bounds = Rect::new(Vector2::new(-20., 4.), Vector2::new(40., 8.));
+        assert_eq!(tilemap.visible_range(bounds), (0..4, 0..2));
+        // Bounds outside of the tilemap are clamped to it
+        let bounds = Rect::new(Vector2::new(-100., -100.), Vector2::new(1000., 50.));
+        assert_eq!(tilemap.visible_range(bounds), (0..10, 0..0));
+    }
+
+    #[test]
+    fn visible_tiles_are_placed_in_world_units() {
+        let mut tilemap = Tilemap::new(4, 4, Vector2::new(16., 8.)).unwrap();
+        tilemap.position = Vector2::new(100., 50.);
+        tilemap.set(1, 2, Some(tile(0)));
+        tilemap.set(3, 3, Some(tile(1)));
+
+        let bounds = Rect::new(Vector2::new(100., 50.), Vector2::new(32., 24.));
+        let tiles = tilemap.visible_tiles(bounds).collect::<Vec<_>>();
+        assert_eq!(tiles.len(), 1);
+        let (sprite, transform) = &tiles[0];
+        assert_eq!(sprite.position, Vector2::new(0, 0));
+        // Bottom left and top right corners of the unit quad
+        assert_eq!(
+            (transform * cgmath::Vector3::new(0., 0., 1.)).truncate(),
+            Vector2::new(116., 66.)
+        );
+        assert_eq!(
+            (transform * cgmath::Vector3::new(1., 1., 1.)).truncate(),
+            Vector2::new(132., 74.)
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::{Arc, Mutex};
+
+use rgine_graphics::ctx::GraphicsCtx;
+use wgpu::*;
+
+/// Render passes timed per frame, the following ones are not timed
+const MAX_TIMED_PASSES: u32 = 16;
+
+/// Measures the GPU duration of the render passes of a frame using timestamp queries.
+///
+/// Timestamps are read back asynchronously, no pass is timed while waiting for them.
+pub(crate) struct GpuTimer {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    /// Passes timed during the current frame
+    timed_passes: u32,
+    /// Passes whose timestamps are being read back
+    pending_passes: u32,
+    /// Set once the readback buffer is mapped, to whether the mapping succeeded
+    mapped: Arc<Mutex<Option<bool>>>,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+}
+
+impl GpuTimer {
+    /// Returns `None` if timestamp queries are not supported by the device
+    pub(crate) fn new(ctx: &GraphicsCtx) -> Option<Self> {
+        if !ctx.device.features().contains(Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let count = MAX_TIMED_PASSES * 2;
+        let size = count as u64 * size_of::<u64>() as u64;
+        Some(Self {
+            query_set: ctx.device.create_query_set(&QuerySetDescriptor {
+                label: Some("2d_timestamp_queries"),
+                ty: QueryType::Timestamp,
+                count,
+            }),
+            resolve_buffer: ctx.device.create_buffer(&BufferDescriptor {
+                label: Some("2d_timestamp_resolve_buffer"),
+                size,
+                usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: ctx.device.create_buffer(&BufferDescriptor {
+                label: Some("2d_timestamp_readback_buffer"),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            timed_passes: 0,
+            pending_passes: 0,
+            mapped: Arc::new(Mutex::new(None)),
+            period: ctx.queue.get_timestamp_period(),
+        })
+    }
+
+    /// Timestamps to write around the next pass, `None` if it can't be timed
+    pub(crate) fn pass_timestamps(&mut self) -> Option<RenderPassTimestampWrites<'_>> {
+        if self.pending_passes > 0 || self.timed_passes == MAX_TIMED_PASSES {
+            return None;
+        }
+        let index = self.timed_passes * 2;
+        self.timed_passes += 1;
+        Some(RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    /// Starts reading back the timestamps of the passes timed during the frame
+    pub(crate) fn end_frame(&mut self, ctx: &GraphicsCtx) {
+        if self.timed_passes == 0 {
+            return;
+        }
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("2d_timestamp_resolve_encoder"),
+            });
+        let count = self.timed_passes * 2;
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            count as u64 * size_of::<u64>() as u64,
+        );
+        ctx.queue.submit(std::iter::once(encoder.finish()));
+
+        let mapped = self.mapped.clone();
+        self.readback_buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                *mapped.lock().unwrap() = Some(result.is_ok());
+            });
+        self.pending_passes = self.timed_passes;
+        self.timed_passes = 0;
+    }
+
+    /// Total GPU duration in milliseconds of the passes of the last frame read back, if any
+    pub(crate) fn read_ms(&mut self) -> Option<f32> {
+        if self.pending_passes == 0 {
+            return None;
+        }
+        match self.mapped.lock().unwrap().take() {
+            None => return None,
+            Some(false) => {
+                self.pending_passes = 0;
+                return None;
+            }
+            Some(true) => (),
+        }
+        let data = self.readback_buffer.slice(..).get_mapped_range();
+        let timestamps: &[u64] = bytemuck::cast_slice(&data);
+        let ticks = timestamps[..self.pending_passes as usize * 2]
+            .chunks_exact(2)
+            .map(|pass| pass[1].saturating_sub(pass[0]))
+            .sum::<u64>();
+        drop(data);
+        self.readback_buffer.unmap();
+        self.pending_passes = 0;
+        Some(ticks as f32 * self.period / 1_000_000.)
+    }
+}
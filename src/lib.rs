@@ -17,8 +17,36 @@ pub mod prelude {
 
     #[cfg(feature = "graphics")]
     pub use crate::{
-        graphics::color::Color3,
-        platform::window::{WindowPlatformConfig, WindowPlatformEngineExt},
+        graphics::{color::Color3, RenderWindowEvent, SetPresentModeEvent},
+        platform::window::{
+            gesture::{
+                DoubleClickEvent, DragEndedEvent, DragEvent, GestureConfig, SetGestureConfigEvent,
+            },
+            headless::HeadlessEngineExt,
+            input::{
+                CursorEnteredEvent, CursorLeftEvent, CursorMovedEvent, ElementState,
+                FileDroppedEvent, FileHoverCancelledEvent, FileHoveredEvent, KeyCode,
+                KeyboardInputEvent, MouseButton, MouseButtonEvent, MouseWheelEvent, ScrollUnit,
+                TextInputEvent, TextPreeditEvent,
+            },
+            module::{
+                ScaleFactorChangedEvent, SetWindowModeEvent, WindowClosedEvent,
+                WindowEventQueueExt, WindowFocusedEvent, WindowMinimizedEvent, WindowOpenedEvent,
+                WindowResizedEvent,
+            },
+            CursorGrabMode, CursorIcon, MonitorInfo, PresentMode, ResumedEvent, SuspendedEvent,
+            WindowId, WindowMode, WindowPlatformConfig, WindowPlatformEngineExt,
+        },
+    };
+
+    #[cfg(feature = "clipboard")]
+    pub use crate::platform::window::clipboard::{
+        ClipboardEventQueueExt, ClipboardModule, SetClipboardTextEvent,
+    };
+
+    #[cfg(feature = "gamepad")]
+    pub use crate::platform::window::gamepad::{
+        GamepadAxis, GamepadAxisEvent, GamepadButton, GamepadButtonEvent, GamepadConnectionEvent,
     };
 
     #[cfg(feature = "2d")]